@@ -0,0 +1,60 @@
+-- migrations/0006_dashboard_notifications.sql
+--
+-- Publishes issue/return/lost/overdue and stock changes on the
+-- 'toolport_events' channel so every backend instance can push them to
+-- connected dashboards. NOTIFY is delivered on commit only.
+
+CREATE OR REPLACE FUNCTION notify_delegation_event()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+DECLARE
+    event_kind TEXT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        event_kind := 'issued';
+    ELSIF NEW.status IS DISTINCT FROM OLD.status THEN
+        event_kind := CASE NEW.status
+            WHEN 'Returned' THEN 'returned'
+            WHEN 'Lost'     THEN 'lost'
+            WHEN 'Overdue'  THEN 'overdue'
+            ELSE 'issued'
+        END;
+    ELSE
+        RETURN NEW;
+    END IF;
+
+    PERFORM pg_notify('toolport_events', json_build_object(
+        'kind',         event_kind,
+        'delegationId', NEW.id,
+        'toolId',       NEW.tool_id,
+        'studentId',    NEW.student_id
+    )::text);
+    RETURN NEW;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS delegations_notify ON delegations;
+CREATE TRIGGER delegations_notify
+    AFTER INSERT OR UPDATE OF status ON delegations
+    FOR EACH ROW EXECUTE FUNCTION notify_delegation_event();
+
+CREATE OR REPLACE FUNCTION notify_stock_event()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+DECLARE
+    r tools%ROWTYPE;
+BEGIN
+    IF TG_OP = 'DELETE' THEN r := OLD; ELSE r := NEW; END IF;
+
+    PERFORM pg_notify('toolport_events', json_build_object(
+        'kind',      'stock_changed',
+        'toolId',    r.id,
+        'quantity',  CASE WHEN TG_OP = 'DELETE' THEN 0 ELSE r.quantity END,
+        'issuedQty', CASE WHEN TG_OP = 'DELETE' THEN 0 ELSE r.issued_qty END
+    )::text);
+    RETURN r;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS tools_notify ON tools;
+CREATE TRIGGER tools_notify
+    AFTER INSERT OR DELETE OR UPDATE OF quantity, issued_qty ON tools
+    FOR EACH ROW EXECUTE FUNCTION notify_stock_event();
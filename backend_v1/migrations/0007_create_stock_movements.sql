@@ -0,0 +1,68 @@
+-- migrations/0007_create_stock_movements.sql
+
+DO $$ BEGIN
+    CREATE TYPE stock_movement_kind AS ENUM (
+        'Received',
+        'Issued Consumed',
+        'Written Off',
+        'Lost',
+        'Found',
+        'Correction',
+        'Transfer'
+    );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS stock_movements (
+    id              SERIAL              PRIMARY KEY,
+    tool_id         INTEGER             NOT NULL REFERENCES tools(id) ON DELETE CASCADE,
+    kind            stock_movement_kind NOT NULL,
+    quantity_delta  INTEGER             NOT NULL CHECK (quantity_delta <> 0),
+    quantity_after  INTEGER             NOT NULL CHECK (quantity_after >= 0),
+    reason          TEXT                NOT NULL,
+    actor           VARCHAR(60)         NOT NULL,
+    delegation_id   INTEGER             REFERENCES delegations(id),
+    created_at      TIMESTAMPTZ         NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_stock_movements_tool ON stock_movements(tool_id, created_at);
+
+-- The ledger is append-only. Rows may only disappear together with their
+-- tool (ON DELETE CASCADE), by which point the parent row is already gone.
+CREATE OR REPLACE FUNCTION stock_movements_immutable()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    IF TG_OP = 'DELETE' AND NOT EXISTS (SELECT 1 FROM tools WHERE id = OLD.tool_id) THEN
+        RETURN OLD;
+    END IF;
+    RAISE EXCEPTION 'stock_movements is append-only';
+END;
+$$;
+
+DROP TRIGGER IF EXISTS stock_movements_no_change ON stock_movements;
+CREATE TRIGGER stock_movements_no_change
+    BEFORE UPDATE OR DELETE ON stock_movements
+    FOR EACH ROW EXECUTE FUNCTION stock_movements_immutable();
+
+-- Lost reusable units used to stay counted in issued_qty forever. Write them
+-- out of stock so the ledger opens on a true balance.
+WITH lost AS (
+    SELECT d.tool_id, SUM(d.quantity) AS qty
+    FROM delegations d JOIN tools t ON t.id = d.tool_id
+    WHERE d.status = 'Lost'
+      AND d.resolution IS DISTINCT FROM 'Recovered'
+      AND NOT t.is_consumable
+    GROUP BY d.tool_id
+)
+UPDATE tools t
+SET issued_qty = GREATEST(0, t.issued_qty - lost.qty),
+    quantity   = GREATEST(0, t.quantity   - lost.qty)
+FROM lost
+WHERE t.id = lost.tool_id;
+
+-- Opening balance for every existing stock line
+INSERT INTO stock_movements (tool_id, kind, quantity_delta, quantity_after, reason, actor)
+SELECT id, 'Correction', quantity, quantity, 'Opening balance', 'system'
+FROM tools
+WHERE quantity > 0
+  AND NOT EXISTS (SELECT 1 FROM stock_movements m WHERE m.tool_id = tools.id);
@@ -0,0 +1,35 @@
+-- migrations/0008_create_stocktakes.sql
+
+DO $$ BEGIN
+    CREATE TYPE stocktake_status AS ENUM ('Open', 'Approved', 'Cancelled');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS stocktakes (
+    id              SERIAL            PRIMARY KEY,
+    lab_id          INTEGER           NOT NULL REFERENCES labs(id),
+    status          stocktake_status  NOT NULL DEFAULT 'Open',
+    notes           TEXT,
+    started_by      VARCHAR(60)       NOT NULL,
+    started_at      TIMESTAMPTZ       NOT NULL DEFAULT NOW(),
+    approved_by     VARCHAR(60),
+    approved_at     TIMESTAMPTZ
+);
+
+-- One open count per lab at a time
+CREATE UNIQUE INDEX IF NOT EXISTS idx_stocktakes_open_lab
+    ON stocktakes(lab_id) WHERE status = 'Open';
+
+-- expected_qty is the on-shelf snapshot (quantity - issued_qty - held_qty)
+-- taken when the session starts and refreshed each time the line is counted;
+-- variance is counted_qty - expected_qty.
+CREATE TABLE IF NOT EXISTS stocktake_lines (
+    id              SERIAL        PRIMARY KEY,
+    stocktake_id    INTEGER       NOT NULL REFERENCES stocktakes(id) ON DELETE CASCADE,
+    tool_id         INTEGER       NOT NULL REFERENCES tools(id) ON DELETE CASCADE,
+    expected_qty    INTEGER       NOT NULL,
+    counted_qty     INTEGER       CHECK (counted_qty >= 0),
+    counted_by      VARCHAR(60),
+    counted_at      TIMESTAMPTZ,
+    UNIQUE (stocktake_id, tool_id)
+);
@@ -0,0 +1,28 @@
+-- migrations/0009_create_stock_transfers.sql
+
+DO $$ BEGIN
+    CREATE TYPE transfer_status AS ENUM ('In Transit', 'Received', 'Cancelled');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+-- Units leave the source stock line on dispatch and only join the
+-- destination line on receipt; while 'In Transit' they count in neither lab.
+CREATE TABLE IF NOT EXISTS stock_transfers (
+    id              SERIAL            PRIMARY KEY,
+    source_tool_id  INTEGER           NOT NULL REFERENCES tools(id),
+    dest_tool_id    INTEGER           REFERENCES tools(id),
+    from_lab_id     INTEGER           NOT NULL REFERENCES labs(id),
+    to_lab_id       INTEGER           NOT NULL REFERENCES labs(id),
+    quantity        INTEGER           NOT NULL CHECK (quantity > 0),
+    status          transfer_status   NOT NULL DEFAULT 'In Transit',
+    notes           TEXT,
+    dispatched_by   VARCHAR(60)       NOT NULL,
+    dispatched_at   TIMESTAMPTZ       NOT NULL DEFAULT NOW(),
+    received_by     VARCHAR(60),
+    received_at     TIMESTAMPTZ,
+    CHECK (from_lab_id <> to_lab_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transfers_from   ON stock_transfers(from_lab_id);
+CREATE INDEX IF NOT EXISTS idx_transfers_to     ON stock_transfers(to_lab_id);
+CREATE INDEX IF NOT EXISTS idx_transfers_status ON stock_transfers(status);
@@ -0,0 +1,89 @@
+-- migrations/0010_create_catalogue_items.sql
+--
+-- Splits the tool model (catalogue item) from per-lab stock holdings. Each
+-- `tools` row is now one lab's holding of a catalogue item. The descriptive
+-- columns on `tools` are kept as a cache of the item, synced by trigger, so
+-- existing joins keep working.
+
+CREATE TABLE IF NOT EXISTS catalogue_items (
+    id              SERIAL          PRIMARY KEY,
+    name            VARCHAR(150)    NOT NULL,
+    category        tool_category   NOT NULL,
+    subcategory     VARCHAR(100),
+    unit            VARCHAR(30)     NOT NULL DEFAULT 'pcs',
+    description     TEXT,
+    is_consumable   BOOLEAN         NOT NULL DEFAULT FALSE,
+    consumable_type VARCHAR(80),
+    created_at      TIMESTAMPTZ     NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ     NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_catalogue_items_name
+    ON catalogue_items(LOWER(name), category);
+
+DROP TRIGGER IF EXISTS catalogue_items_updated_at ON catalogue_items;
+CREATE TRIGGER catalogue_items_updated_at
+    BEFORE UPDATE ON catalogue_items
+    FOR EACH ROW EXECUTE FUNCTION touch_updated_at();
+
+-- One catalogue item per distinct (name, category) already in stock
+INSERT INTO catalogue_items
+    (name, category, subcategory, unit, description, is_consumable, consumable_type)
+SELECT DISTINCT ON (LOWER(name), category)
+       name, category, subcategory, unit, description, is_consumable, consumable_type
+FROM tools
+ORDER BY LOWER(name), category, id
+ON CONFLICT DO NOTHING;
+
+ALTER TABLE tools ADD COLUMN IF NOT EXISTS catalogue_item_id INTEGER REFERENCES catalogue_items(id);
+
+UPDATE tools t SET catalogue_item_id = c.id
+FROM catalogue_items c
+WHERE t.catalogue_item_id IS NULL
+  AND LOWER(c.name) = LOWER(t.name) AND c.category = t.category;
+
+ALTER TABLE tools ALTER COLUMN catalogue_item_id SET NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_tools_catalogue_item ON tools(catalogue_item_id);
+
+-- A lab holds an item on one stock line, trashed lines included. Lines that
+-- already duplicate each other must be merged by hand before this applies.
+DO $$
+DECLARE
+    dups TEXT;
+BEGIN
+    SELECT STRING_AGG(FORMAT('item %s in lab %s (tools %s)', catalogue_item_id, lab_id, ids), '; ')
+    INTO dups
+    FROM (
+        SELECT catalogue_item_id, lab_id, STRING_AGG(id::TEXT, ', ' ORDER BY id) AS ids
+        FROM tools WHERE lab_id IS NOT NULL
+        GROUP BY catalogue_item_id, lab_id HAVING COUNT(*) > 1
+    ) d;
+    IF dups IS NOT NULL THEN
+        RAISE EXCEPTION 'Merge duplicate stock lines before migrating: %', dups;
+    END IF;
+END;
+$$;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tools_catalogue_item_lab ON tools(catalogue_item_id, lab_id);
+
+-- Keep the cached descriptive columns on every holding in step with the item
+CREATE OR REPLACE FUNCTION sync_catalogue_item_to_tools()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    UPDATE tools SET
+        name            = NEW.name,
+        category        = NEW.category,
+        subcategory     = NEW.subcategory,
+        unit            = NEW.unit,
+        description     = NEW.description,
+        is_consumable   = NEW.is_consumable,
+        consumable_type = NEW.consumable_type
+    WHERE catalogue_item_id = NEW.id;
+    RETURN NEW;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS catalogue_items_sync ON catalogue_items;
+CREATE TRIGGER catalogue_items_sync
+    AFTER UPDATE ON catalogue_items
+    FOR EACH ROW EXECUTE FUNCTION sync_catalogue_item_to_tools();
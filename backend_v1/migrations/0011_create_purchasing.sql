@@ -0,0 +1,45 @@
+-- migrations/0011_create_purchasing.sql
+
+CREATE TABLE IF NOT EXISTS suppliers (
+    id              SERIAL        PRIMARY KEY,
+    name            VARCHAR(150)  NOT NULL UNIQUE,
+    contact_name    VARCHAR(120),
+    email           VARCHAR(180),
+    phone           VARCHAR(40),
+    address         TEXT,
+    notes           TEXT,
+    created_at      TIMESTAMPTZ   NOT NULL DEFAULT NOW()
+);
+
+DO $$ BEGIN
+    CREATE TYPE po_status AS ENUM ('Ordered', 'Partially Received', 'Received', 'Cancelled');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS purchase_orders (
+    id                  SERIAL        PRIMARY KEY,
+    supplier_id         INTEGER       NOT NULL REFERENCES suppliers(id),
+    status              po_status     NOT NULL DEFAULT 'Ordered',
+    order_date          DATE          NOT NULL DEFAULT CURRENT_DATE,
+    expected_delivery   DATE,
+    notes               TEXT,
+    created_by          VARCHAR(60)   NOT NULL,
+    created_at          TIMESTAMPTZ   NOT NULL DEFAULT NOW(),
+    closed_at           TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_purchase_orders_status   ON purchase_orders(status);
+CREATE INDEX IF NOT EXISTS idx_purchase_orders_supplier ON purchase_orders(supplier_id);
+
+-- Costs are stored in the smallest currency unit (cents)
+CREATE TABLE IF NOT EXISTS purchase_order_lines (
+    id                  SERIAL    PRIMARY KEY,
+    purchase_order_id   INTEGER   NOT NULL REFERENCES purchase_orders(id) ON DELETE CASCADE,
+    tool_id             INTEGER   NOT NULL REFERENCES tools(id),
+    quantity_ordered    INTEGER   NOT NULL CHECK (quantity_ordered > 0),
+    quantity_received   INTEGER   NOT NULL DEFAULT 0 CHECK (quantity_received >= 0),
+    unit_cost_cents     BIGINT    NOT NULL DEFAULT 0 CHECK (unit_cost_cents >= 0),
+    CHECK (quantity_received <= quantity_ordered)
+);
+
+CREATE INDEX IF NOT EXISTS idx_po_lines_tool ON purchase_order_lines(tool_id);
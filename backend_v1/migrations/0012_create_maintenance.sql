@@ -0,0 +1,60 @@
+-- migrations/0012_create_maintenance.sql
+
+ALTER TYPE tool_status ADD VALUE IF NOT EXISTS 'Under Maintenance';
+
+-- Units out for calibration/servicing stay in `quantity` but cannot be issued.
+-- held_qty is everything unavailable for reasons other than being on loan;
+-- available = quantity - issued_qty - held_qty.
+ALTER TABLE tools ADD COLUMN IF NOT EXISTS maintenance_qty INTEGER NOT NULL DEFAULT 0
+    CHECK (maintenance_qty >= 0);
+ALTER TABLE tools ADD COLUMN IF NOT EXISTS held_qty INTEGER
+    GENERATED ALWAYS AS (maintenance_qty) STORED;
+
+DO $$ BEGIN
+    CREATE TYPE maintenance_kind AS ENUM ('Calibration', 'Service', 'Inspection');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE maintenance_outcome AS ENUM ('Passed', 'Adjusted', 'Repaired', 'Failed', 'Retired');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+-- A plan is due every interval_days since it was last performed, or once the
+-- tool has been lent interval_loans times since then, whichever comes first.
+CREATE TABLE IF NOT EXISTS maintenance_plans (
+    id                  SERIAL            PRIMARY KEY,
+    tool_id             INTEGER           NOT NULL REFERENCES tools(id) ON DELETE CASCADE,
+    kind                maintenance_kind  NOT NULL,
+    interval_days       INTEGER           CHECK (interval_days > 0),
+    interval_loans      INTEGER           CHECK (interval_loans > 0),
+    last_performed_at   TIMESTAMPTZ,
+    loans_since         INTEGER           NOT NULL DEFAULT 0,
+    due_since           TIMESTAMPTZ,
+    is_active           BOOLEAN           NOT NULL DEFAULT TRUE,
+    notes               TEXT,
+    created_at          TIMESTAMPTZ       NOT NULL DEFAULT NOW(),
+    CHECK (interval_days IS NOT NULL OR interval_loans IS NOT NULL)
+);
+
+CREATE INDEX IF NOT EXISTS idx_maintenance_plans_tool ON maintenance_plans(tool_id);
+CREATE INDEX IF NOT EXISTS idx_maintenance_plans_due  ON maintenance_plans(due_since) WHERE due_since IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS maintenance_records (
+    id              SERIAL                PRIMARY KEY,
+    tool_id         INTEGER               NOT NULL REFERENCES tools(id) ON DELETE CASCADE,
+    plan_id         INTEGER               REFERENCES maintenance_plans(id) ON DELETE SET NULL,
+    kind            maintenance_kind      NOT NULL,
+    quantity        INTEGER               NOT NULL CHECK (quantity > 0),
+    technician      VARCHAR(120)          NOT NULL,
+    started_by      VARCHAR(60)           NOT NULL,
+    started_at      TIMESTAMPTZ           NOT NULL DEFAULT NOW(),
+    completed_by    VARCHAR(60),
+    completed_at    TIMESTAMPTZ,
+    outcome         maintenance_outcome,
+    cost_cents      BIGINT                CHECK (cost_cents >= 0),
+    notes           TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_maintenance_records_tool ON maintenance_records(tool_id);
+CREATE INDEX IF NOT EXISTS idx_maintenance_records_open ON maintenance_records(tool_id) WHERE completed_at IS NULL;
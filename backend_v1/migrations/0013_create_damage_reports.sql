@@ -0,0 +1,57 @@
+-- migrations/0013_create_damage_reports.sql
+
+-- Damaged returns are quarantined until repaired or written off. Quarantined
+-- units join maintenance units in held_qty so neither can be issued; a line
+-- held only in quarantine shows 'Quarantined'.
+ALTER TYPE tool_status ADD VALUE IF NOT EXISTS 'Quarantined';
+ALTER TABLE tools ADD COLUMN IF NOT EXISTS quarantined_qty INTEGER NOT NULL DEFAULT 0
+    CHECK (quarantined_qty >= 0);
+ALTER TABLE tools DROP COLUMN IF EXISTS held_qty;
+ALTER TABLE tools ADD COLUMN held_qty INTEGER
+    GENERATED ALWAYS AS (maintenance_qty + quarantined_qty) STORED;
+
+DO $$ BEGIN
+    CREATE TYPE damage_status AS ENUM ('Quarantined', 'Repaired', 'Written Off');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS damage_reports (
+    id                  SERIAL          PRIMARY KEY,
+    delegation_id       INTEGER         REFERENCES delegations(id) ON DELETE SET NULL,
+    tool_id             INTEGER         NOT NULL REFERENCES tools(id) ON DELETE CASCADE,
+    student_id          VARCHAR(30)     REFERENCES students(student_id) ON DELETE SET NULL,
+    quantity            INTEGER         NOT NULL CHECK (quantity > 0),
+    description         TEXT            NOT NULL,
+    photo_urls          TEXT[]          NOT NULL DEFAULT '{}',
+    status              damage_status   NOT NULL DEFAULT 'Quarantined',
+    reported_by         VARCHAR(60)     NOT NULL,
+    reported_at         TIMESTAMPTZ     NOT NULL DEFAULT NOW(),
+    resolved_by         VARCHAR(60),
+    resolved_at         TIMESTAMPTZ,
+    resolution_notes    TEXT,
+    repair_cost_cents   BIGINT          CHECK (repair_cost_cents >= 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_damage_reports_tool    ON damage_reports(tool_id);
+CREATE INDEX IF NOT EXISTS idx_damage_reports_student ON damage_reports(student_id);
+CREATE INDEX IF NOT EXISTS idx_damage_reports_open    ON damage_reports(status) WHERE status='Quarantined';
+
+-- Money owed by a student. Damage is the first source; amounts are in cents.
+DO $$ BEGIN
+    CREATE TYPE charge_kind AS ENUM ('Damage');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS student_charges (
+    id                  SERIAL          PRIMARY KEY,
+    student_id          VARCHAR(30)     NOT NULL REFERENCES students(student_id) ON DELETE CASCADE,
+    kind                charge_kind     NOT NULL,
+    amount_cents        BIGINT          NOT NULL CHECK (amount_cents > 0),
+    description         TEXT            NOT NULL,
+    delegation_id       INTEGER         REFERENCES delegations(id) ON DELETE SET NULL,
+    damage_report_id    INTEGER         REFERENCES damage_reports(id) ON DELETE SET NULL,
+    created_by          VARCHAR(60)     NOT NULL,
+    created_at          TIMESTAMPTZ     NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_student_charges_student ON student_charges(student_id);
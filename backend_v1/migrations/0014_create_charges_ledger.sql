@@ -0,0 +1,56 @@
+-- migrations/0014_create_charges_ledger.sql
+
+ALTER TYPE charge_kind ADD VALUE IF NOT EXISTS 'Replacement';
+ALTER TYPE charge_kind ADD VALUE IF NOT EXISTS 'Late Fee';
+ALTER TYPE charge_kind ADD VALUE IF NOT EXISTS 'Other';
+
+-- What a lost unit costs to replace. When unset, the most recent purchase
+-- order price for the item is used.
+ALTER TABLE catalogue_items ADD COLUMN IF NOT EXISTS replacement_cost_cents BIGINT
+    CHECK (replacement_cost_cents >= 0);
+
+-- Payments and waivers are append-only; a charge's balance is derived.
+CREATE TABLE IF NOT EXISTS charge_payments (
+    id                  SERIAL          PRIMARY KEY,
+    charge_id           INTEGER         NOT NULL REFERENCES student_charges(id) ON DELETE CASCADE,
+    amount_cents        BIGINT          NOT NULL CHECK (amount_cents > 0),
+    method              VARCHAR(30),
+    reference           VARCHAR(120),
+    received_by         VARCHAR(60)     NOT NULL,
+    received_at         TIMESTAMPTZ     NOT NULL DEFAULT NOW()
+);
+
+DO $$ BEGIN
+    CREATE TYPE waiver_status AS ENUM ('Pending', 'Approved', 'Rejected');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+-- A waiver is requested by one person and decided by another; only
+-- approved waivers reduce the balance.
+CREATE TABLE IF NOT EXISTS charge_waivers (
+    id                  SERIAL          PRIMARY KEY,
+    charge_id           INTEGER         NOT NULL REFERENCES student_charges(id) ON DELETE CASCADE,
+    amount_cents        BIGINT          NOT NULL CHECK (amount_cents > 0),
+    reason              TEXT            NOT NULL CHECK (BTRIM(reason) <> ''),
+    status              waiver_status   NOT NULL DEFAULT 'Pending',
+    requested_by        VARCHAR(60)     NOT NULL,
+    requested_at        TIMESTAMPTZ     NOT NULL DEFAULT NOW(),
+    decided_by          VARCHAR(60),
+    decided_at          TIMESTAMPTZ,
+    CHECK ((status = 'Pending') = (decided_by IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_charge_payments_charge ON charge_payments(charge_id);
+CREATE INDEX IF NOT EXISTS idx_charge_waivers_charge  ON charge_waivers(charge_id);
+
+CREATE OR REPLACE VIEW charge_balances AS
+SELECT c.id AS charge_id, c.student_id, c.amount_cents,
+       COALESCE(p.paid,0)::BIGINT   AS paid_cents,
+       COALESCE(w.waived,0)::BIGINT AS waived_cents,
+       (c.amount_cents - COALESCE(p.paid,0) - COALESCE(w.waived,0))::BIGINT AS outstanding_cents
+FROM student_charges c
+LEFT JOIN (SELECT charge_id, SUM(amount_cents) AS paid   FROM charge_payments GROUP BY charge_id) p
+       ON p.charge_id=c.id
+LEFT JOIN (SELECT charge_id, SUM(amount_cents) AS waived FROM charge_waivers
+           WHERE status = 'Approved' GROUP BY charge_id) w
+       ON w.charge_id=c.id;
@@ -0,0 +1,26 @@
+-- migrations/0015_create_attachments.sql
+
+DO $$ BEGIN
+    CREATE TYPE attachment_owner AS ENUM ('Tool', 'Damage Report', 'Lost Tool');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+-- File bytes live in the storage backend under storage_key. owner_id is the
+-- tool id, damage report id, or (for lost-tool resolutions) delegation id.
+CREATE TABLE IF NOT EXISTS attachments (
+    id              SERIAL              PRIMARY KEY,
+    owner_type      attachment_owner    NOT NULL,
+    owner_id        INTEGER             NOT NULL,
+    file_name       VARCHAR(255)        NOT NULL,
+    content_type    VARCHAR(100)        NOT NULL,
+    size_bytes      BIGINT              NOT NULL CHECK (size_bytes > 0),
+    storage_key     VARCHAR(255)        NOT NULL UNIQUE,
+    uploaded_by     VARCHAR(60)         NOT NULL,
+    uploaded_at     TIMESTAMPTZ         NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_owner ON attachments(owner_type, owner_id);
+
+-- A payment's receipt is an uploaded file and stays as long as the payment does.
+ALTER TABLE charge_payments ADD COLUMN IF NOT EXISTS receipt_attachment_id INTEGER
+    REFERENCES attachments(id) ON DELETE RESTRICT;
@@ -0,0 +1,9 @@
+-- migrations/0016_tool_images.sql
+
+-- Image attachments get a generated thumbnail; one image per owner can be
+-- the primary photo shown in tool listings.
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS thumbnail_key VARCHAR(255);
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS is_primary BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_attachments_one_primary
+    ON attachments(owner_type, owner_id) WHERE is_primary;
@@ -0,0 +1,47 @@
+-- migrations/0017_create_ban_policy.sql
+
+-- Bans are decided by the application's policy evaluator, not a trigger.
+DROP TRIGGER IF EXISTS student_auto_ban ON students;
+DROP FUNCTION IF EXISTS auto_ban_student();
+
+-- When a delegation was marked lost, so losses can be counted in a window.
+-- Losses from before this column have no known date; they stay NULL and
+-- count only when the policy has no window.
+ALTER TABLE delegations ADD COLUMN IF NOT EXISTS lost_at TIMESTAMPTZ;
+
+-- Single-row policy. A NULL threshold disables that rule.
+CREATE TABLE IF NOT EXISTS ban_policy (
+    id                      INTEGER       PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+    max_lost_tools          INTEGER       CHECK (max_lost_tools > 0),
+    lost_window_months      INTEGER       CHECK (lost_window_months > 0),
+    count_resolved_losses   BOOLEAN       NOT NULL DEFAULT FALSE,
+    max_unpaid_cents        BIGINT        CHECK (max_unpaid_cents >= 0),
+    max_overdue_loans       INTEGER       CHECK (max_overdue_loans > 0),
+    updated_by              VARCHAR(60),
+    updated_at              TIMESTAMPTZ   NOT NULL DEFAULT NOW()
+);
+
+-- Same behaviour as the old trigger: five unresolved losses, all time.
+INSERT INTO ban_policy (id, max_lost_tools) VALUES (1, 5) ON CONFLICT (id) DO NOTHING;
+
+ALTER TABLE students ADD COLUMN IF NOT EXISTS ban_reason TEXT;
+ALTER TABLE students ADD COLUMN IF NOT EXISTS banned_at  TIMESTAMPTZ;
+
+DO $$ BEGIN
+    CREATE TYPE ban_action AS ENUM ('Banned', 'Unbanned');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS student_ban_events (
+    id          SERIAL        PRIMARY KEY,
+    student_id  VARCHAR(30)   NOT NULL REFERENCES students(student_id) ON DELETE CASCADE,
+    action      ban_action    NOT NULL,
+    reason      TEXT          NOT NULL,
+    actor       VARCHAR(60)   NOT NULL,
+    created_at  TIMESTAMPTZ   NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ban_events_student ON student_ban_events(student_id, created_at);
+
+UPDATE students SET ban_reason = 'Lost 5 or more tools', banned_at = NOW()
+WHERE account_status='Banned' AND ban_reason IS NULL;
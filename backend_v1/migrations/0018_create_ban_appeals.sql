@@ -0,0 +1,34 @@
+-- migrations/0018_create_ban_appeals.sql
+
+-- Time-limited bans imposed by staff; jobs lift them at suspended_until.
+ALTER TYPE account_status ADD VALUE IF NOT EXISTS 'Suspended';
+ALTER TYPE ban_action     ADD VALUE IF NOT EXISTS 'Suspended';
+
+ALTER TABLE students ADD COLUMN IF NOT EXISTS suspended_until TIMESTAMPTZ;
+-- A staff ban is never lifted by the ban policy.
+ALTER TABLE students ADD COLUMN IF NOT EXISTS ban_is_manual   BOOLEAN NOT NULL DEFAULT FALSE;
+-- Set when staff lift a policy ban; the policy will not re-ban until the
+-- student complies again.
+ALTER TABLE students ADD COLUMN IF NOT EXISTS policy_waived   BOOLEAN NOT NULL DEFAULT FALSE;
+
+DO $$ BEGIN
+    CREATE TYPE appeal_status AS ENUM ('Pending', 'Upheld', 'Rejected');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS ban_appeals (
+    id              SERIAL          PRIMARY KEY,
+    student_id      VARCHAR(30)     NOT NULL REFERENCES students(student_id) ON DELETE CASCADE,
+    ban_event_id    INTEGER         REFERENCES student_ban_events(id) ON DELETE SET NULL,
+    statement       TEXT            NOT NULL,
+    status          appeal_status   NOT NULL DEFAULT 'Pending',
+    filed_by        VARCHAR(60)     NOT NULL,
+    filed_at        TIMESTAMPTZ     NOT NULL DEFAULT NOW(),
+    decided_by      VARCHAR(60),
+    decided_at      TIMESTAMPTZ,
+    decision_notes  TEXT
+);
+
+-- One open appeal per student at a time.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_ban_appeals_pending
+    ON ban_appeals(student_id) WHERE status = 'Pending';
@@ -0,0 +1,19 @@
+-- migrations/0019_student_lifecycle.sql
+
+-- Students who have left keep their history; they just cannot borrow.
+ALTER TYPE account_status ADD VALUE IF NOT EXISTS 'Graduated';
+ALTER TYPE account_status ADD VALUE IF NOT EXISTS 'Archived';
+
+ALTER TABLE students ADD COLUMN IF NOT EXISTS left_at  TIMESTAMPTZ;
+ALTER TABLE students ADD COLUMN IF NOT EXISTS left_by  VARCHAR(60);
+
+-- Issued clearance certificates, so a printed number can be verified.
+CREATE TABLE IF NOT EXISTS student_clearances (
+    id              SERIAL        PRIMARY KEY,
+    student_id      VARCHAR(30)   NOT NULL REFERENCES students(student_id) ON DELETE CASCADE,
+    certificate_no  VARCHAR(40)   NOT NULL UNIQUE,
+    issued_by       VARCHAR(60)   NOT NULL,
+    issued_at       TIMESTAMPTZ   NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_clearances_student ON student_clearances(student_id);
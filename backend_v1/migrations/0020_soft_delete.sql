@@ -0,0 +1,18 @@
+-- migrations/0020_soft_delete.sql
+
+-- Deleting a lab, tool, lecturer or student moves it to the trash. Rows stay
+-- put so history keeps resolving, and can be restored until the purge job
+-- removes them after the retention period.
+ALTER TABLE labs      ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE labs      ADD COLUMN IF NOT EXISTS deleted_by VARCHAR(60);
+ALTER TABLE tools     ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE tools     ADD COLUMN IF NOT EXISTS deleted_by VARCHAR(60);
+ALTER TABLE lecturers ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE lecturers ADD COLUMN IF NOT EXISTS deleted_by VARCHAR(60);
+ALTER TABLE students  ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE students  ADD COLUMN IF NOT EXISTS deleted_by VARCHAR(60);
+
+CREATE INDEX IF NOT EXISTS idx_labs_deleted      ON labs(deleted_at)      WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_tools_deleted     ON tools(deleted_at)     WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_lecturers_deleted ON lecturers(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_students_deleted  ON students(deleted_at)  WHERE deleted_at IS NOT NULL;
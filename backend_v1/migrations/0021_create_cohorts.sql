@@ -0,0 +1,93 @@
+-- migrations/0021_create_cohorts.sql
+--
+-- Classes become cohorts. `students.class_name` stays as a cache of the
+-- cohort's name, synced by trigger, so "MEC 2A", "mec2a" and "MEC-2A" all
+-- land in the same cohort and existing joins keep working.
+
+CREATE TABLE IF NOT EXISTS cohorts (
+    id              SERIAL          PRIMARY KEY,
+    name            VARCHAR(20)     NOT NULL,
+    -- Upper-cased letters and digits of the name; what makes two names one class
+    code            VARCHAR(20)     GENERATED ALWAYS AS
+                        (UPPER(REGEXP_REPLACE(name, '[^A-Za-z0-9]', '', 'g'))) STORED,
+    programme       VARCHAR(120)    NOT NULL,
+    year_of_study   SMALLINT        CHECK (year_of_study BETWEEN 1 AND 10),
+    intake          VARCHAR(40),
+    class_rep_id    VARCHAR(30)     REFERENCES students(student_id) ON DELETE SET NULL,
+    lecturer_id     INTEGER         REFERENCES lecturers(id) ON DELETE SET NULL,
+    created_at      TIMESTAMPTZ     NOT NULL DEFAULT NOW(),
+    CHECK (code <> '')
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_cohorts_code ON cohorts(code);
+
+ALTER TABLE students ADD COLUMN IF NOT EXISTS cohort_id INTEGER REFERENCES cohorts(id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS idx_students_cohort ON students(cohort_id);
+
+-- One cohort per class already in use, under its most common spelling
+WITH spellings AS (
+    SELECT UPPER(REGEXP_REPLACE(class_name, '[^A-Za-z0-9]', '', 'g')) AS code,
+           BTRIM(class_name) AS name,
+           MODE() WITHIN GROUP (ORDER BY department) AS programme,
+           COUNT(*) AS n
+    FROM students
+    WHERE REGEXP_REPLACE(COALESCE(class_name, ''), '[^A-Za-z0-9]', '', 'g') <> ''
+    GROUP BY 1, 2
+)
+INSERT INTO cohorts (name, programme)
+SELECT DISTINCT ON (code) name, programme FROM spellings
+ORDER BY code, n DESC, name
+ON CONFLICT DO NOTHING;
+
+UPDATE students s SET cohort_id = c.id, class_name = c.name
+FROM cohorts c
+WHERE s.cohort_id IS NULL
+  AND c.code = UPPER(REGEXP_REPLACE(s.class_name, '[^A-Za-z0-9]', '', 'g'));
+
+-- Joining a cohort sets the class name; setting a class name joins the
+-- cohort it normalises to. Cohorts are opened under /cohorts, and the API
+-- rejects a class name no cohort matches before it gets here.
+CREATE OR REPLACE FUNCTION sync_student_cohort()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+DECLARE
+    normalised TEXT;
+BEGIN
+    IF TG_OP = 'UPDATE' AND NEW.cohort_id IS DISTINCT FROM OLD.cohort_id
+       OR TG_OP = 'INSERT' AND NEW.cohort_id IS NOT NULL THEN
+        NEW.class_name := (SELECT name FROM cohorts WHERE id = NEW.cohort_id);
+    ELSIF TG_OP = 'INSERT' OR NEW.class_name IS DISTINCT FROM OLD.class_name THEN
+        normalised := UPPER(REGEXP_REPLACE(COALESCE(NEW.class_name, ''), '[^A-Za-z0-9]', '', 'g'));
+        IF normalised = '' THEN
+            NEW.class_name := NULL;
+            NEW.cohort_id  := NULL;
+        ELSE
+            IF NOT EXISTS (SELECT 1 FROM cohorts WHERE code = normalised) THEN
+                RAISE EXCEPTION 'No cohort matches class %', NEW.class_name;
+            END IF;
+            SELECT id, name INTO NEW.cohort_id, NEW.class_name FROM cohorts WHERE code = normalised;
+        END IF;
+    END IF;
+    RETURN NEW;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS students_cohort_sync ON students;
+CREATE TRIGGER students_cohort_sync
+    BEFORE INSERT OR UPDATE OF class_name, cohort_id ON students
+    FOR EACH ROW EXECUTE FUNCTION sync_student_cohort();
+
+-- Kits issued to a whole cohort at once; each student's loan points here
+CREATE TABLE IF NOT EXISTS cohort_issues (
+    id              SERIAL          PRIMARY KEY,
+    cohort_id       INTEGER         NOT NULL REFERENCES cohorts(id),
+    lecturer_id     INTEGER         NOT NULL REFERENCES lecturers(id),
+    expected_return DATE            NOT NULL,
+    notes           TEXT,
+    issued_by       VARCHAR(60)     NOT NULL,
+    issued_at       TIMESTAMPTZ     NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_cohort_issues_cohort ON cohort_issues(cohort_id);
+
+ALTER TABLE delegations ADD COLUMN IF NOT EXISTS cohort_issue_id INTEGER REFERENCES cohort_issues(id);
+CREATE INDEX IF NOT EXISTS idx_delegations_cohort_issue ON delegations(cohort_issue_id);
@@ -0,0 +1,99 @@
+-- migrations/0022_create_course_units.sql
+--
+-- A catalogue of course units. `students.units` stays the list of codes a
+-- student takes, normalised by trigger, and `unit_enrolments` mirrors it so
+-- enrolments can be joined and counted. Each unit lists the catalogue items
+-- its practicals need; loans record the unit they were issued for.
+
+CREATE TABLE IF NOT EXISTS course_units (
+    id              SERIAL          PRIMARY KEY,
+    -- Upper-cased letters and digits, e.g. "EMT2104"
+    code            VARCHAR(20)     NOT NULL,
+    name            VARCHAR(150)    NOT NULL,
+    department      VARCHAR(100),
+    lecturer_id     INTEGER         REFERENCES lecturers(id) ON DELETE SET NULL,
+    created_at      TIMESTAMPTZ     NOT NULL DEFAULT NOW(),
+    CHECK (code ~ '^[A-Z0-9]+$')
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_course_units_code ON course_units(code);
+
+CREATE TABLE IF NOT EXISTS unit_enrolments (
+    unit_id         INTEGER         NOT NULL REFERENCES course_units(id) ON DELETE CASCADE,
+    student_id      VARCHAR(30)     NOT NULL REFERENCES students(student_id) ON DELETE CASCADE,
+    enrolled_at     TIMESTAMPTZ     NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (unit_id, student_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_unit_enrolments_student ON unit_enrolments(student_id);
+
+-- The kit a student needs for the unit. An `enrolled_only` line reserves
+-- the item for students enrolled in a unit that lists it that way; other
+-- lines only record what the practicals use.
+CREATE TABLE IF NOT EXISTS unit_kit_items (
+    unit_id           INTEGER       NOT NULL REFERENCES course_units(id) ON DELETE CASCADE,
+    catalogue_item_id INTEGER       NOT NULL REFERENCES catalogue_items(id),
+    quantity          INTEGER       NOT NULL DEFAULT 1 CHECK (quantity > 0),
+    enrolled_only     BOOLEAN       NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (unit_id, catalogue_item_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_unit_kit_items_item ON unit_kit_items(catalogue_item_id);
+
+ALTER TABLE delegations ADD COLUMN IF NOT EXISTS unit_id INTEGER REFERENCES course_units(id);
+CREATE INDEX IF NOT EXISTS idx_delegations_unit ON delegations(unit_id);
+
+-- One unit per code already in use; its name is the code until someone edits it
+INSERT INTO course_units (code, name)
+SELECT DISTINCT code, code FROM (
+    SELECT UPPER(REGEXP_REPLACE(u, '[^A-Za-z0-9]', '', 'g')) AS code
+    FROM students, UNNEST(units) AS u
+) codes
+WHERE code <> ''
+ON CONFLICT DO NOTHING;
+
+-- Codes are normalised, de-duplicated and sorted; codes missing from the
+-- catalogue are dropped (the API rejects them before they get here).
+CREATE OR REPLACE FUNCTION normalise_student_units()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    IF NEW.units IS NOT NULL THEN
+        NEW.units := ARRAY(
+            SELECT DISTINCT c.code
+            FROM UNNEST(NEW.units) AS u
+            JOIN course_units c ON c.code = UPPER(REGEXP_REPLACE(u, '[^A-Za-z0-9]', '', 'g'))
+            ORDER BY c.code
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS students_units_normalise ON students;
+CREATE TRIGGER students_units_normalise
+    BEFORE INSERT OR UPDATE OF units ON students
+    FOR EACH ROW EXECUTE FUNCTION normalise_student_units();
+
+CREATE OR REPLACE FUNCTION sync_unit_enrolments()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    DELETE FROM unit_enrolments e
+    USING course_units c
+    WHERE e.unit_id = c.id AND e.student_id = NEW.student_id
+      AND NOT (c.code = ANY(COALESCE(NEW.units, '{}')));
+
+    INSERT INTO unit_enrolments (unit_id, student_id)
+    SELECT c.id, NEW.student_id FROM course_units c
+    WHERE c.code = ANY(COALESCE(NEW.units, '{}'))
+    ON CONFLICT DO NOTHING;
+    RETURN NULL;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS students_units_enrol ON students;
+CREATE TRIGGER students_units_enrol
+    AFTER INSERT OR UPDATE OF units ON students
+    FOR EACH ROW EXECUTE FUNCTION sync_unit_enrolments();
+
+-- Run both triggers over the existing lists
+UPDATE students SET units = units WHERE units IS NOT NULL;
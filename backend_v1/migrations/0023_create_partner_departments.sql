@@ -0,0 +1,76 @@
+-- migrations/0023_create_partner_departments.sql
+--
+-- A registry of partner departments and their guest borrowers, replacing the
+-- free-text guest department on inter-departmental loans. The student who
+-- collects a loan stays accountable for it; `guest_department` is kept as a
+-- cache of the department's name so existing reads keep working.
+
+CREATE TABLE IF NOT EXISTS partner_departments (
+    id                 SERIAL          PRIMARY KEY,
+    name               VARCHAR(100)    NOT NULL,
+    institution        VARCHAR(150),
+    contact_name       VARCHAR(120),
+    contact_email      VARCHAR(150),
+    contact_phone      VARCHAR(40),
+    agreement_ref      VARCHAR(60),
+    agreement_terms    TEXT,
+    -- No loans may be issued after this date, or fall due after it
+    agreement_expires  DATE,
+    -- Items the department may have out at once, across its guests
+    max_items          INTEGER         CHECK (max_items > 0),
+    -- Longest loan, in days; guests may have a shorter one of their own
+    max_loan_days      INTEGER         CHECK (max_loan_days > 0),
+    is_active          BOOLEAN         NOT NULL DEFAULT TRUE,
+    created_at         TIMESTAMPTZ     NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_partner_departments_name ON partner_departments(LOWER(name));
+
+CREATE TABLE IF NOT EXISTS guest_borrowers (
+    id                 SERIAL          PRIMARY KEY,
+    department_id      INTEGER         NOT NULL REFERENCES partner_departments(id),
+    name               VARCHAR(120)    NOT NULL,
+    email              VARCHAR(150),
+    phone              VARCHAR(40),
+    -- Staff or student number at the partner institution
+    external_ref       VARCHAR(60),
+    max_items          INTEGER         CHECK (max_items > 0),
+    max_loan_days      INTEGER         CHECK (max_loan_days > 0),
+    is_active          BOOLEAN         NOT NULL DEFAULT TRUE,
+    created_at         TIMESTAMPTZ     NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_guest_borrowers_department ON guest_borrowers(department_id);
+
+ALTER TABLE delegations
+    ADD COLUMN IF NOT EXISTS partner_department_id INTEGER REFERENCES partner_departments(id),
+    ADD COLUMN IF NOT EXISTS guest_borrower_id     INTEGER REFERENCES guest_borrowers(id);
+
+CREATE INDEX IF NOT EXISTS idx_delegations_partner ON delegations(partner_department_id);
+CREATE INDEX IF NOT EXISTS idx_delegations_guest   ON delegations(guest_borrower_id);
+
+-- One department per name already typed on a loan
+INSERT INTO partner_departments (name)
+SELECT DISTINCT ON (LOWER(BTRIM(guest_department))) BTRIM(guest_department)
+FROM delegations
+WHERE BTRIM(COALESCE(guest_department, '')) <> ''
+ORDER BY LOWER(BTRIM(guest_department)), created_at
+ON CONFLICT DO NOTHING;
+
+UPDATE delegations d SET partner_department_id = p.id, guest_department = p.name
+FROM partner_departments p
+WHERE d.partner_department_id IS NULL
+  AND LOWER(p.name) = LOWER(BTRIM(d.guest_department));
+
+CREATE OR REPLACE FUNCTION sync_partner_department_name()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    UPDATE delegations SET guest_department = NEW.name WHERE partner_department_id = NEW.id;
+    RETURN NULL;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS partner_departments_name_sync ON partner_departments;
+CREATE TRIGGER partner_departments_name_sync
+    AFTER UPDATE OF name ON partner_departments
+    FOR EACH ROW EXECUTE FUNCTION sync_partner_department_name();
@@ -0,0 +1,29 @@
+-- migrations/0024_create_borrowing_limits.sql
+--
+-- Limits on what one student may hold, checked when a loan is issued. A
+-- NULL limit is disabled. Staff may override a limit with a reason, which is
+-- recorded on the loan.
+
+-- Single-row limits applying to every student
+CREATE TABLE IF NOT EXISTS borrowing_limits (
+    id              INTEGER       PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+    -- Reusable items a student may have out at once, across all tools
+    max_items       INTEGER       CHECK (max_items > 0),
+    -- Longest loan, in days, for categories without their own
+    max_loan_days   INTEGER       CHECK (max_loan_days > 0),
+    updated_by      VARCHAR(60),
+    updated_at      TIMESTAMPTZ   NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO borrowing_limits (id) VALUES (1) ON CONFLICT (id) DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS category_limits (
+    category        tool_category PRIMARY KEY,
+    max_items       INTEGER       CHECK (max_items > 0),
+    max_loan_days   INTEGER       CHECK (max_loan_days > 0)
+);
+
+ALTER TABLE delegations
+    ADD COLUMN IF NOT EXISTS overridden_limits     TEXT[],
+    ADD COLUMN IF NOT EXISTS limit_override_reason TEXT,
+    ADD COLUMN IF NOT EXISTS limit_override_by     VARCHAR(60);
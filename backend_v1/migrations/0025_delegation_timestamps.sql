@@ -0,0 +1,64 @@
+-- migrations/0025_delegation_timestamps.sql
+--
+-- Checkout, return and due instants become TIMESTAMPTZ. The bare UTC TIME
+-- columns are dropped; date_issued, expected_return and date_returned stay
+-- as the institution's calendar days. A loan without an expected_return_time
+-- is due at the end of its expected_return day.
+--
+-- Existing rows are converted in the session's zone, so run this migration
+-- with the session set to INSTITUTION_TIMEZONE, e.g. by adding
+-- `options=-c%20TimeZone%3DAfrica/Nairobi` to DATABASE_URL. With loans on
+-- record it refuses to run in UTC or the server's default zone; an
+-- institution that really is on UTC adds `-c toolport.timezone_confirmed=on`.
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM delegations)
+       AND COALESCE(current_setting('toolport.timezone_confirmed', TRUE), '') <> 'on'
+       AND (current_setting('TimeZone') IN ('UTC','Etc/UTC','GMT','Etc/GMT','UCT','Etc/UCT','Zulu','Etc/Zulu','Universal','Etc/Universal')
+            OR (SELECT source FROM pg_settings WHERE name='TimeZone') NOT IN ('client','session'))
+    THEN
+        RAISE EXCEPTION 'Run 0025 with the session TimeZone set to INSTITUTION_TIMEZONE (currently %)',
+            current_setting('TimeZone');
+    END IF;
+END $$;
+
+ALTER TABLE delegations
+    ADD COLUMN IF NOT EXISTS checked_out_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS returned_at    TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS due_at         TIMESTAMPTZ;
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM information_schema.columns
+               WHERE table_name='delegations' AND column_name='actual_checkout_time') THEN
+        -- created_at is the exact checkout instant; the return was written
+        -- as a UTC date and time.
+        UPDATE delegations SET
+            checked_out_at = created_at,
+            returned_at    = CASE WHEN date_returned IS NOT NULL
+                                  THEN (date_returned + COALESCE(actual_return_time,'00:00')) AT TIME ZONE 'UTC'
+                             END;
+        -- Days issued or returned near midnight move to the local day.
+        UPDATE delegations SET
+            date_issued   = checked_out_at::DATE,
+            date_returned = returned_at::DATE;
+    END IF;
+END $$;
+
+UPDATE delegations SET
+    due_at = CASE WHEN expected_return_time IS NULL
+                  THEN (expected_return + 1)::TIMESTAMP::TIMESTAMPTZ
+                  ELSE (expected_return + expected_return_time)::TIMESTAMPTZ
+             END
+WHERE due_at IS NULL;
+
+ALTER TABLE delegations
+    ALTER COLUMN checked_out_at SET DEFAULT NOW(),
+    ALTER COLUMN checked_out_at SET NOT NULL,
+    ALTER COLUMN due_at         SET NOT NULL,
+    DROP COLUMN IF EXISTS actual_checkout_time,
+    DROP COLUMN IF EXISTS actual_return_time;
+
+DROP INDEX IF EXISTS idx_delegations_expected;
+CREATE INDEX IF NOT EXISTS idx_delegations_due ON delegations(due_at) WHERE status = 'Issued';
@@ -0,0 +1,40 @@
+-- migrations/0026_create_calendar.sql
+--
+-- Institution holidays and per-lab opening hours. A lab is open on a day
+-- that is not a holiday and, if the lab has opening hours, is one of its
+-- opening weekdays; a lab without hours (or no lab) opens Monday to Friday.
+-- Loans must fall due on an open day of the tool's lab, and a due day that
+-- closes after the loan was issued rolls to the next open day.
+
+CREATE TABLE IF NOT EXISTS holidays (
+    id          SERIAL        PRIMARY KEY,
+    date        DATE          NOT NULL UNIQUE,
+    name        VARCHAR(120)  NOT NULL,
+    created_by  VARCHAR(60),
+    created_at  TIMESTAMPTZ   NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS lab_opening_hours (
+    lab_id      INTEGER       NOT NULL REFERENCES labs(id) ON DELETE CASCADE,
+    -- ISO weekday: 1 = Monday … 7 = Sunday
+    weekday     SMALLINT      NOT NULL CHECK (weekday BETWEEN 1 AND 7),
+    opens_at    TIME          NOT NULL,
+    closes_at   TIME          NOT NULL,
+    PRIMARY KEY (lab_id, weekday),
+    CHECK (closes_at > opens_at)
+);
+
+CREATE OR REPLACE FUNCTION lab_is_open(p_lab INTEGER, p_day DATE) RETURNS BOOLEAN AS $$
+    SELECT NOT EXISTS (SELECT 1 FROM holidays WHERE date = p_day)
+       AND ((NOT EXISTS (SELECT 1 FROM lab_opening_hours WHERE lab_id = p_lab)
+             AND EXTRACT(ISODOW FROM p_day) <= 5)
+            OR EXISTS (SELECT 1 FROM lab_opening_hours
+                       WHERE lab_id = p_lab AND weekday = EXTRACT(ISODOW FROM p_day)));
+$$ LANGUAGE sql STABLE;
+
+-- The first open day on or after p_day, looking a year ahead
+CREATE OR REPLACE FUNCTION next_open_day(p_lab INTEGER, p_day DATE) RETURNS DATE AS $$
+    SELECT COALESCE((SELECT p_day + n FROM generate_series(0, 366) AS n
+                     WHERE lab_is_open(p_lab, p_day + n)
+                     ORDER BY n LIMIT 1), p_day);
+$$ LANGUAGE sql STABLE;
@@ -1,44 +1,57 @@
-use axum::{extract::{Query, State}, Json};
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures::{stream, Stream, StreamExt};
 use serde::Deserialize;
 use serde_json::{json, Value};
-use sqlx::Row;
+use sqlx::{PgPool, Row};
+use tokio_stream::wrappers::BroadcastStream;
 
 use crate::{
     analytics::models::{
-        ClassUsage, LecturerUsage, OverviewStats, StudentUsage, TopTool, TrendPoint,
+        ClassUsage, LecturerUsage, OverviewStats, StudentUsage, TopTool, TrendPoint, UnitUsage,
     },
-    auth::middleware::AuthUser,
+    auth::middleware::{AuthUser, StreamUser},
     errors::Result,
+    purchasing::{handlers::stock_forecast, models::{ReorderQuery, StockForecast}},
     state::AppState,
 };
 
 pub async fn overview(
     _auth: AuthUser, State(state): State<AppState>,
 ) -> Result<Json<OverviewStats>> {
+    fetch_overview(&state.db).await.map(Json)
+}
+
+pub async fn fetch_overview(db: &PgPool) -> Result<OverviewStats> {
     let row = sqlx::query(
         r#"SELECT
-            COUNT(*)::BIGINT                              AS total_tools,
+            COUNT(DISTINCT catalogue_item_id)::BIGINT    AS total_tools,
+            COUNT(*)::BIGINT                              AS stock_lines,
             COALESCE(SUM(quantity),0)::BIGINT            AS total_quantity,
-            COALESCE(SUM(quantity-issued_qty),0)::BIGINT AS available_quantity,
+            COALESCE(SUM(quantity-issued_qty-held_qty),0)::BIGINT AS available_quantity,
             COALESCE(SUM(issued_qty),0)::BIGINT          AS issued_quantity,
             COUNT(*) FILTER (WHERE status='Out of Stock')::BIGINT AS out_of_stock,
             COUNT(*) FILTER (WHERE status='Low Stock')::BIGINT    AS low_stock
-           FROM tools"#,
+           FROM tools WHERE deleted_at IS NULL"#,
     )
-    .fetch_one(&state.db).await?;
+    .fetch_one(db).await?;
 
     let overdue: i64 = sqlx::query(
         "SELECT COUNT(*)::BIGINT AS c FROM delegations WHERE status='Overdue'::delegation_status",
     )
-    .fetch_one(&state.db).await?.try_get("c")?;
+    .fetch_one(db).await?.try_get("c")?;
 
     let lost: i64 = sqlx::query(
         "SELECT COUNT(*)::BIGINT AS c FROM delegations WHERE status='Lost'::delegation_status",
     )
-    .fetch_one(&state.db).await?.try_get("c")?;
+    .fetch_one(db).await?.try_get("c")?;
 
-    Ok(Json(OverviewStats {
+    Ok(OverviewStats {
         total_tools:        row.try_get("total_tools")?,
+        stock_lines:        row.try_get("stock_lines")?,
         total_quantity:     row.try_get("total_quantity")?,
         available_quantity: row.try_get("available_quantity")?,
         issued_quantity:    row.try_get("issued_quantity")?,
@@ -46,7 +59,44 @@ pub async fn overview(
         low_stock_items:    row.try_get("low_stock")?,
         overdue_items:      overdue,
         lost_items:         lost,
-    }))
+    })
+}
+
+fn overview_event(overview: Option<&OverviewStats>) -> std::result::Result<Event, axum::Error> {
+    match overview {
+        Some(stats) => Event::default().event("overview").json_data(stats),
+        None        => Ok(Event::default().event("error").data("overview refresh failed")),
+    }
+}
+
+/// GET /analytics/stream — Server-Sent Events. Sends the current overview on
+/// connect, then every issue/return/lost/overdue/stock change. Changes
+/// arriving together are followed by one refreshed `overview` event, computed
+/// once per batch and shared by every open stream.
+pub async fn stream(
+    _auth: StreamUser, State(state): State<AppState>,
+) -> Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>> {
+    let current = match fetch_overview(&state.db).await {
+        Ok(stats) => Some(stats),
+        Err(e) => {
+            tracing::error!("Overview refresh failed: {}", e);
+            None
+        }
+    };
+    let initial = stream::iter([overview_event(current.as_ref())]);
+
+    // A lagged receiver just skips ahead; the next update carries fresh totals.
+    let updates = BroadcastStream::new(state.events.subscribe())
+        .filter_map(|msg| async move { msg.ok() })
+        .flat_map(|update| {
+            let mut events: Vec<_> = update.events.iter()
+                .map(|e| Event::default().event(e.kind.clone()).json_data(e))
+                .collect();
+            events.push(overview_event(update.overview.as_ref()));
+            stream::iter(events)
+        });
+
+    Sse::new(initial.chain(updates)).keep_alive(KeepAlive::default())
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -63,23 +113,40 @@ pub async fn usage(
     _auth: AuthUser, State(state): State<AppState>, Query(_q): Query<UsageQuery>,
 ) -> Result<Json<Value>> {
     let most_used: Vec<TopTool> = sqlx::query_as::<_, TopTool>(
-        "SELECT t.name AS tool_name, COUNT(d.id) AS total_issued
-         FROM delegations d JOIN tools t ON t.id=d.tool_id
-         GROUP BY t.id,t.name ORDER BY total_issued DESC LIMIT 10",
+        "SELECT c.name AS tool_name, COUNT(d.id) AS total_issued
+         FROM delegations d
+         JOIN tools t ON t.id=d.tool_id
+         JOIN catalogue_items c ON c.id=t.catalogue_item_id
+         GROUP BY c.id,c.name ORDER BY total_issued DESC LIMIT 10",
     )
     .fetch_all(&state.db).await?;
 
     let least_used: Vec<TopTool> = sqlx::query_as::<_, TopTool>(
-        "SELECT t.name AS tool_name, COUNT(d.id) AS total_issued
-         FROM tools t LEFT JOIN delegations d ON d.tool_id=t.id
-         GROUP BY t.id,t.name ORDER BY total_issued ASC LIMIT 10",
+        "SELECT c.name AS tool_name, COUNT(d.id) AS total_issued
+         FROM catalogue_items c
+         JOIN tools t ON t.catalogue_item_id=c.id AND t.deleted_at IS NULL
+         LEFT JOIN delegations d ON d.tool_id=t.id
+         GROUP BY c.id,c.name ORDER BY total_issued ASC LIMIT 10",
     )
     .fetch_all(&state.db).await?;
 
     let usage_by_class: Vec<ClassUsage> = sqlx::query_as::<_, ClassUsage>(
-        "SELECT s.class_name, COUNT(d.id) AS total_issued
-         FROM delegations d JOIN students s ON s.student_id=d.student_id
-         GROUP BY s.class_name ORDER BY total_issued DESC",
+        "SELECT c.name AS class_name, COUNT(d.id) AS total_issued
+         FROM delegations d
+         JOIN students s ON s.student_id=d.student_id
+         LEFT JOIN cohorts c ON c.id=s.cohort_id
+         GROUP BY c.id,c.name ORDER BY total_issued DESC",
+    )
+    .fetch_all(&state.db).await?;
+
+    let usage_by_unit: Vec<UnitUsage> = sqlx::query_as::<_, UnitUsage>(
+        r#"SELECT u.code AS unit_code, u.name AS unit_name,
+                  (SELECT COUNT(*) FROM unit_enrolments e WHERE e.unit_id=u.id) AS enrolled,
+                  COUNT(DISTINCT d.student_id) AS borrowers,
+                  COUNT(d.id) AS total_issued
+           FROM course_units u
+           LEFT JOIN delegations d ON d.unit_id=u.id
+           GROUP BY u.id,u.code,u.name ORDER BY total_issued DESC, u.code"#,
     )
     .fetch_all(&state.db).await?;
 
@@ -112,8 +179,19 @@ pub async fn usage(
         "mostUsed":        most_used,
         "leastUsed":       least_used,
         "usageByClass":    usage_by_class,
+        "usageByUnit":     usage_by_unit,
         "usageByLecturer": usage_by_lecturer,
         "usageByStudent":  usage_by_student,
         "trend":           trend,
     })))
 }
+
+/// GET /analytics/reorder — burn rate for every consumable: consumption per
+/// look-back window, the `basis_days` average, days to stock-out and the
+/// quantity that would cover `coverage_days` (default 60). Unlike
+/// `/reorder-suggestions`, lines with enough stock are listed too.
+pub async fn reorder_forecast(
+    _auth: AuthUser, State(state): State<AppState>, Query(q): Query<ReorderQuery>,
+) -> Result<Json<StockForecast>> {
+    stock_forecast(&state.db, state.config.today(), &q, 60, true).await.map(Json)
+}
@@ -1,9 +1,12 @@
 use serde::Serialize;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OverviewStats {
+    /// Distinct catalogue items
     pub total_tools:        i64,
+    /// Per-lab holdings (`tools` rows)
+    pub stock_lines:        i64,
     pub total_quantity:     i64,
     pub available_quantity: i64,
     pub issued_quantity:    i64,
@@ -27,6 +30,18 @@ pub struct ClassUsage {
     pub total_issued: Option<i64>,
 }
 
+/// Loans issued for a course unit, against how many students take it
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UnitUsage {
+    pub unit_code:    String,
+    pub unit_name:    String,
+    pub enrolled:     i64,
+    /// Enrolled students who borrowed for the unit
+    pub borrowers:    i64,
+    pub total_issued: i64,
+}
+
 #[derive(Debug, Serialize, sqlx::FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct LecturerUsage {
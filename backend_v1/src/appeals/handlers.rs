@@ -0,0 +1,121 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use serde_json::{json, Value};
+use sqlx::{PgPool, Row};
+
+use crate::{
+    appeals::models::{AppealFilters, BanAppeal, DecideAppealRequest, FileAppealRequest},
+    auth::middleware::AuthUser,
+    errors::{AppError, Result},
+    policy::handlers::lift_ban,
+    state::AppState,
+};
+
+pub const APPEAL_SELECT: &str = r#"
+    SELECT a.id,a.student_id,s.name AS student_name,a.ban_event_id,e.reason AS ban_reason,
+           a.statement,a.status,a.filed_by,a.filed_at,a.decided_by,a.decided_at,a.decision_notes
+    FROM ban_appeals a
+    JOIN students s ON s.student_id=a.student_id
+    LEFT JOIN student_ban_events e ON e.id=a.ban_event_id"#;
+
+async fn fetch_appeal(db: &PgPool, id: i32) -> Result<BanAppeal> {
+    sqlx::query_as::<_, BanAppeal>(&format!("{} WHERE a.id=$1", APPEAL_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+// GET /appeals?status=&student_id=
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<AppealFilters>,
+) -> Result<Json<Value>> {
+    let appeals = sqlx::query_as::<_, BanAppeal>(&format!(
+        "{} WHERE ($1::TEXT IS NULL OR a.status::text ILIKE $1)
+              AND ($2::TEXT IS NULL OR a.student_id=$2)
+            ORDER BY a.filed_at DESC", APPEAL_SELECT,
+    ))
+    .bind(&filters.status).bind(&filters.student_id)
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": appeals })))
+}
+
+// GET /appeals/:id
+pub async fn get_one(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<BanAppeal>> {
+    fetch_appeal(&state.db, id).await.map(Json)
+}
+
+// POST /students/:id/appeals — staff file on the student's behalf
+pub async fn file(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(student_id): Path<String>, Json(body): Json<FileAppealRequest>,
+) -> Result<(StatusCode, Json<BanAppeal>)> {
+    let id = file_appeal(&state.db, &student_id, &body.statement, &claims.sub).await?;
+    Ok((StatusCode::CREATED, Json(fetch_appeal(&state.db, id).await?)))
+}
+
+/// Files an appeal against the student's current ban or suspension.
+async fn file_appeal(db: &PgPool, student_id: &str, statement: &str, filed_by: &str) -> Result<i32> {
+    if statement.trim().is_empty() {
+        return Err(AppError::Validation("Appeal statement required".into()));
+    }
+    let mut tx = db.begin().await?;
+    let status: String = sqlx::query(
+        "SELECT account_status::text AS account_status FROM students WHERE student_id=$1 FOR UPDATE",
+    )
+    .bind(student_id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?
+    .try_get("account_status")?;
+    if status != "Banned" && status != "Suspended" {
+        return Err(AppError::Conflict("Student is not banned".into()));
+    }
+    if sqlx::query("SELECT id FROM ban_appeals WHERE student_id=$1 AND status='Pending'")
+        .bind(student_id).fetch_optional(&mut *tx).await?.is_some() {
+        return Err(AppError::Conflict("Student already has a pending appeal".into()));
+    }
+
+    let ban_event_id: Option<i32> = sqlx::query_scalar(
+        "SELECT id FROM student_ban_events WHERE student_id=$1 AND action IN ('Banned','Suspended')
+         ORDER BY created_at DESC, id DESC LIMIT 1",
+    )
+    .bind(student_id).fetch_optional(&mut *tx).await?;
+
+    let id: i32 = sqlx::query(
+        "INSERT INTO ban_appeals (student_id,ban_event_id,statement,filed_by) VALUES ($1,$2,$3,$4) RETURNING id",
+    )
+    .bind(student_id).bind(ban_event_id).bind(statement.trim()).bind(filed_by)
+    .fetch_one(&mut *tx).await?.try_get("id")?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+// POST /appeals/:id/decide — upholding lifts the ban
+pub async fn decide(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<DecideAppealRequest>,
+) -> Result<Json<BanAppeal>> {
+    let mut tx = state.db.begin().await?;
+    let row = sqlx::query(
+        "SELECT student_id,filed_by,status::text AS status FROM ban_appeals WHERE id=$1 FOR UPDATE",
+    )
+    .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    if row.try_get::<String,_>("status")? != "Pending" {
+        return Err(AppError::Conflict("Appeal has already been decided".into()));
+    }
+    if row.try_get::<String,_>("filed_by")? == claims.sub {
+        return Err(AppError::Forbidden("An appeal cannot be decided by whoever filed it".into()));
+    }
+    let student_id: String = row.try_get("student_id")?;
+
+    let status = if body.upheld { "Upheld" } else { "Rejected" };
+    sqlx::query(
+        r#"UPDATE ban_appeals SET status=$1::appeal_status, decided_by=$2, decided_at=NOW(),
+               decision_notes=$3
+           WHERE id=$4"#,
+    )
+    .bind(status).bind(&claims.sub).bind(&body.notes).bind(id)
+    .execute(&mut *tx).await?;
+
+    if body.upheld {
+        lift_ban(&mut tx, &student_id, &format!("Appeal #{} upheld", id), true, &claims.sub).await?;
+    }
+    tx.commit().await?;
+    Ok(Json(fetch_appeal(&state.db, id).await?))
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[sqlx(type_name = "appeal_status", rename_all = "PascalCase")]
+pub enum AppealStatus {
+    Pending,
+    Upheld,
+    Rejected,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct BanAppeal {
+    pub id:             i32,
+    pub student_id:     String,
+    pub student_name:   String,
+    /// The ban or suspension being appealed
+    pub ban_event_id:   Option<i32>,
+    pub ban_reason:     Option<String>,
+    pub statement:      String,
+    pub status:         AppealStatus,
+    pub filed_by:       String,
+    pub filed_at:       DateTime<Utc>,
+    pub decided_by:     Option<String>,
+    pub decided_at:     Option<DateTime<Utc>>,
+    pub decision_notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileAppealRequest {
+    pub statement: String,
+}
+
+/// Upholding an appeal lifts the ban; rejecting leaves it in place.
+#[derive(Debug, Deserialize)]
+pub struct DecideAppealRequest {
+    pub upheld: bool,
+    pub notes:  Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct AppealFilters {
+    pub status:     Option<String>,
+    pub student_id: Option<String>,
+}
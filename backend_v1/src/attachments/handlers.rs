@@ -0,0 +1,397 @@
+use axum::{
+    extract::{Multipart, Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool, Row};
+use uuid::Uuid;
+
+use crate::{
+    attachments::models::{Attachment, AttachmentOwner},
+    auth::middleware::AuthUser,
+    errors::{AppError, Result},
+    state::AppState,
+};
+
+const ATTACHMENT_SELECT: &str = r#"
+    SELECT id,owner_type,owner_id,file_name,content_type,size_bytes,
+           '/v1/attachments/' || id || '/download' AS download_url,
+           CASE WHEN thumbnail_key IS NOT NULL
+                THEN '/v1/attachments/' || id || '/thumbnail' END AS thumbnail_url,
+           is_primary,uploaded_by,uploaded_at
+    FROM attachments"#;
+
+/// Magic bytes expected at each offset
+type Signature = &'static [(usize, &'static [u8])];
+
+/// Accepted uploads, identified by their leading bytes rather than the
+/// client-declared type.
+const ALLOWED_TYPES: &[(&str, &str, Signature)] = &[
+    ("image/png",       "png",  &[(0, b"\x89PNG\r\n\x1a\n")]),
+    ("image/jpeg",      "jpg",  &[(0, b"\xFF\xD8\xFF")]),
+    ("image/webp",      "webp", &[(0, b"RIFF"), (8, b"WEBP")]),
+    ("application/pdf", "pdf",  &[(0, b"%PDF-")]),
+];
+
+/// Returns (content type, extension) for a supported file.
+pub fn sniff(bytes: &[u8]) -> Option<(&'static str, &'static str)> {
+    ALLOWED_TYPES.iter()
+        .find(|(_, _, magic)| magic.iter().all(|(at, sig)| bytes.get(*at..*at + sig.len()) == Some(*sig)))
+        .map(|(ct, ext, _)| (*ct, *ext))
+}
+
+/// Longest edge of generated thumbnails, in pixels
+const THUMBNAIL_EDGE: u32 = 320;
+
+/// Largest image accepted, per edge in pixels and in decoded bytes, so a
+/// small file cannot declare a huge canvas and make the decoder allocate it.
+const MAX_IMAGE_EDGE: u32 = 12_000;
+const MAX_IMAGE_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Scales an image down to a JPEG thumbnail. Runs on the blocking pool since
+/// decoding a large photo is CPU-bound.
+async fn make_thumbnail(bytes: Vec<u8>) -> Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || {
+        let undecodable = |e: image::ImageError| AppError::Validation(format!("Image could not be decoded: {}", e));
+        let mut limits = image::Limits::default();
+        limits.max_image_width = Some(MAX_IMAGE_EDGE);
+        limits.max_image_height = Some(MAX_IMAGE_EDGE);
+        limits.max_alloc = Some(MAX_IMAGE_ALLOC);
+        let mut reader = image::ImageReader::new(std::io::Cursor::new(&bytes))
+            .with_guessed_format().map_err(|e| undecodable(e.into()))?;
+        reader.limits(limits);
+        let img = reader.decode().map_err(undecodable)?;
+        let thumb = img.thumbnail(THUMBNAIL_EDGE, THUMBNAIL_EDGE).to_rgb8();
+        let mut out = std::io::Cursor::new(Vec::new());
+        thumb.write_to(&mut out, image::ImageFormat::Jpeg)
+            .map_err(|e| AppError::Internal(e.into()))?;
+        Ok(out.into_inner())
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.into()))?
+}
+
+/// Strips any client path and control characters from an uploaded file name.
+fn clean_file_name(raw: Option<&str>) -> String {
+    let base = raw.unwrap_or("").rsplit(['/', '\\']).next().unwrap_or("");
+    let name: String = base.chars().filter(|c| !c.is_control()).take(200).collect();
+    if name.trim().is_empty() { "upload".into() } else { name.trim().to_string() }
+}
+
+pub async fn fetch_attachment(db: &PgPool, id: i32) -> Result<Attachment> {
+    sqlx::query_as::<_, Attachment>(&format!("{} WHERE id=$1", ATTACHMENT_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+pub async fn list_for(db: &PgPool, owner: AttachmentOwner, owner_id: i32) -> Result<Vec<Attachment>> {
+    Ok(sqlx::query_as::<_, Attachment>(&format!(
+        "{} WHERE owner_type=$1 AND owner_id=$2 ORDER BY uploaded_at, id", ATTACHMENT_SELECT,
+    ))
+    .bind(owner).bind(owner_id).fetch_all(db).await?)
+}
+
+/// A file read from the `file` field of a multipart upload
+pub struct Upload {
+    pub file_name:    String,
+    pub content_type: &'static str,
+    pub extension:    &'static str,
+    pub bytes:        Vec<u8>,
+}
+
+/// Reads the `file` field, enforcing the size limit while streaming and the
+/// allowed content types once the leading bytes are in.
+pub async fn read_upload(multipart: &mut Multipart, max_bytes: usize) -> Result<Upload> {
+    let bad_form = |e: axum::extract::multipart::MultipartError| AppError::Validation(e.body_text());
+    while let Some(mut field) = multipart.next_field().await.map_err(bad_form)? {
+        if field.name() != Some("file") { continue; }
+        let file_name = clean_file_name(field.file_name());
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(bad_form)? {
+            if bytes.len() + chunk.len() > max_bytes { return Err(AppError::PayloadTooLarge(max_bytes)); }
+            bytes.extend_from_slice(&chunk);
+        }
+        if bytes.is_empty() { return Err(AppError::Validation("Uploaded file is empty".into())); }
+
+        let (content_type, extension) = sniff(&bytes).ok_or_else(|| AppError::UnsupportedMediaType(
+            field.content_type().unwrap_or("unknown").to_string(),
+        ))?;
+        return Ok(Upload { file_name, content_type, extension, bytes });
+    }
+    Err(AppError::Validation("Multipart field 'file' required".into()))
+}
+
+impl Upload {
+    pub fn is_image(&self) -> bool {
+        self.content_type.starts_with("image/")
+    }
+}
+
+/// Removes stored objects, logging rather than failing: the rows that
+/// referenced them are already gone.
+async fn discard_objects(state: &AppState, keys: &[String]) {
+    for key in keys {
+        if let Err(e) = state.storage.delete(key).await {
+            tracing::warn!("Could not remove stored object {}: {}", key, e);
+        }
+    }
+}
+
+/// Writes the bytes (and a thumbnail for images) to storage and records the
+/// attachment row. The first image uploaded for an owner becomes its primary;
+/// the owner's row is locked so two first uploads do not both claim it.
+pub async fn save_upload(
+    state: &AppState, owner: AttachmentOwner, owner_id: i32, upload: &Upload, actor: &str,
+) -> Result<Attachment> {
+    let stem = format!("{}/{}/{}", owner.storage_prefix(), owner_id, Uuid::new_v4());
+    let key = format!("{}.{}", stem, upload.extension);
+
+    let thumbnail = if upload.is_image() { Some(make_thumbnail(upload.bytes.clone()).await?) } else { None };
+    let thumbnail_key = thumbnail.as_ref().map(|_| format!("{}_thumb.jpg", stem));
+
+    state.storage.put(&key, &upload.bytes).await?;
+    let mut stored = vec![key.clone()];
+    if let (Some(k), Some(bytes)) = (&thumbnail_key, &thumbnail) {
+        if let Err(e) = state.storage.put(k, bytes).await {
+            discard_objects(state, &stored).await;
+            return Err(e.into());
+        }
+        stored.push(k.clone());
+    }
+
+    let inserted: Result<i32> = async {
+        let mut tx = state.db.begin().await?;
+        ensure_owner(&mut tx, owner, owner_id, true).await?;
+        let id = sqlx::query_scalar(
+            r#"INSERT INTO attachments
+                   (owner_type,owner_id,file_name,content_type,size_bytes,storage_key,thumbnail_key,
+                    is_primary,uploaded_by)
+               VALUES ($1,$2,$3,$4,$5,$6,$7,
+                       $7 IS NOT NULL AND NOT EXISTS (
+                           SELECT 1 FROM attachments WHERE owner_type=$1 AND owner_id=$2 AND is_primary),
+                       $8)
+               RETURNING id"#,
+        )
+        .bind(owner).bind(owner_id).bind(&upload.file_name).bind(upload.content_type)
+        .bind(upload.bytes.len() as i64).bind(&key).bind(&thumbnail_key).bind(actor)
+        .fetch_one(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(id)
+    }.await;
+
+    let id = match inserted {
+        Ok(id) => id,
+        Err(e) => {
+            // Don't leave orphaned objects behind.
+            discard_objects(state, &stored).await;
+            return Err(e);
+        }
+    };
+    fetch_attachment(&state.db, id).await
+}
+
+/// Deletes every attachment of an owner along with its stored objects.
+pub async fn delete_owned(state: &AppState, owner: AttachmentOwner, owner_id: i32) -> Result<()> {
+    let rows = sqlx::query(
+        "DELETE FROM attachments WHERE owner_type=$1 AND owner_id=$2 RETURNING storage_key,thumbnail_key",
+    )
+    .bind(owner).bind(owner_id).fetch_all(&state.db).await?;
+    let mut keys = Vec::new();
+    for r in rows {
+        keys.push(r.try_get::<String,_>("storage_key")?);
+        keys.extend(r.try_get::<Option<String>,_>("thumbnail_key")?);
+    }
+    discard_objects(state, &keys).await;
+    Ok(())
+}
+
+/// Checks the owner exists; `lock` also locks its row until the transaction
+/// ends, so uploads for one owner are recorded one after the other.
+async fn ensure_owner(conn: &mut PgConnection, owner: AttachmentOwner, owner_id: i32, lock: bool) -> Result<()> {
+    let sql = match owner {
+        AttachmentOwner::Tool         => "SELECT id FROM tools WHERE id=$1",
+        AttachmentOwner::DamageReport => "SELECT id FROM damage_reports WHERE id=$1",
+        AttachmentOwner::LostTool     => "SELECT id FROM delegations WHERE id=$1 AND status='Lost'::delegation_status",
+    };
+    let sql = if lock { format!("{} FOR NO KEY UPDATE", sql) } else { sql.to_string() };
+    if sqlx::query(&sql).bind(owner_id).fetch_optional(&mut *conn).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    Ok(())
+}
+
+async fn upload(
+    state: &AppState, owner: AttachmentOwner, owner_id: i32, mut multipart: Multipart, actor: &str,
+) -> Result<(StatusCode, Json<Attachment>)> {
+    ensure_owner(&mut *state.db.acquire().await?, owner, owner_id, false).await?;
+    let file = read_upload(&mut multipart, state.config.max_upload_bytes).await?;
+    let attachment = save_upload(state, owner, owner_id, &file, actor).await?;
+    Ok((StatusCode::CREATED, Json(attachment)))
+}
+
+async fn list(state: &AppState, owner: AttachmentOwner, owner_id: i32) -> Result<Json<Value>> {
+    ensure_owner(&mut *state.db.acquire().await?, owner, owner_id, false).await?;
+    Ok(Json(json!({ "data": list_for(&state.db, owner, owner_id).await? })))
+}
+
+// GET /tools/:id/attachments
+pub async fn list_tool(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<Value>> {
+    list(&state, AttachmentOwner::Tool, id).await
+}
+
+// POST /tools/:id/attachments (multipart `file`)
+pub async fn upload_tool(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(id): Path<i32>, multipart: Multipart,
+) -> Result<(StatusCode, Json<Attachment>)> {
+    upload(&state, AttachmentOwner::Tool, id, multipart, &claims.sub).await
+}
+
+// GET /damage-reports/:id/attachments
+pub async fn list_damage(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<Value>> {
+    list(&state, AttachmentOwner::DamageReport, id).await
+}
+
+// POST /damage-reports/:id/attachments (multipart `file`)
+pub async fn upload_damage(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(id): Path<i32>, multipart: Multipart,
+) -> Result<(StatusCode, Json<Attachment>)> {
+    upload(&state, AttachmentOwner::DamageReport, id, multipart, &claims.sub).await
+}
+
+/// Lost-tool routes are scoped by student; the delegation must be theirs.
+async fn ensure_students_loss(db: &PgPool, student_id: &str, delegation_id: i32) -> Result<()> {
+    if sqlx::query("SELECT id FROM delegations WHERE id=$1 AND student_id=$2")
+        .bind(delegation_id).bind(student_id).fetch_optional(db).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    Ok(())
+}
+
+// GET /students/:student_id/lost-tools/:delegation_id/attachments
+pub async fn list_lost(
+    _auth: AuthUser, State(state): State<AppState>, Path((student_id, id)): Path<(String, i32)>,
+) -> Result<Json<Value>> {
+    ensure_students_loss(&state.db, &student_id, id).await?;
+    list(&state, AttachmentOwner::LostTool, id).await
+}
+
+// POST /students/:student_id/lost-tools/:delegation_id/attachments — e.g. payment receipts
+pub async fn upload_lost(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path((student_id, id)): Path<(String, i32)>, multipart: Multipart,
+) -> Result<(StatusCode, Json<Attachment>)> {
+    ensure_students_loss(&state.db, &student_id, id).await?;
+    upload(&state, AttachmentOwner::LostTool, id, multipart, &claims.sub).await
+}
+
+// GET /attachments/:id
+pub async fn get_one(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<Attachment>> {
+    fetch_attachment(&state.db, id).await.map(Json)
+}
+
+// GET /attachments/:id/download
+pub async fn download(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<impl IntoResponse> {
+    let row = sqlx::query("SELECT file_name,content_type,storage_key FROM attachments WHERE id=$1")
+        .bind(id).fetch_optional(&state.db).await?.ok_or(AppError::NotFound)?;
+    let file_name: String    = row.try_get("file_name")?;
+    let content_type: String = row.try_get("content_type")?;
+    let bytes = state.storage.get(&row.try_get::<String,_>("storage_key")?).await?;
+    Ok(serve(bytes, content_type, &file_name))
+}
+
+// GET /attachments/:id/thumbnail
+pub async fn thumbnail(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<impl IntoResponse> {
+    let key: Option<String> = sqlx::query("SELECT thumbnail_key FROM attachments WHERE id=$1")
+        .bind(id).fetch_optional(&state.db).await?.ok_or(AppError::NotFound)?
+        .try_get("thumbnail_key")?;
+    let bytes = state.storage.get(&key.ok_or(AppError::NotFound)?).await?;
+    Ok(serve(bytes, "image/jpeg".into(), &format!("thumbnail-{}.jpg", id)))
+}
+
+// POST /attachments/:id/primary — makes an image the owner's primary photo
+pub async fn make_primary(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<Attachment>> {
+    let mut tx = state.db.begin().await?;
+    let row = sqlx::query(
+        "SELECT owner_type,owner_id,thumbnail_key IS NOT NULL AS is_image FROM attachments WHERE id=$1 FOR UPDATE",
+    )
+    .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    if !row.try_get::<bool,_>("is_image")? {
+        return Err(AppError::Validation("Only images can be the primary photo".into()));
+    }
+    let owner: AttachmentOwner = row.try_get("owner_type")?;
+    let owner_id: i32 = row.try_get("owner_id")?;
+
+    sqlx::query("UPDATE attachments SET is_primary=FALSE WHERE owner_type=$1 AND owner_id=$2 AND is_primary")
+        .bind(owner).bind(owner_id).execute(&mut *tx).await?;
+    sqlx::query("UPDATE attachments SET is_primary=TRUE WHERE id=$1")
+        .bind(id).execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(Json(fetch_attachment(&state.db, id).await?))
+}
+
+fn serve(bytes: Vec<u8>, content_type: String, file_name: &str) -> impl IntoResponse {
+    let ascii_name: String = file_name.chars()
+        .filter(|c| (c.is_ascii_graphic() || *c == ' ') && *c != '"' && *c != '\\').collect();
+    let disposition = format!("inline; filename=\"{}\"", ascii_name);
+    (
+        [(header::CONTENT_TYPE, content_type), (header::CONTENT_DISPOSITION, disposition)],
+        bytes,
+    )
+}
+
+// DELETE /attachments/:id — the owner's next image takes over as primary.
+// Payment receipts are evidence and cannot be deleted.
+pub async fn delete(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    let mut tx = state.db.begin().await?;
+    // Locked first so a payment citing it as a receipt waits for the delete.
+    sqlx::query("SELECT id FROM attachments WHERE id=$1 FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let payments: Vec<i32> = sqlx::query_scalar(
+        "SELECT id FROM charge_payments WHERE receipt_attachment_id=$1 ORDER BY id",
+    )
+    .bind(id).fetch_all(&mut *tx).await?;
+    if !payments.is_empty() {
+        return Err(AppError::Blocked(
+            "Attachment is a payment receipt and cannot be deleted".into(),
+            payments.iter().map(|p| format!("Receipt for payment #{}", p)).collect(),
+        ));
+    }
+    let row = sqlx::query(
+        r#"DELETE FROM attachments WHERE id=$1
+           RETURNING owner_type,owner_id,is_primary,storage_key,thumbnail_key"#,
+    )
+    .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+
+    if row.try_get::<bool,_>("is_primary")? {
+        sqlx::query(
+            r#"UPDATE attachments SET is_primary=TRUE WHERE id=(
+                   SELECT id FROM attachments
+                   WHERE owner_type=$1 AND owner_id=$2 AND thumbnail_key IS NOT NULL
+                   ORDER BY uploaded_at, id LIMIT 1)"#,
+        )
+        .bind(row.try_get::<AttachmentOwner,_>("owner_type")?).bind(row.try_get::<i32,_>("owner_id")?)
+        .execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+
+    let mut keys = vec![row.try_get::<String,_>("storage_key")?];
+    keys.extend(row.try_get::<Option<String>,_>("thumbnail_key")?);
+    discard_objects(&state, &keys).await;
+    Ok(StatusCode::NO_CONTENT)
+}
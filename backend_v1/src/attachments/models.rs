@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What an attachment belongs to. `LostTool` owners are delegation ids.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq)]
+#[sqlx(type_name = "attachment_owner", rename_all = "PascalCase")]
+pub enum AttachmentOwner {
+    Tool,
+    #[serde(rename = "Damage Report")]
+    #[sqlx(rename = "Damage Report")]
+    DamageReport,
+    #[serde(rename = "Lost Tool")]
+    #[sqlx(rename = "Lost Tool")]
+    LostTool,
+}
+
+impl AttachmentOwner {
+    /// Top-level folder for this owner's objects in storage.
+    pub fn storage_prefix(self) -> &'static str {
+        match self {
+            AttachmentOwner::Tool         => "tools",
+            AttachmentOwner::DamageReport => "damage-reports",
+            AttachmentOwner::LostTool     => "lost-tools",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id:           i32,
+    pub owner_type:   AttachmentOwner,
+    pub owner_id:     i32,
+    pub file_name:    String,
+    pub content_type: String,
+    pub size_bytes:   i64,
+    pub download_url: String,
+    pub thumbnail_url: Option<String>,
+    pub is_primary:   bool,
+    pub uploaded_by:  String,
+    pub uploaded_at:  DateTime<Utc>,
+}
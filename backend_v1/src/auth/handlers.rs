@@ -7,7 +7,13 @@ use serde_json::{json, Value};
 use sqlx::Row;
 
 use crate::{
-    auth::{middleware::AuthUser, models::{AdminInfo, Claims, ChangePasswordRequest, LoginRequest, LoginResponse}},
+    auth::{
+        middleware::AuthUser,
+        models::{
+            AdminInfo, Claims, ChangePasswordRequest, LoginRequest, LoginResponse, StreamTicket,
+            STREAM_TICKET_PURPOSE, STREAM_TICKET_SECONDS,
+        },
+    },
     errors::{AppError, Result},
     state::AppState,
 };
@@ -73,3 +79,16 @@ pub async fn change_password(
 
     Ok(Json(json!({ "message": "Password changed successfully" })))
 }
+
+// POST /auth/stream-ticket — a short-lived ticket for GET /analytics/stream,
+// since EventSource cannot send the Authorization header
+pub async fn stream_ticket(AuthUser(claims): AuthUser, State(state): State<AppState>) -> Result<Json<Value>> {
+    let expiry = Utc::now() + chrono::Duration::seconds(STREAM_TICKET_SECONDS);
+    let ticket = StreamTicket {
+        sub: claims.sub, purpose: STREAM_TICKET_PURPOSE.into(), exp: expiry.timestamp() as usize,
+    };
+    let token = encode(&Header::default(), &ticket,
+        &EncodingKey::from_secret(state.config.jwt_secret.as_bytes()))
+        .map_err(|e| AppError::Internal(e.into()))?;
+    Ok(Json(json!({ "ticket": token, "expiresIn": STREAM_TICKET_SECONDS })))
+}
@@ -5,7 +5,11 @@ use axum::{
 };
 use jsonwebtoken::{decode, DecodingKey, Validation};
 
-use crate::{auth::models::Claims, errors::AppError, state::AppState};
+use crate::{
+    auth::models::{Claims, StreamTicket, STREAM_TICKET_PURPOSE},
+    errors::AppError,
+    state::AppState,
+};
 
 /// Axum extractor: validates Bearer JWT and injects Claims into handlers.
 /// Add `_auth: AuthUser` (or `AuthUser(claims): AuthUser`) as a parameter
@@ -20,12 +24,7 @@ impl FromRequestParts<AppState> for AuthUser {
         parts: &mut Parts,
         state: &AppState,
     ) -> Result<Self, AppError> {
-        let token = parts
-            .headers
-            .get(header::AUTHORIZATION)
-            .and_then(|v| v.to_str().ok())
-            .and_then(|v| v.strip_prefix("Bearer "))
-            .ok_or(AppError::Unauthorized)?;
+        let token = bearer_token(parts).ok_or(AppError::Unauthorized)?;
 
         let key = DecodingKey::from_secret(state.config.jwt_secret.as_bytes());
         let data = decode::<Claims>(token, &key, &Validation::default())
@@ -34,3 +33,44 @@ impl FromRequestParts<AppState> for AuthUser {
         Ok(AuthUser(data.claims))
     }
 }
+
+/// Extractor for the SSE stream. Browsers' `EventSource` cannot set headers,
+/// so besides the Bearer JWT it accepts a `?ticket=` from
+/// POST /auth/stream-ticket. A ticket is short-lived and opens nothing but
+/// the stream, so one that ends up in an access log is of little use.
+pub struct StreamUser;
+
+#[async_trait]
+impl FromRequestParts<AppState> for StreamUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, AppError> {
+        if bearer_token(parts).is_some() {
+            AuthUser::from_request_parts(parts, state).await?;
+            return Ok(StreamUser);
+        }
+        let ticket = parts.uri.query()
+            .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("ticket=")))
+            .filter(|t| !t.is_empty())
+            .ok_or(AppError::Unauthorized)?;
+
+        let key = DecodingKey::from_secret(state.config.jwt_secret.as_bytes());
+        let data = decode::<StreamTicket>(ticket, &key, &Validation::default())
+            .map_err(|_| AppError::Unauthorized)?;
+        if data.claims.purpose != STREAM_TICKET_PURPOSE {
+            return Err(AppError::Unauthorized);
+        }
+        Ok(StreamUser)
+    }
+}
+
+fn bearer_token(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
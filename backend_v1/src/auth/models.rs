@@ -25,6 +25,20 @@ pub struct Claims {
     pub exp:  usize,    // expiry     (UNIX)
 }
 
+/// Single-purpose token for opening the SSE stream. It has no `name` or
+/// `role`, so it never passes as an ordinary login token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StreamTicket {
+    pub sub:     String,
+    pub purpose: String,
+    pub exp:     usize,
+}
+
+pub const STREAM_TICKET_PURPOSE: &str = "analytics-stream";
+
+/// How long a stream ticket stays valid.
+pub const STREAM_TICKET_SECONDS: i64 = 60;
+
 // ── Responses ─────────────────────────────────────────────────────────────────
 
 #[derive(Debug, Serialize)]
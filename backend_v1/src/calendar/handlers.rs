@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{NaiveDate, NaiveTime};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    calendar::models::{
+        ClosedDay, CreateHolidayRequest, DueDateQuery, DueDateSuggestion, Holiday, HolidayFilters,
+        LabHours, OpeningHours, SetHoursRequest,
+    },
+    errors::{AppError, Result},
+    state::AppState,
+};
+
+/// Closed days from `day` up to the lab's next open day, with their reasons
+async fn closed_days(conn: &mut PgConnection, lab_id: Option<i32>, day: NaiveDate) -> Result<Vec<ClosedDay>> {
+    let rows = sqlx::query(
+        r#"SELECT g.day, h.name AS holiday
+           FROM (SELECT $2::DATE + n AS day
+                 FROM generate_series(0, next_open_day($1,$2) - $2 - 1) AS n) g
+           LEFT JOIN holidays h ON h.date=g.day
+           ORDER BY g.day"#,
+    )
+    .bind(lab_id).bind(day).fetch_all(&mut *conn).await?;
+    rows.iter().map(|r| {
+        let date: NaiveDate = r.try_get("day")?;
+        let reason = r.try_get::<Option<String>,_>("holiday")?
+            .unwrap_or_else(|| format!("Closed on {}s", date.format("%A")));
+        Ok(ClosedDay { date, reason })
+    }).collect()
+}
+
+async fn hours_on(
+    conn: &mut PgConnection, lab_id: Option<i32>, day: NaiveDate,
+) -> Result<Option<(NaiveTime, NaiveTime)>> {
+    let row = sqlx::query(
+        "SELECT opens_at,closes_at FROM lab_opening_hours
+         WHERE lab_id=$1 AND weekday=EXTRACT(ISODOW FROM $2::DATE)",
+    )
+    .bind(lab_id).bind(day).fetch_optional(&mut *conn).await?;
+    row.map(|r| Ok((r.try_get("opens_at")?, r.try_get("closes_at")?))).transpose()
+}
+
+/// A loan from a lab must fall due on a day it is open, and within its
+/// hours when a return time is given. The error names the next open day.
+pub async fn check_due(
+    conn: &mut PgConnection, lab_id: Option<i32>, date: NaiveDate, time: Option<NaiveTime>,
+) -> Result<()> {
+    let closed = closed_days(conn, lab_id, date).await?;
+    if let Some(first) = closed.first() {
+        let next = date + chrono::Duration::days(closed.len() as i64);
+        return Err(AppError::Validation(format!(
+            "{} is not an open day ({}); the next open day is {}", date, first.reason, next,
+        )));
+    }
+    if let (Some(time), Some((opens, closes))) = (time, hours_on(conn, lab_id, date).await?) {
+        if time < opens || time > closes {
+            return Err(AppError::Validation(format!(
+                "expected_return_time must be between {} and {} on {}",
+                opens.format("%H:%M"), closes.format("%H:%M"), date,
+            )));
+        }
+    }
+    Ok(())
+}
+
+// GET /holidays?from=&to=
+pub async fn list_holidays(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<HolidayFilters>,
+) -> Result<Json<Value>> {
+    let holidays = sqlx::query_as::<_, Holiday>(
+        r#"SELECT id,date,name,created_by,created_at FROM holidays
+           WHERE ($1::DATE IS NULL OR date >= $1) AND ($2::DATE IS NULL OR date <= $2)
+           ORDER BY date"#,
+    )
+    .bind(filters.from).bind(filters.to).fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": holidays })))
+}
+
+// POST /holidays — loans already due that day roll to the next open day
+pub async fn create_holiday(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Json(body): Json<CreateHolidayRequest>,
+) -> Result<(StatusCode, Json<Holiday>)> {
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation("name cannot be blank".into()));
+    }
+    if sqlx::query("SELECT id FROM holidays WHERE date=$1")
+        .bind(body.date).fetch_optional(&state.db).await?.is_some() {
+        return Err(AppError::Conflict(format!("{} is already a holiday", body.date)));
+    }
+    let holiday = sqlx::query_as::<_, Holiday>(
+        "INSERT INTO holidays (date,name,created_by) VALUES ($1,$2,$3)
+         RETURNING id,date,name,created_by,created_at",
+    )
+    .bind(body.date).bind(name).bind(&claims.sub).fetch_one(&state.db).await?;
+    Ok((StatusCode::CREATED, Json(holiday)))
+}
+
+// DELETE /holidays/:id
+pub async fn delete_holiday(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    let result = sqlx::query("DELETE FROM holidays WHERE id=$1").bind(id).execute(&state.db).await?;
+    if result.rows_affected() == 0 { return Err(AppError::NotFound); }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn fetch_hours(conn: &mut PgConnection, lab_id: i32) -> Result<LabHours> {
+    let hours = sqlx::query_as::<_, OpeningHours>(
+        "SELECT weekday,opens_at,closes_at FROM lab_opening_hours WHERE lab_id=$1 ORDER BY weekday",
+    )
+    .bind(lab_id).fetch_all(&mut *conn).await?;
+    Ok(LabHours { lab_id, hours })
+}
+
+// GET /labs/:id/hours
+pub async fn get_hours(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<LabHours>> {
+    let mut conn = state.db.acquire().await?;
+    if sqlx::query("SELECT id FROM labs WHERE id=$1")
+        .bind(id).fetch_optional(&mut *conn).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    fetch_hours(&mut conn, id).await.map(Json)
+}
+
+// PUT /labs/:id/hours — replaces the lab's opening hours
+pub async fn set_hours(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>, Json(body): Json<SetHoursRequest>,
+) -> Result<Json<LabHours>> {
+    let mut seen = HashSet::new();
+    for h in &body.hours {
+        if !(1..=7).contains(&h.weekday) {
+            return Err(AppError::Validation("weekday must be 1 (Monday) to 7 (Sunday)".into()));
+        }
+        if h.closes_at <= h.opens_at {
+            return Err(AppError::Validation("closes_at must be after opens_at".into()));
+        }
+        if !seen.insert(h.weekday) {
+            return Err(AppError::Validation("Each weekday may appear once".into()));
+        }
+    }
+
+    let mut tx = state.db.begin().await?;
+    if sqlx::query("SELECT id FROM labs WHERE id=$1 AND deleted_at IS NULL FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    sqlx::query("DELETE FROM lab_opening_hours WHERE lab_id=$1").bind(id).execute(&mut *tx).await?;
+    for h in &body.hours {
+        sqlx::query("INSERT INTO lab_opening_hours (lab_id,weekday,opens_at,closes_at) VALUES ($1,$2,$3,$4)")
+            .bind(id).bind(h.weekday).bind(h.opens_at).bind(h.closes_at)
+            .execute(&mut *tx).await?;
+    }
+    let hours = fetch_hours(&mut tx, id).await?;
+    tx.commit().await?;
+    Ok(Json(hours))
+}
+
+// GET /calendar/due-date?tool_id=&lab_id=&date=&days=
+pub async fn suggest_due_date(
+    _auth: AuthUser, State(state): State<AppState>, Query(q): Query<DueDateQuery>,
+) -> Result<Json<DueDateSuggestion>> {
+    let mut conn = state.db.acquire().await?;
+    let lab_id = match q.tool_id {
+        Some(tool_id) => sqlx::query("SELECT lab_id FROM tools WHERE id=$1 AND deleted_at IS NULL")
+            .bind(tool_id).fetch_optional(&mut *conn).await?
+            .ok_or_else(|| AppError::Validation("Tool not found".into()))?
+            .try_get("lab_id")?,
+        None => q.lab_id,
+    };
+    let requested = match (q.date, q.days) {
+        (Some(date), _) => date,
+        (None, Some(days)) if days >= 0 => state.config.today() + chrono::Duration::days(i64::from(days)),
+        (None, Some(_)) => return Err(AppError::Validation("days must be >= 0".into())),
+        (None, None) => return Err(AppError::Validation("date or days required".into())),
+    };
+
+    let closed_days = closed_days(&mut conn, lab_id, requested).await?;
+    let due_date = requested + chrono::Duration::days(closed_days.len() as i64);
+    let hours = hours_on(&mut conn, lab_id, due_date).await?;
+    Ok(Json(DueDateSuggestion {
+        lab_id, requested, due_date, closed_days,
+        opens_at:  hours.map(|h| h.0),
+        closes_at: hours.map(|h| h.1),
+    }))
+}
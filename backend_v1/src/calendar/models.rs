@@ -0,0 +1,76 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Holiday {
+    pub id:         i32,
+    pub date:       NaiveDate,
+    pub name:       String,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HolidayFilters {
+    pub from: Option<NaiveDate>,
+    pub to:   Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateHolidayRequest {
+    pub date: NaiveDate,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct OpeningHours {
+    /// ISO weekday: 1 = Monday … 7 = Sunday
+    pub weekday:   i16,
+    pub opens_at:  NaiveTime,
+    pub closes_at: NaiveTime,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabHours {
+    pub lab_id: i32,
+    /// Empty when the lab keeps the default Monday to Friday
+    pub hours:  Vec<OpeningHours>,
+}
+
+/// Replaces a lab's hours; an empty list opens it Monday to Friday.
+#[derive(Debug, Deserialize)]
+pub struct SetHoursRequest {
+    pub hours: Vec<OpeningHours>,
+}
+
+/// A due date `days` from today, or on `date`, for a tool or lab
+#[derive(Debug, Deserialize)]
+pub struct DueDateQuery {
+    pub tool_id: Option<i32>,
+    pub lab_id:  Option<i32>,
+    pub date:    Option<NaiveDate>,
+    pub days:    Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClosedDay {
+    pub date:   NaiveDate,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DueDateSuggestion {
+    pub lab_id:      Option<i32>,
+    pub requested:   NaiveDate,
+    /// The first open day on or after `requested`
+    pub due_date:    NaiveDate,
+    /// Closed days skipped to reach `due_date`
+    pub closed_days: Vec<ClosedDay>,
+    pub opens_at:    Option<NaiveTime>,
+    pub closes_at:   Option<NaiveTime>,
+}
@@ -0,0 +1,167 @@
+use axum::{extract::{Path, Query, State}, Json};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    catalogue::models::{
+        CatalogueFilters, CatalogueItem, CatalogueItemDetail, Holding, UpdateCatalogueItemRequest,
+    },
+    errors::{AppError, Result},
+    state::AppState,
+    tools::models::CreateToolRequest,
+};
+
+const ITEM_SELECT: &str = r#"
+    SELECT c.id,c.name,c.category,c.subcategory,c.unit,c.description,
+           c.is_consumable,c.consumable_type,c.replacement_cost_cents,
+           COUNT(DISTINCT t.lab_id)::BIGINT                 AS lab_count,
+           COALESCE(SUM(t.quantity),0)::BIGINT              AS total_quantity,
+           COALESCE(SUM(t.issued_qty),0)::BIGINT            AS issued_quantity,
+           COALESCE(SUM(t.quantity-t.issued_qty-t.held_qty),0)::BIGINT AS available_quantity,
+           c.created_at,c.updated_at
+    FROM catalogue_items c
+    LEFT JOIN tools t ON t.catalogue_item_id=c.id AND t.deleted_at IS NULL"#;
+
+async fn fetch_item(db: &PgPool, id: i32) -> Result<CatalogueItem> {
+    sqlx::query_as::<_, CatalogueItem>(&format!("{} WHERE c.id=$1 GROUP BY c.id", ITEM_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+/// Catalogue item matching the request's name (case-insensitive) and
+/// category, created from the request's descriptive fields if new. An
+/// existing item takes the request's subcategory, description and consumable
+/// type where it has none; a different unit or consumable flag is a conflict,
+/// since every lab's holding shares them.
+pub async fn find_or_create_item(conn: &mut PgConnection, body: &CreateToolRequest) -> Result<i32> {
+    let existing = sqlx::query(
+        "SELECT id,name,unit,is_consumable FROM catalogue_items
+         WHERE LOWER(name)=LOWER($1) AND category=$2 FOR UPDATE",
+    )
+    .bind(body.name.trim()).bind(&body.category)
+    .fetch_optional(&mut *conn).await?;
+    if let Some(r) = existing {
+        let id: i32 = r.try_get("id")?;
+        let name: String = r.try_get("name")?;
+        let unit: String = r.try_get("unit")?;
+        if body.unit.as_deref().is_some_and(|u| u != unit) {
+            return Err(AppError::Conflict(format!(
+                "{} is catalogued (item #{}) in {}; use that unit or change it on the catalogue item", name, id, unit,
+            )));
+        }
+        if body.is_consumable.is_some_and(|c| c != r.get::<bool,_>("is_consumable")) {
+            return Err(AppError::Conflict(format!(
+                "{} is catalogued (item #{}) with a different is_consumable; change it on the catalogue item", name, id,
+            )));
+        }
+        sqlx::query(
+            r#"UPDATE catalogue_items SET
+                   subcategory=COALESCE(subcategory,$2), description=COALESCE(description,$3),
+                   consumable_type=COALESCE(consumable_type,$4)
+               WHERE id=$1 AND (subcategory IS NULL AND $2::TEXT IS NOT NULL
+                                OR description IS NULL AND $3::TEXT IS NOT NULL
+                                OR consumable_type IS NULL AND $4::TEXT IS NOT NULL)"#,
+        )
+        .bind(id).bind(&body.subcategory).bind(&body.description).bind(&body.consumable_type)
+        .execute(&mut *conn).await?;
+        return Ok(id);
+    }
+
+    let row = sqlx::query(
+        r#"INSERT INTO catalogue_items
+               (name,category,subcategory,unit,description,is_consumable,consumable_type)
+           VALUES ($1,$2,$3,$4,$5,$6,$7)
+           RETURNING id"#,
+    )
+    .bind(body.name.trim()).bind(&body.category).bind(&body.subcategory)
+    .bind(body.unit.as_deref().unwrap_or("pcs")).bind(&body.description)
+    .bind(body.is_consumable.unwrap_or(false)).bind(&body.consumable_type)
+    .fetch_one(&mut *conn).await?;
+    Ok(row.try_get("id")?)
+}
+
+/// Rejects a second holding of the same item in one lab — stock for a lab
+/// lives on a single line. The unique index on `(catalogue_item_id, lab_id)`
+/// settles a race; this gives the friendlier message.
+pub async fn ensure_single_holding(
+    conn: &mut PgConnection, catalogue_item_id: i32, lab_id: Option<i32>, except_tool_id: Option<i32>,
+) -> Result<()> {
+    let Some(lab_id) = lab_id else { return Ok(()) };
+    let dup = sqlx::query(
+        r#"SELECT id, deleted_at IS NOT NULL AS trashed FROM tools
+           WHERE catalogue_item_id=$1 AND lab_id=$2 AND ($3::INT IS NULL OR id<>$3)"#,
+    )
+    .bind(catalogue_item_id).bind(lab_id).bind(except_tool_id)
+    .fetch_optional(&mut *conn).await?;
+    match dup {
+        Some(r) if r.try_get("trashed")? => Err(AppError::Conflict(format!(
+            "This lab's stock line for this item, tool #{}, is in the trash; restore it instead",
+            r.try_get::<i32,_>("id")?,
+        ))),
+        Some(r) => Err(AppError::Conflict(format!(
+            "This lab already holds this item as tool #{}; adjust that stock line instead",
+            r.try_get::<i32,_>("id")?,
+        ))),
+        None => Ok(()),
+    }
+}
+
+// GET /catalogue
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<CatalogueFilters>,
+) -> Result<Json<Value>> {
+    let items = sqlx::query_as::<_, CatalogueItem>(&format!(
+        "{} WHERE ($1::TEXT IS NULL OR c.category::text ILIKE '%' || $1 || '%')
+              AND ($2::TEXT IS NULL OR c.name ILIKE '%' || $2 || '%'
+                                    OR c.description ILIKE '%' || $2 || '%')
+            GROUP BY c.id ORDER BY c.name", ITEM_SELECT,
+    ))
+    .bind(&filters.category).bind(&filters.search)
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": items, "total": items.len() })))
+}
+
+// GET /catalogue/:id — item with its per-lab holdings
+pub async fn get_one(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<CatalogueItemDetail>> {
+    let item = fetch_item(&state.db, id).await?;
+    let holdings = sqlx::query_as::<_, Holding>(
+        r#"SELECT t.id AS tool_id, t.lab_id, l.name AS lab_name, t.quantity, t.issued_qty,
+                  t.quantity-t.issued_qty-t.held_qty AS available, t.low_stock_threshold, t.status
+           FROM tools t LEFT JOIN labs l ON l.id=t.lab_id
+           WHERE t.catalogue_item_id=$1 AND t.deleted_at IS NULL
+           ORDER BY l.name"#,
+    )
+    .bind(id).fetch_all(&state.db).await?;
+    Ok(Json(CatalogueItemDetail { item, holdings }))
+}
+
+// PUT /catalogue/:id — descriptive changes propagate to every holding
+pub async fn update(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<UpdateCatalogueItemRequest>,
+) -> Result<Json<CatalogueItem>> {
+    if body.name.as_deref().is_some_and(|n| n.trim().is_empty()) {
+        return Err(AppError::Validation("Tool name required".into()));
+    }
+    if body.replacement_cost_cents.is_some_and(|c| c < 0) {
+        return Err(AppError::Validation("Replacement cost cannot be negative".into()));
+    }
+    let r = sqlx::query(
+        r#"UPDATE catalogue_items SET
+               name=COALESCE($1,name), category=COALESCE($2,category),
+               subcategory=COALESCE($3,subcategory), unit=COALESCE($4,unit),
+               description=COALESCE($5,description), is_consumable=COALESCE($6,is_consumable),
+               consumable_type=COALESCE($7,consumable_type),
+               replacement_cost_cents=COALESCE($8,replacement_cost_cents)
+           WHERE id=$9"#,
+    )
+    .bind(body.name.as_deref().map(str::trim)).bind(&body.category).bind(&body.subcategory)
+    .bind(&body.unit).bind(&body.description).bind(body.is_consumable)
+    .bind(&body.consumable_type).bind(body.replacement_cost_cents).bind(id)
+    .execute(&state.db).await?;
+    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+
+    fetch_item(&state.db, id).await.map(Json)
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::tools::models::{ToolCategory, ToolStatus};
+
+/// A tool model, with stock totals aggregated across every lab holding it
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogueItem {
+    pub id:                 i32,
+    pub name:               String,
+    pub category:           ToolCategory,
+    pub subcategory:        Option<String>,
+    pub unit:               String,
+    pub description:        Option<String>,
+    pub is_consumable:      bool,
+    pub consumable_type:    Option<String>,
+    pub replacement_cost_cents: Option<i64>,
+    pub lab_count:          i64,
+    pub total_quantity:     i64,
+    pub issued_quantity:    i64,
+    pub available_quantity: i64,
+    pub created_at:         DateTime<Utc>,
+    pub updated_at:         DateTime<Utc>,
+}
+
+/// One lab's stock line of a catalogue item (a `tools` row)
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Holding {
+    pub tool_id:             i32,
+    pub lab_id:              Option<i32>,
+    pub lab_name:            Option<String>,
+    pub quantity:            i32,
+    pub issued_qty:          i32,
+    pub available:           i32,
+    pub low_stock_threshold: i32,
+    pub status:              ToolStatus,
+}
+
+/// Composite response for GET /catalogue/:id
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogueItemDetail {
+    #[serde(flatten)]
+    pub item:     CatalogueItem,
+    pub holdings: Vec<Holding>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCatalogueItemRequest {
+    pub name:            Option<String>,
+    pub category:        Option<ToolCategory>,
+    pub subcategory:     Option<String>,
+    pub unit:            Option<String>,
+    pub description:     Option<String>,
+    pub is_consumable:   Option<bool>,
+    pub consumable_type: Option<String>,
+    pub replacement_cost_cents: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct CatalogueFilters {
+    pub category: Option<String>,
+    pub search:   Option<String>,
+}
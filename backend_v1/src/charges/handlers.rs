@@ -0,0 +1,276 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    charges::models::{
+        Charge, ChargeDetail, ChargeFilters, CreateChargeRequest, DecideWaiverRequest, NewCharge,
+        Payment, PaymentRequest, Waiver, WaiverRequest,
+    },
+    errors::{AppError, Result},
+    policy::handlers::evaluate,
+    state::AppState,
+};
+
+/// Roles that may approve or reject a requested waiver
+const WAIVER_APPROVER_ROLES: &[&str] = &["admin"];
+
+pub const CHARGE_SELECT: &str = r#"
+    SELECT c.id,c.student_id,c.kind,c.amount_cents,b.paid_cents,b.waived_cents,
+           b.outstanding_cents,c.description,c.delegation_id,c.damage_report_id,
+           c.created_by,c.created_at
+    FROM student_charges c
+    JOIN charge_balances b ON b.charge_id=c.id"#;
+
+async fn fetch_detail(db: &PgPool, id: i32) -> Result<ChargeDetail> {
+    let charge = sqlx::query_as::<_, Charge>(&format!("{} WHERE c.id=$1", CHARGE_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)?;
+    let payments = sqlx::query_as::<_, Payment>(
+        r#"SELECT id,charge_id,amount_cents,method,reference,receipt_attachment_id,received_by,received_at
+           FROM charge_payments WHERE charge_id=$1 ORDER BY received_at"#,
+    )
+    .bind(id).fetch_all(db).await?;
+    let waivers = sqlx::query_as::<_, Waiver>(
+        r#"SELECT id,charge_id,amount_cents,reason,status,requested_by,requested_at,decided_by,decided_at
+           FROM charge_waivers WHERE charge_id=$1 ORDER BY requested_at"#,
+    )
+    .bind(id).fetch_all(db).await?;
+    Ok(ChargeDetail { charge, payments, waivers })
+}
+
+/// Raises a charge against a student. Returns the charge id.
+pub async fn insert_charge(conn: &mut PgConnection, c: NewCharge<'_>) -> Result<i32> {
+    if c.amount_cents <= 0 { return Err(AppError::Validation("Charge amount must be positive".into())); }
+    let id = sqlx::query(
+        r#"INSERT INTO student_charges
+               (student_id,kind,amount_cents,description,delegation_id,damage_report_id,created_by)
+           VALUES ($1,$2,$3,$4,$5,$6,$7) RETURNING id"#,
+    )
+    .bind(c.student_id).bind(&c.kind).bind(c.amount_cents).bind(c.description)
+    .bind(c.delegation_id).bind(c.damage_report_id).bind(c.actor)
+    .fetch_one(&mut *conn).await?.try_get("id")?;
+    Ok(id)
+}
+
+/// Total a student still owes across all charges.
+pub async fn outstanding_balance(conn: &mut PgConnection, student_id: &str) -> Result<i64> {
+    Ok(sqlx::query(
+        "SELECT COALESCE(SUM(outstanding_cents),0)::BIGINT AS owed FROM charge_balances WHERE student_id=$1",
+    )
+    .bind(student_id).fetch_one(&mut *conn).await?.try_get("owed")?)
+}
+
+/// Per-unit replacement cost of a stock line: the catalogue item's cost, else
+/// the latest price paid for it on a purchase order.
+pub async fn replacement_cost(conn: &mut PgConnection, tool_id: i32) -> Result<Option<i64>> {
+    Ok(sqlx::query(
+        r#"SELECT COALESCE(ci.replacement_cost_cents, (
+                   SELECT pl.unit_cost_cents
+                   FROM purchase_order_lines pl
+                   JOIN purchase_orders po ON po.id=pl.purchase_order_id
+                   JOIN tools pt ON pt.id=pl.tool_id
+                   WHERE pt.catalogue_item_id=ci.id AND pl.unit_cost_cents > 0
+                   ORDER BY po.order_date DESC, pl.id DESC LIMIT 1)) AS cost
+           FROM tools t JOIN catalogue_items ci ON ci.id=t.catalogue_item_id
+           WHERE t.id=$1"#,
+    )
+    .bind(tool_id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?
+    .try_get("cost")?)
+}
+
+/// Locks a charge and returns its outstanding balance.
+async fn lock_outstanding(conn: &mut PgConnection, charge_id: i32) -> Result<i64> {
+    sqlx::query("SELECT id FROM student_charges WHERE id=$1 FOR UPDATE")
+        .bind(charge_id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
+    Ok(sqlx::query("SELECT outstanding_cents FROM charge_balances WHERE charge_id=$1")
+        .bind(charge_id).fetch_one(&mut *conn).await?.try_get("outstanding_cents")?)
+}
+
+async fn charge_student(conn: &mut PgConnection, charge_id: i32) -> Result<String> {
+    Ok(sqlx::query("SELECT student_id FROM student_charges WHERE id=$1")
+        .bind(charge_id).fetch_one(&mut *conn).await?.try_get("student_id")?)
+}
+
+/// Resolves the amount to settle: defaults to everything owed and may not
+/// exceed it.
+fn settle_amount(requested: Option<i64>, outstanding: i64) -> Result<i64> {
+    if outstanding <= 0 { return Err(AppError::Conflict("Charge is already settled".into())); }
+    let amount = requested.unwrap_or(outstanding);
+    if amount <= 0 || amount > outstanding {
+        return Err(AppError::Validation(format!("Amount must be between 1 and {} cents", outstanding)));
+    }
+    Ok(amount)
+}
+
+/// Records a (possibly partial) payment. Returns the balance left.
+pub async fn apply_payment(
+    conn: &mut PgConnection, charge_id: i32, body: &PaymentRequest, actor: &str,
+) -> Result<i64> {
+    let outstanding = lock_outstanding(conn, charge_id).await?;
+    let amount = settle_amount(body.amount_cents, outstanding)?;
+    // A receipt is a file uploaded against the loss the charge is for.
+    if let Some(receipt) = body.receipt_attachment_id {
+        if sqlx::query(
+            r#"SELECT a.id FROM attachments a JOIN student_charges c ON c.id=$2
+               WHERE a.id=$1 AND a.owner_type='Lost Tool' AND a.owner_id=c.delegation_id
+               FOR SHARE OF a"#,
+        )
+        .bind(receipt).bind(charge_id).fetch_optional(&mut *conn).await?.is_none() {
+            return Err(AppError::Validation(
+                "Receipt must be an attachment uploaded for this charge's lost tool".into(),
+            ));
+        }
+    }
+    sqlx::query(
+        r#"INSERT INTO charge_payments (charge_id,amount_cents,method,reference,receipt_attachment_id,received_by)
+           VALUES ($1,$2,$3,$4,$5,$6)"#,
+    )
+    .bind(charge_id).bind(amount).bind(&body.method).bind(&body.reference)
+    .bind(body.receipt_attachment_id).bind(actor)
+    .execute(&mut *conn).await?;
+    Ok(outstanding - amount)
+}
+
+/// Waives part or all of a charge straight away, for waivers that follow
+/// from another action (a recovered tool) rather than someone's discretion.
+/// Returns the balance left.
+pub async fn apply_waiver(
+    conn: &mut PgConnection, charge_id: i32, amount: Option<i64>, reason: &str, actor: &str,
+) -> Result<i64> {
+    if reason.trim().is_empty() { return Err(AppError::Validation("Waiver reason required".into())); }
+    let outstanding = lock_outstanding(conn, charge_id).await?;
+    let amount = settle_amount(amount, outstanding)?;
+    sqlx::query(
+        r#"INSERT INTO charge_waivers (charge_id,amount_cents,reason,status,requested_by,decided_by,decided_at)
+           VALUES ($1,$2,$3,'Approved',$4,$4,NOW())"#,
+    )
+    .bind(charge_id).bind(amount).bind(reason.trim()).bind(actor)
+    .execute(&mut *conn).await?;
+    Ok(outstanding - amount)
+}
+
+// GET /charges?student_id=&kind=&outstanding=true
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<ChargeFilters>,
+) -> Result<Json<Value>> {
+    let only_outstanding = filters.outstanding.as_deref() == Some("true");
+    let charges = sqlx::query_as::<_, Charge>(&format!(
+        "{} WHERE ($1::TEXT IS NULL OR c.student_id=$1)
+              AND ($2::TEXT IS NULL OR c.kind::text ILIKE $2)
+              AND (NOT $3 OR b.outstanding_cents > 0)
+            ORDER BY c.created_at DESC", CHARGE_SELECT,
+    ))
+    .bind(&filters.student_id).bind(&filters.kind).bind(only_outstanding)
+    .fetch_all(&state.db).await?;
+    let total_outstanding: i64 = charges.iter().map(|c| c.outstanding_cents).sum();
+    Ok(Json(json!({ "data": charges, "totalOutstandingCents": total_outstanding })))
+}
+
+// GET /charges/:id
+pub async fn get_one(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<ChargeDetail>> {
+    fetch_detail(&state.db, id).await.map(Json)
+}
+
+// POST /students/:id/charges
+pub async fn create(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(student_id): Path<String>, Json(body): Json<CreateChargeRequest>,
+) -> Result<(StatusCode, Json<ChargeDetail>)> {
+    if body.description.trim().is_empty() {
+        return Err(AppError::Validation("Charge description required".into()));
+    }
+    let student_id = student_id.trim().to_uppercase();
+    let mut tx = state.db.begin().await?;
+    if sqlx::query("SELECT student_id FROM students WHERE student_id=$1 AND deleted_at IS NULL")
+        .bind(&student_id).fetch_optional(&mut *tx).await?.is_none() { return Err(AppError::NotFound); }
+    // A charge may only point at one of the student's own loans.
+    if let Some(delegation_id) = body.delegation_id {
+        if sqlx::query("SELECT 1 FROM delegations WHERE id=$1 AND student_id=$2")
+            .bind(delegation_id).bind(&student_id).fetch_optional(&mut *tx).await?.is_none() {
+            return Err(AppError::Validation("Loan does not belong to this student".into()));
+        }
+    }
+
+    let id = insert_charge(&mut tx, NewCharge {
+        student_id: &student_id, kind: body.kind, amount_cents: body.amount_cents,
+        description: body.description.trim(), delegation_id: body.delegation_id,
+        damage_report_id: None, actor: &claims.sub,
+    }).await?;
+    evaluate(&mut tx, &student_id, &claims.sub).await?;
+    tx.commit().await?;
+    Ok((StatusCode::CREATED, Json(fetch_detail(&state.db, id).await?)))
+}
+
+// POST /charges/:id/payments
+pub async fn pay(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<PaymentRequest>,
+) -> Result<(StatusCode, Json<ChargeDetail>)> {
+    let mut tx = state.db.begin().await?;
+    apply_payment(&mut tx, id, &body, &claims.sub).await?;
+    let student_id = charge_student(&mut tx, id).await?;
+    evaluate(&mut tx, &student_id, &claims.sub).await?;
+    tx.commit().await?;
+    Ok((StatusCode::CREATED, Json(fetch_detail(&state.db, id).await?)))
+}
+
+// POST /charges/:id/waive — requests a waiver; someone else decides it
+pub async fn waive(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<WaiverRequest>,
+) -> Result<(StatusCode, Json<ChargeDetail>)> {
+    if body.reason.trim().is_empty() { return Err(AppError::Validation("Waiver reason required".into())); }
+    let mut tx = state.db.begin().await?;
+    let outstanding = lock_outstanding(&mut tx, id).await?;
+    let amount = settle_amount(body.amount_cents, outstanding)?;
+    sqlx::query("INSERT INTO charge_waivers (charge_id,amount_cents,reason,requested_by) VALUES ($1,$2,$3,$4)")
+        .bind(id).bind(amount).bind(body.reason.trim()).bind(&claims.sub)
+        .execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok((StatusCode::CREATED, Json(fetch_detail(&state.db, id).await?)))
+}
+
+// POST /charges/:id/waivers/:waiver_id/decide — an approved waiver reduces the balance
+pub async fn decide_waiver(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path((id, waiver_id)): Path<(i32, i32)>, Json(body): Json<DecideWaiverRequest>,
+) -> Result<Json<ChargeDetail>> {
+    if !WAIVER_APPROVER_ROLES.contains(&claims.role.as_str()) {
+        return Err(AppError::Forbidden("Only admins may decide waivers".into()));
+    }
+    let mut tx = state.db.begin().await?;
+    let outstanding = lock_outstanding(&mut tx, id).await?;
+    let row = sqlx::query(
+        "SELECT amount_cents,requested_by,status::text AS status FROM charge_waivers
+         WHERE id=$1 AND charge_id=$2 FOR UPDATE",
+    )
+    .bind(waiver_id).bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    if row.try_get::<String,_>("status")? != "Pending" {
+        return Err(AppError::Conflict("Waiver has already been decided".into()));
+    }
+    if row.try_get::<String,_>("requested_by")? == claims.sub {
+        return Err(AppError::Forbidden("A waiver cannot be decided by whoever requested it".into()));
+    }
+    let amount: i64 = row.try_get("amount_cents")?;
+    if body.approved && amount > outstanding {
+        return Err(AppError::Conflict(format!(
+            "Waiver is for {} cents but only {} are still owed; reject it and request a new one", amount, outstanding,
+        )));
+    }
+
+    let status = if body.approved { "Approved" } else { "Rejected" };
+    sqlx::query(
+        "UPDATE charge_waivers SET status=$1::waiver_status, decided_by=$2, decided_at=NOW() WHERE id=$3",
+    )
+    .bind(status).bind(&claims.sub).bind(waiver_id)
+    .execute(&mut *tx).await?;
+    if body.approved {
+        let student_id = charge_student(&mut tx, id).await?;
+        evaluate(&mut tx, &student_id, &claims.sub).await?;
+    }
+    tx.commit().await?;
+    Ok(Json(fetch_detail(&state.db, id).await?))
+}
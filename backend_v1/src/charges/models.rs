@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[sqlx(type_name = "charge_kind", rename_all = "PascalCase")]
+pub enum ChargeKind {
+    Damage,
+    Replacement,
+    #[serde(rename = "Late Fee")]
+    #[sqlx(rename = "Late Fee")]
+    LateFee,
+    Other,
+}
+
+/// A charge with its balance derived from payments and waivers
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Charge {
+    pub id:                i32,
+    pub student_id:        String,
+    pub kind:              ChargeKind,
+    pub amount_cents:      i64,
+    pub paid_cents:        i64,
+    pub waived_cents:      i64,
+    pub outstanding_cents: i64,
+    pub description:       String,
+    pub delegation_id:     Option<i32>,
+    pub damage_report_id:  Option<i32>,
+    pub created_by:        String,
+    pub created_at:        DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Payment {
+    pub id:               i32,
+    pub charge_id:        i32,
+    pub amount_cents:     i64,
+    pub method:           Option<String>,
+    pub reference:        Option<String>,
+    pub receipt_attachment_id: Option<i32>,
+    pub received_by:      String,
+    pub received_at:      DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[sqlx(type_name = "waiver_status", rename_all = "PascalCase")]
+pub enum WaiverStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Waiver {
+    pub id:           i32,
+    pub charge_id:    i32,
+    pub amount_cents: i64,
+    pub reason:       String,
+    pub status:       WaiverStatus,
+    pub requested_by: String,
+    pub requested_at: DateTime<Utc>,
+    pub decided_by:   Option<String>,
+    pub decided_at:   Option<DateTime<Utc>>,
+}
+
+/// Composite response for GET /charges/:id
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChargeDetail {
+    #[serde(flatten)]
+    pub charge:   Charge,
+    pub payments: Vec<Payment>,
+    pub waivers:  Vec<Waiver>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateChargeRequest {
+    pub kind:          ChargeKind,
+    pub amount_cents:  i64,
+    pub description:   String,
+    pub delegation_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct PaymentRequest {
+    /// Defaults to the full outstanding amount.
+    pub amount_cents:     Option<i64>,
+    pub method:           Option<String>,
+    pub reference:        Option<String>,
+    /// An uploaded receipt (see /attachments)
+    pub receipt_attachment_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WaiverRequest {
+    /// Defaults to the full outstanding amount.
+    pub amount_cents: Option<i64>,
+    pub reason:       String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DecideWaiverRequest {
+    pub approved: bool,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ChargeFilters {
+    pub student_id:  Option<String>,
+    pub kind:        Option<String>,
+    /// If "true", only charges with a balance still owed
+    pub outstanding: Option<String>,
+}
+
+/// A charge about to be raised. `amount_cents` must be positive.
+#[derive(Debug)]
+pub struct NewCharge<'a> {
+    pub student_id:       &'a str,
+    pub kind:             ChargeKind,
+    pub amount_cents:     i64,
+    pub description:      &'a str,
+    pub delegation_id:    Option<i32>,
+    pub damage_report_id: Option<i32>,
+    pub actor:            &'a str,
+}
@@ -0,0 +1,6 @@
+/// Letters and digits, upper-cased: "emt 2104" and "EMT-2104" are both
+/// EMT2104. Unit and cohort codes are compared this way, here and in the
+/// database triggers that fill `cohorts.code` and `students.units`.
+pub fn normalise(text: &str) -> String {
+    text.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_uppercase()).collect()
+}
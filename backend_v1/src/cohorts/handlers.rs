@@ -0,0 +1,464 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    codes,
+    cohorts::models::{
+        Cohort, CohortDetail, CohortFilters, CohortIssue, CohortIssueDetail, CohortIssueRequest,
+        CohortReturnRequest, CreateCohortRequest, MembersRequest, SkippedStudent, UpdateCohortRequest,
+    },
+    delegations::{
+        handlers::{check_borrower, insert_loan, return_loan, DELEGATION_SELECT},
+        models::{ConditionGrade, CreateDelegationRequest, Delegation, ReturnRequest},
+    },
+    errors::{AppError, Result},
+    lecturers::handlers::check_lecturer,
+    limits::handlers::{check_override, limits_hit},
+    state::AppState,
+    students::{handlers::STUDENT_COLUMNS, models::Student},
+};
+
+const COHORT_SELECT: &str = r#"
+    SELECT c.id,c.name,c.code,c.programme,c.year_of_study,c.intake,
+           c.class_rep_id,r.name AS class_rep_name,c.lecturer_id,l.name AS lecturer_name,
+           (SELECT COUNT(*) FROM students s WHERE s.cohort_id=c.id AND s.deleted_at IS NULL) AS member_count,
+           c.created_at
+    FROM cohorts c
+    LEFT JOIN students r ON r.student_id=c.class_rep_id
+    LEFT JOIN lecturers l ON l.id=c.lecturer_id"#;
+
+const ISSUE_SELECT: &str = r#"
+    SELECT i.id,i.cohort_id,c.name AS cohort_name,i.lecturer_id,l.name AS lecturer_name,
+           i.expected_return,i.notes,i.issued_by,i.issued_at,
+           COUNT(d.id) AS loan_count,
+           COUNT(d.id) FILTER (WHERE d.status IN ('Issued','Overdue')) AS outstanding_count
+    FROM cohort_issues i
+    JOIN cohorts c ON c.id=i.cohort_id
+    JOIN lecturers l ON l.id=i.lecturer_id
+    LEFT JOIN delegations d ON d.cohort_issue_id=i.id"#;
+
+async fn fetch_cohort(db: &PgPool, id: i32) -> Result<CohortDetail> {
+    let cohort = sqlx::query_as::<_, Cohort>(&format!("{} WHERE c.id=$1", COHORT_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)?;
+    let members = sqlx::query_as::<_, Student>(&format!(
+        "SELECT {} FROM students WHERE cohort_id=$1 AND deleted_at IS NULL ORDER BY name", STUDENT_COLUMNS,
+    ))
+    .bind(id).fetch_all(db).await?;
+    Ok(CohortDetail { cohort, members })
+}
+
+async fn fetch_issue(db: &PgPool, id: i32) -> Result<CohortIssue> {
+    sqlx::query_as::<_, CohortIssue>(&format!("{} WHERE i.id=$1 GROUP BY i.id,c.name,l.name", ISSUE_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+/// The class names in `names` that match no cohort, as given. Blank names
+/// match nothing and are not reported; they clear a student's class.
+pub async fn unknown_classes(conn: &mut PgConnection, names: &[String]) -> Result<Vec<String>> {
+    let normalised: Vec<String> = names.iter().map(|n| codes::normalise(n)).collect();
+    let known: HashSet<String> = sqlx::query_scalar("SELECT code FROM cohorts WHERE code=ANY($1)")
+        .bind(&normalised).fetch_all(&mut *conn).await?.into_iter().collect();
+    Ok(names.iter().zip(&normalised)
+        .filter(|(_, n)| !n.is_empty() && !known.contains(*n))
+        .map(|(c, _)| c.clone())
+        .collect())
+}
+
+/// Rejects a `class_name` that matches no cohort; classes are opened under
+/// /cohorts, so a misspelling does not start a new one.
+pub async fn check_class(conn: &mut PgConnection, name: Option<&str>) -> Result<()> {
+    let Some(name) = name else { return Ok(()) };
+    if !unknown_classes(conn, &[name.to_string()]).await?.is_empty() {
+        return Err(AppError::Validation(format!("Unknown class '{}'; create its cohort first", name.trim())));
+    }
+    Ok(())
+}
+
+/// A cohort name must normalise to a code no other cohort has.
+async fn check_name(conn: &mut PgConnection, name: &str, except: Option<i32>) -> Result<()> {
+    let code = codes::normalise(name);
+    if code.is_empty() { return Err(AppError::Validation("Cohort name needs letters or digits".into())); }
+    if name.trim().len() > 20 { return Err(AppError::Validation("Cohort name is longer than 20 characters".into())); }
+    let clash: Option<String> = sqlx::query_scalar(
+        "SELECT name FROM cohorts WHERE code=$1 AND ($2::INT IS NULL OR id<>$2)",
+    )
+    .bind(&code).bind(except).fetch_optional(&mut *conn).await?;
+    match clash {
+        Some(other) => Err(AppError::Conflict(format!("'{}' is the same class as existing cohort '{}'", name.trim(), other))),
+        None => Ok(()),
+    }
+}
+
+// GET /cohorts?programme=&search=
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<CohortFilters>,
+) -> Result<Json<Value>> {
+    let cohorts = sqlx::query_as::<_, Cohort>(&format!(
+        "{} WHERE ($1::TEXT IS NULL OR c.programme ILIKE $1)
+              AND ($2::TEXT IS NULL OR STRPOS(LOWER(c.name), LOWER($2)) > 0
+                   OR STRPOS(c.code, UPPER($2)) > 0)
+            ORDER BY c.programme, c.year_of_study NULLS LAST, c.name", COHORT_SELECT,
+    ))
+    .bind(&filters.programme).bind(&filters.search)
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": cohorts })))
+}
+
+// GET /cohorts/:id — with members
+pub async fn get_one(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<CohortDetail>> {
+    fetch_cohort(&state.db, id).await.map(Json)
+}
+
+// POST /cohorts
+pub async fn create(
+    _auth: AuthUser, State(state): State<AppState>, Json(body): Json<CreateCohortRequest>,
+) -> Result<(StatusCode, Json<CohortDetail>)> {
+    if body.programme.trim().is_empty() { return Err(AppError::Validation("Programme is required".into())); }
+    let mut tx = state.db.begin().await?;
+    check_name(&mut tx, &body.name, None).await?;
+    if let Some(lecturer_id) = body.lecturer_id { check_lecturer(&mut tx, lecturer_id).await?; }
+    let id: i32 = sqlx::query_scalar(
+        r#"INSERT INTO cohorts (name,programme,year_of_study,intake,lecturer_id)
+           VALUES ($1,$2,$3,$4,$5) RETURNING id"#,
+    )
+    .bind(body.name.trim()).bind(body.programme.trim()).bind(body.year_of_study)
+    .bind(&body.intake).bind(body.lecturer_id)
+    .fetch_one(&mut *tx).await?;
+    tx.commit().await?;
+    Ok((StatusCode::CREATED, Json(fetch_cohort(&state.db, id).await?)))
+}
+
+// PUT /cohorts/:id — a rename relabels every member
+pub async fn update(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<UpdateCohortRequest>,
+) -> Result<Json<CohortDetail>> {
+    if body.clear_class_rep && body.class_rep_id.is_some() || body.clear_lecturer && body.lecturer_id.is_some() {
+        return Err(AppError::Validation("Either set or clear the class rep and lecturer, not both".into()));
+    }
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SELECT id FROM cohorts WHERE id=$1 FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    if let Some(name) = &body.name { check_name(&mut tx, name, Some(id)).await?; }
+    if let Some(lecturer_id) = body.lecturer_id { check_lecturer(&mut tx, lecturer_id).await?; }
+    if let Some(rep) = &body.class_rep_id {
+        if sqlx::query("SELECT 1 FROM students WHERE student_id=$1 AND cohort_id=$2 AND deleted_at IS NULL")
+            .bind(rep).bind(id).fetch_optional(&mut *tx).await?.is_none() {
+            return Err(AppError::Validation(format!("{} is not a member of this cohort", rep)));
+        }
+    }
+
+    sqlx::query(
+        r#"UPDATE cohorts SET
+               name=COALESCE($1,name), programme=COALESCE($2,programme),
+               year_of_study=COALESCE($3,year_of_study), intake=COALESCE($4,intake),
+               class_rep_id=CASE WHEN $7 THEN NULL ELSE COALESCE($5,class_rep_id) END,
+               lecturer_id=CASE WHEN $8 THEN NULL ELSE COALESCE($6,lecturer_id) END
+           WHERE id=$9"#,
+    )
+    .bind(body.name.as_deref().map(str::trim)).bind(&body.programme).bind(body.year_of_study)
+    .bind(&body.intake).bind(&body.class_rep_id).bind(body.lecturer_id)
+    .bind(body.clear_class_rep).bind(body.clear_lecturer).bind(id)
+    .execute(&mut *tx).await?;
+    if body.name.is_some() {
+        sqlx::query("UPDATE students s SET class_name=c.name FROM cohorts c WHERE c.id=$1 AND s.cohort_id=c.id")
+            .bind(id).execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    Ok(Json(fetch_cohort(&state.db, id).await?))
+}
+
+// DELETE /cohorts/:id — only once it has no members or kit issues
+pub async fn delete(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SELECT id FROM cohorts WHERE id=$1 FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let uses = sqlx::query(
+        r#"SELECT (SELECT COUNT(*) FROM students WHERE cohort_id=$1) AS members,
+                  (SELECT COUNT(*) FROM cohort_issues WHERE cohort_id=$1) AS issues"#,
+    )
+    .bind(id).fetch_one(&mut *tx).await?;
+    let mut blockers = Vec::new();
+    let members: i64 = uses.try_get("members")?;
+    if members > 0 { blockers.push(format!("{} student(s) still in the cohort", members)); }
+    let issues: i64 = uses.try_get("issues")?;
+    if issues > 0 { blockers.push(format!("{} kit issue(s) on record", issues)); }
+    if !blockers.is_empty() {
+        return Err(AppError::Blocked("Cohort cannot be deleted".into(), blockers));
+    }
+    sqlx::query("DELETE FROM cohorts WHERE id=$1").bind(id).execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// POST /cohorts/:id/members — moves the students into this cohort
+pub async fn add_members(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<MembersRequest>,
+) -> Result<Json<CohortDetail>> {
+    if body.student_ids.is_empty() { return Err(AppError::Validation("student_ids is empty".into())); }
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SELECT id FROM cohorts WHERE id=$1 FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let found: HashSet<String> = sqlx::query_scalar(
+        "SELECT student_id FROM students WHERE student_id=ANY($1) AND deleted_at IS NULL",
+    )
+    .bind(&body.student_ids).fetch_all(&mut *tx).await?.into_iter().collect();
+    let unknown: Vec<&str> = body.student_ids.iter().filter(|s| !found.contains(*s)).map(String::as_str).collect();
+    if !unknown.is_empty() {
+        return Err(AppError::Validation(format!("Unknown students: {}", unknown.join(", "))));
+    }
+    // A rep who moves away stops being the rep of their old cohort.
+    sqlx::query(
+        "UPDATE cohorts SET class_rep_id=NULL WHERE id<>$1 AND class_rep_id=ANY($2)",
+    )
+    .bind(id).bind(&body.student_ids).execute(&mut *tx).await?;
+    sqlx::query("UPDATE students SET cohort_id=$1 WHERE student_id=ANY($2)")
+        .bind(id).bind(&body.student_ids).execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(Json(fetch_cohort(&state.db, id).await?))
+}
+
+// DELETE /cohorts/:id/members/:student_id
+pub async fn remove_member(
+    _auth: AuthUser, State(state): State<AppState>, Path((id, student_id)): Path<(i32, String)>,
+) -> Result<StatusCode> {
+    let mut tx = state.db.begin().await?;
+    let r = sqlx::query("UPDATE students SET cohort_id=NULL WHERE student_id=$1 AND cohort_id=$2")
+        .bind(&student_id).bind(id).execute(&mut *tx).await?;
+    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+    sqlx::query("UPDATE cohorts SET class_rep_id=NULL WHERE id=$1 AND class_rep_id=$2")
+        .bind(id).bind(&student_id).execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// GET /cohorts/:id/issues
+pub async fn list_issues(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<Value>> {
+    let issues = sqlx::query_as::<_, CohortIssue>(&format!(
+        "{} WHERE i.cohort_id=$1 GROUP BY i.id,c.name,l.name ORDER BY i.issued_at DESC", ISSUE_SELECT,
+    ))
+    .bind(id).fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": issues })))
+}
+
+// POST /cohorts/:id/issue — one loan per student and kit item, all in one
+// transaction
+pub async fn issue_kit(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<CohortIssueRequest>,
+) -> Result<(StatusCode, Json<CohortIssueDetail>)> {
+    if body.items.is_empty() { return Err(AppError::Validation("A kit needs at least one item".into())); }
+    check_override(&claims, body.override_reason.as_deref())?;
+    if body.items.iter().any(|i| i.quantity <= 0) {
+        return Err(AppError::Validation("Quantity must be >= 1".into()));
+    }
+    let mut tool_ids = HashSet::new();
+    if !body.items.iter().all(|i| tool_ids.insert(i.tool_id)) {
+        return Err(AppError::Validation("Each tool may appear once in a kit".into()));
+    }
+
+    let cohort_lecturer: Option<i32> = sqlx::query_scalar("SELECT lecturer_id FROM cohorts WHERE id=$1")
+        .bind(id).fetch_optional(&state.db).await?.ok_or(AppError::NotFound)?;
+    let unit_lecturer: Option<i32> = match body.unit_id {
+        Some(unit_id) => sqlx::query_scalar("SELECT lecturer_id FROM course_units WHERE id=$1")
+            .bind(unit_id).fetch_optional(&state.db).await?
+            .ok_or_else(|| AppError::Validation("Unit not found".into()))?,
+        None => None,
+    };
+    let lecturer_id = body.lecturer_id.or(unit_lecturer).or(cohort_lecturer).ok_or_else(|| AppError::Validation(
+        "lecturer_id required; the cohort has no assigned lecturer".into(),
+    ))?;
+    check_lecturer(&mut *state.db.acquire().await?, lecturer_id).await?;
+
+    let members = sqlx::query(
+        r#"SELECT student_id,name,account_status::TEXT AS account_status FROM students
+           WHERE cohort_id=$1 AND deleted_at IS NULL AND ($2::TEXT[] IS NULL OR student_id=ANY($2))
+           ORDER BY name"#,
+    )
+    .bind(id).bind(&body.student_ids).fetch_all(&state.db).await?;
+    if let Some(wanted) = &body.student_ids {
+        let listed: HashSet<String> = members.iter().map(|r| r.try_get("student_id")).collect::<sqlx::Result<_>>()?;
+        let outsiders: Vec<&str> = wanted.iter().filter(|s| !listed.contains(*s)).map(String::as_str).collect();
+        if !outsiders.is_empty() {
+            return Err(AppError::Validation(format!("Not members of this cohort: {}", outsiders.join(", "))));
+        }
+    }
+    // A kit goes to the active members; students named in `student_ids`
+    // are checked anyway, so a leaver or banned student there is reported.
+    let members: Vec<_> = members.into_iter()
+        .filter(|m| body.student_ids.is_some() || m.get::<String,_>("account_status") == "Active")
+        .collect();
+
+    // The kit's units per tool category, so borrowing limits see the whole
+    // kit rather than one item at a time.
+    let mut kit: Vec<(String, bool, i32)> = Vec::new();
+    for item in &body.items {
+        let tool = sqlx::query("SELECT category::TEXT AS category,is_consumable FROM tools WHERE id=$1 AND deleted_at IS NULL")
+            .bind(item.tool_id).fetch_optional(&state.db).await?
+            .ok_or_else(|| AppError::Validation(format!("Tool {} does not exist", item.tool_id)))?;
+        let (category, consumable): (String, bool) = (tool.try_get("category")?, tool.try_get("is_consumable")?);
+        match kit.iter_mut().find(|(c, k, _)| *c == category && *k == consumable) {
+            Some(line) => line.2 += item.quantity,
+            None => kit.push((category, consumable, item.quantity)),
+        }
+    }
+    let reusable: i32 = kit.iter().filter(|(_, k, _)| !k).map(|(_, _, q)| q).sum();
+
+    // Same borrower rules as a single issue; a ban found here still sticks.
+    let mut eligible = Vec::new();
+    let mut skipped = Vec::new();
+    let mut conn = state.db.acquire().await?;
+    for m in &members {
+        let (student_id, name): (String, String) = (m.try_get("student_id")?, m.try_get("name")?);
+        match check_borrower(&state, &student_id, &claims.sub).await {
+            Ok(()) => {}
+            Err(e @ (AppError::StudentBanned(_) | AppError::StudentSuspended(..)
+                | AppError::StudentInactive(_) | AppError::OutstandingBalance(_))) => {
+                skipped.push(SkippedStudent { student_id, name, reason: e.to_string() });
+                continue;
+            }
+            Err(e) => return Err(e),
+        }
+        if body.override_reason.is_none() {
+            let mut hits = Vec::new();
+            for (category, consumable, quantity) in &kit {
+                let alongside = if *consumable { 0 } else { reusable - quantity };
+                for hit in limits_hit(
+                    &mut conn, &student_id, category, *consumable, *quantity, alongside, body.expected_return,
+                ).await? {
+                    if !hits.contains(&hit.message) { hits.push(hit.message); }
+                }
+            }
+            if !hits.is_empty() {
+                skipped.push(SkippedStudent { student_id, name, reason: hits.join("; ") });
+                continue;
+            }
+        }
+        eligible.push(student_id);
+    }
+    drop(conn);
+    if !skipped.is_empty() && body.skip_ineligible != Some(true) {
+        return Err(AppError::Blocked(
+            "Some students cannot borrow; pass skip_ineligible to issue to the rest".into(),
+            skipped.iter().map(|s| format!("{} ({}): {}", s.name, s.student_id, s.reason)).collect(),
+        ));
+    }
+    if eligible.is_empty() { return Err(AppError::Validation("No students in the cohort can borrow".into())); }
+
+    let mut tx = state.db.begin().await?;
+    let students = eligible.len() as i32;
+    let mut shortages = Vec::new();
+    for item in &body.items {
+        let tool = sqlx::query(
+            "SELECT name, quantity-issued_qty-held_qty AS available FROM tools
+             WHERE id=$1 AND deleted_at IS NULL FOR UPDATE",
+        )
+        .bind(item.tool_id).fetch_optional(&mut *tx).await?
+        .ok_or_else(|| AppError::Validation(format!("Tool {} does not exist", item.tool_id)))?;
+        let available: i32 = tool.try_get("available")?;
+        if available < item.quantity * students {
+            shortages.push(format!(
+                "{}: {} needed, {} available", tool.try_get::<String,_>("name")?, item.quantity * students, available,
+            ));
+        }
+    }
+    if !shortages.is_empty() {
+        return Err(AppError::Blocked("Not enough stock to issue the kit to every student".into(), shortages));
+    }
+
+    let issue_id: i32 = sqlx::query_scalar(
+        r#"INSERT INTO cohort_issues (cohort_id,lecturer_id,expected_return,notes,issued_by)
+           VALUES ($1,$2,$3,$4,$5) RETURNING id"#,
+    )
+    .bind(id).bind(lecturer_id).bind(body.expected_return).bind(&body.notes).bind(&claims.sub)
+    .fetch_one(&mut *tx).await?;
+    for student_id in &eligible {
+        for item in &body.items {
+            insert_loan(&mut tx, &CreateDelegationRequest {
+                tool_id: item.tool_id, quantity: item.quantity, lecturer_id: Some(lecturer_id),
+                student_id: student_id.clone(), expected_return: body.expected_return,
+                expected_return_time: body.expected_return_time,
+                condition_before: body.condition_before.clone(),
+                is_inter_departmental: None, guest_department: None, guest_lab_project: None,
+                unit_id: body.unit_id, partner_department_id: None, guest_borrower_id: None,
+                override_reason: body.override_reason.clone(),
+            }, Some(issue_id), &claims.sub).await?;
+        }
+    }
+    tx.commit().await?;
+
+    let issue = fetch_issue(&state.db, issue_id).await?;
+    let loans = issue_loans(&state.db, issue_id).await?;
+    Ok((StatusCode::CREATED, Json(CohortIssueDetail { issue, loans, skipped })))
+}
+
+async fn issue_loans(db: &PgPool, issue_id: i32) -> Result<Vec<Delegation>> {
+    Ok(sqlx::query_as::<_, Delegation>(&format!(
+        "{} WHERE d.cohort_issue_id=$1 ORDER BY s.name, t.name", DELEGATION_SELECT,
+    ))
+    .bind(issue_id).fetch_all(db).await?)
+}
+
+// GET /cohort-issues/:id
+pub async fn get_issue(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<CohortIssueDetail>> {
+    let issue = fetch_issue(&state.db, id).await?;
+    let loans = issue_loans(&state.db, id).await?;
+    Ok(Json(CohortIssueDetail { issue, loans, skipped: Vec::new() }))
+}
+
+// POST /cohort-issues/:id/return — returns every outstanding loan of the kit
+pub async fn return_kit(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<CohortReturnRequest>,
+) -> Result<Json<Value>> {
+    let condition = body.condition_after.unwrap_or(ConditionGrade::Good);
+    if condition == ConditionGrade::Damaged {
+        return Err(AppError::Validation("List damaged loans under exceptions, with their damage details".into()));
+    }
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SELECT id FROM cohort_issues WHERE id=$1")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let outstanding: Vec<i32> = sqlx::query_scalar(
+        r#"SELECT id FROM delegations
+           WHERE cohort_issue_id=$1 AND status IN ('Issued','Overdue')
+           ORDER BY id FOR UPDATE"#,
+    )
+    .bind(id).fetch_all(&mut *tx).await?;
+    if outstanding.is_empty() {
+        return Err(AppError::Conflict("Every loan in this kit issue is already back".into()));
+    }
+    let mut exceptions = body.exceptions.unwrap_or_default();
+    if let Some(e) = exceptions.iter().find(|e| !outstanding.contains(&e.delegation_id)) {
+        return Err(AppError::Validation(format!(
+            "Delegation #{} is not an outstanding loan of this kit issue", e.delegation_id,
+        )));
+    }
+
+    let mut results = Vec::new();
+    for loan in outstanding {
+        let details = match exceptions.iter().position(|e| e.delegation_id == loan) {
+            Some(i) => exceptions.swap_remove(i).details,
+            None => ReturnRequest { condition_after: condition.clone(), mark_as_lost: false, damage: None },
+        };
+        results.push(return_loan(&mut tx, &state.config, loan, &details, &claims.sub).await?);
+    }
+    tx.commit().await?;
+    Ok(Json(json!({ "cohortIssueId": id, "returned": results.len(), "data": results })))
+}
@@ -0,0 +1,145 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    delegations::models::{ConditionGrade, Delegation, ReturnRequest},
+    students::models::Student,
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Cohort {
+    pub id:             i32,
+    /// Class name as shown, e.g. "MEC 2A"; also cached on each member
+    pub name:           String,
+    /// Normalised name; names with the same code are the same class
+    pub code:           String,
+    pub programme:      String,
+    pub year_of_study:  Option<i16>,
+    pub intake:         Option<String>,
+    pub class_rep_id:   Option<String>,
+    pub class_rep_name: Option<String>,
+    pub lecturer_id:    Option<i32>,
+    pub lecturer_name:  Option<String>,
+    pub member_count:   i64,
+    pub created_at:     DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CohortDetail {
+    #[serde(flatten)]
+    pub cohort:  Cohort,
+    pub members: Vec<Student>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCohortRequest {
+    pub name:          String,
+    pub programme:     String,
+    pub year_of_study: Option<i16>,
+    pub intake:        Option<String>,
+    pub lecturer_id:   Option<i32>,
+}
+
+/// The class rep must already be a member. `clear_class_rep` and
+/// `clear_lecturer` remove them.
+#[derive(Debug, Deserialize)]
+pub struct UpdateCohortRequest {
+    pub name:            Option<String>,
+    pub programme:       Option<String>,
+    pub year_of_study:   Option<i16>,
+    pub intake:          Option<String>,
+    pub class_rep_id:    Option<String>,
+    pub lecturer_id:     Option<i32>,
+    #[serde(default)]
+    pub clear_class_rep: bool,
+    #[serde(default)]
+    pub clear_lecturer:  bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MembersRequest {
+    pub student_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct CohortFilters {
+    pub programme: Option<String>,
+    pub search:    Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KitItem {
+    pub tool_id:  i32,
+    /// Units per student
+    pub quantity: i32,
+}
+
+/// Issues the same kit to every active member (or the listed ones).
+#[derive(Debug, Deserialize)]
+pub struct CohortIssueRequest {
+    pub items:                Vec<KitItem>,
+    /// Defaults to the unit's lecturer, then the cohort's
+    pub lecturer_id:          Option<i32>,
+    /// Course unit the kit is for; every student must be enrolled in it
+    pub unit_id:              Option<i32>,
+    pub expected_return:      NaiveDate,
+    pub expected_return_time: Option<NaiveTime>,
+    pub condition_before:     ConditionGrade,
+    pub student_ids:          Option<Vec<String>>,
+    /// Issue to the students who may borrow and report the rest, instead of
+    /// refusing the whole kit
+    pub skip_ineligible:      Option<bool>,
+    pub notes:                Option<String>,
+    /// Issues past borrowing limits (lecturers and admins only)
+    pub override_reason:      Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CohortIssue {
+    pub id:                i32,
+    pub cohort_id:         i32,
+    pub cohort_name:       String,
+    pub lecturer_id:       i32,
+    pub lecturer_name:     String,
+    pub expected_return:   NaiveDate,
+    pub notes:             Option<String>,
+    pub issued_by:         String,
+    pub issued_at:         DateTime<Utc>,
+    pub loan_count:        i64,
+    /// Loans still Issued or Overdue
+    pub outstanding_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedStudent {
+    pub student_id: String,
+    pub name:       String,
+    pub reason:     String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CohortIssueDetail {
+    #[serde(flatten)]
+    pub issue:   CohortIssue,
+    pub loans:   Vec<Delegation>,
+    pub skipped: Vec<SkippedStudent>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoanReturn {
+    pub delegation_id: i32,
+    #[serde(flatten)]
+    pub details:       ReturnRequest,
+}
+
+/// Returns every outstanding loan of a kit issue in one go.
+#[derive(Debug, Deserialize)]
+pub struct CohortReturnRequest {
+    /// Recorded for every loan not listed in `exceptions`; defaults to Good
+    pub condition_after: Option<ConditionGrade>,
+    /// Loans coming back in a different condition, damaged or lost
+    pub exceptions:      Option<Vec<LoanReturn>>,
+}
@@ -1,4 +1,6 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
 
 #[derive(Clone, Debug)]
 pub struct AppConfig {
@@ -6,6 +8,18 @@ pub struct AppConfig {
     pub jwt_secret:       String,
     pub jwt_expiry_hours: i64,
     pub port:             u16,
+    /// Charged per day a delegation is returned late; 0 disables late fees.
+    pub late_fee_cents_per_day: i64,
+    /// Students owing more than this cannot borrow; unset disables the block.
+    pub loan_block_balance_cents: Option<i64>,
+    /// Root directory for uploaded attachments (local storage backend).
+    pub upload_dir:       String,
+    pub max_upload_bytes: usize,
+    /// Days a deleted record stays in the trash before it is purged.
+    pub trash_retention_days: i32,
+    /// Zone the institution keeps its calendar in. Loan dates, due dates and
+    /// the overdue sweep follow it; database sessions are set to it.
+    pub timezone:         Tz,
 }
 
 impl AppConfig {
@@ -23,6 +37,39 @@ impl AppConfig {
                 .unwrap_or_else(|_| "8080".into())
                 .parse()
                 .context("PORT must be a number")?,
+            late_fee_cents_per_day: std::env::var("LATE_FEE_CENTS_PER_DAY")
+                .unwrap_or_else(|_| "0".into())
+                .parse()
+                .context("LATE_FEE_CENTS_PER_DAY must be a number")?,
+            loan_block_balance_cents: std::env::var("LOAN_BLOCK_BALANCE_CENTS")
+                .ok()
+                .map(|v| v.parse())
+                .transpose()
+                .context("LOAN_BLOCK_BALANCE_CENTS must be a number")?,
+            upload_dir: std::env::var("UPLOAD_DIR")
+                .unwrap_or_else(|_| "./uploads".into()),
+            max_upload_bytes: std::env::var("MAX_UPLOAD_BYTES")
+                .unwrap_or_else(|_| "10485760".into())
+                .parse()
+                .context("MAX_UPLOAD_BYTES must be a number")?,
+            trash_retention_days: std::env::var("TRASH_RETENTION_DAYS")
+                .unwrap_or_else(|_| "30".into())
+                .parse()
+                .context("TRASH_RETENTION_DAYS must be a number")?,
+            timezone: std::env::var("INSTITUTION_TIMEZONE")
+                .unwrap_or_else(|_| "UTC".into())
+                .parse()
+                .map_err(|e| anyhow!("INSTITUTION_TIMEZONE must be an IANA zone name: {}", e))?,
         })
     }
+
+    /// Today's date at the institution
+    pub fn today(&self) -> NaiveDate {
+        Utc::now().with_timezone(&self.timezone).date_naive()
+    }
+
+    /// A moment as shown to staff and students, in the institution's zone
+    pub fn local_time(&self, at: DateTime<Utc>) -> String {
+        at.with_timezone(&self.timezone).format("%Y-%m-%d %H:%M %Z").to_string()
+    }
 }
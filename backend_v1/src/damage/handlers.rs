@@ -0,0 +1,187 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    charges::{handlers::insert_charge, models::{ChargeKind, NewCharge}},
+    damage::models::{
+        ChargeDamageRequest, DamageDetails, DamageFilters, DamageReport, ResolveDamageRequest,
+    },
+    errors::{AppError, Result},
+    policy::handlers::evaluate,
+    state::AppState,
+    stock::{handlers::record_movement, models::{NewMovement, StockMovementKind}},
+    tools::handlers::refresh_status,
+};
+
+const REPORT_SELECT: &str = r#"
+    SELECT r.id,r.delegation_id,r.tool_id,t.name AS tool_name,l.name AS lab_name,
+           r.student_id,s.name AS student_name,r.quantity,r.description,r.photo_urls,
+           r.status,r.reported_by,r.reported_at,r.resolved_by,r.resolved_at,
+           r.resolution_notes,r.repair_cost_cents,
+           (SELECT COALESCE(SUM(c.amount_cents),0)::BIGINT FROM student_charges c
+             WHERE c.damage_report_id=r.id) AS charged_cents
+    FROM damage_reports r
+    JOIN tools t ON t.id=r.tool_id
+    LEFT JOIN labs l ON l.id=t.lab_id
+    LEFT JOIN students s ON s.student_id=r.student_id"#;
+
+async fn fetch_report(db: &PgPool, id: i32) -> Result<DamageReport> {
+    sqlx::query_as::<_, DamageReport>(&format!("{} WHERE r.id=$1", REPORT_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+/// Opens a report for a damaged return and moves the damaged units from the
+/// loan count into quarantine. Returns the report id.
+pub async fn open_report(
+    conn: &mut PgConnection, delegation_id: i32, tool_id: i32, borrower: &str,
+    loan_qty: i32, details: &DamageDetails, actor: &str,
+) -> Result<i32> {
+    if details.description.trim().is_empty() {
+        return Err(AppError::Validation("Damage description required".into()));
+    }
+    let quantity = details.quantity.unwrap_or(loan_qty);
+    if quantity <= 0 || quantity > loan_qty {
+        return Err(AppError::Validation(format!("Damaged quantity must be between 1 and {}", loan_qty)));
+    }
+    let student_id = details.responsible_student_id.as_deref()
+        .map(|s| s.trim().to_uppercase()).unwrap_or_else(|| borrower.to_string());
+    if sqlx::query("SELECT 1 FROM students WHERE student_id=$1 AND deleted_at IS NULL")
+        .bind(&student_id).fetch_optional(&mut *conn).await?.is_none()
+    {
+        return Err(AppError::Validation(format!("Responsible student {} not found", student_id)));
+    }
+
+    sqlx::query("UPDATE tools SET quarantined_qty=quarantined_qty+$1 WHERE id=$2")
+        .bind(quantity).bind(tool_id).execute(&mut *conn).await?;
+
+    let id: i32 = sqlx::query(
+        r#"INSERT INTO damage_reports
+               (delegation_id,tool_id,student_id,quantity,description,photo_urls,reported_by)
+           VALUES ($1,$2,$3,$4,$5,$6,$7) RETURNING id"#,
+    )
+    .bind(delegation_id).bind(tool_id).bind(&student_id).bind(quantity)
+    .bind(details.description.trim()).bind(&details.photo_urls).bind(actor)
+    .fetch_one(&mut *conn).await?.try_get("id")?;
+
+    if let Some(amount) = details.charge_cents {
+        raise_charge(conn, id, Some(delegation_id), &student_id, amount, None, actor).await?;
+    }
+    Ok(id)
+}
+
+async fn raise_charge(
+    conn: &mut PgConnection, report_id: i32, delegation_id: Option<i32>, student_id: &str,
+    amount_cents: i64, description: Option<&str>, actor: &str,
+) -> Result<i32> {
+    let description = description.map(str::to_string)
+        .unwrap_or_else(|| format!("Damage report #{}", report_id));
+    insert_charge(conn, NewCharge {
+        student_id, kind: ChargeKind::Damage, amount_cents, description: &description,
+        delegation_id, damage_report_id: Some(report_id), actor,
+    }).await
+}
+
+/// Locks a quarantined report; returns (tool_id, quantity).
+async fn lock_quarantined(conn: &mut PgConnection, id: i32) -> Result<(i32, i32)> {
+    let row = sqlx::query(
+        "SELECT tool_id,quantity,status::text AS status FROM damage_reports WHERE id=$1 FOR UPDATE",
+    )
+    .bind(id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
+    if row.try_get::<String,_>("status")? != "Quarantined" {
+        return Err(AppError::Conflict("Damage report is already resolved".into()));
+    }
+    let tool_id: i32 = row.try_get("tool_id")?;
+    let quantity: i32 = row.try_get("quantity")?;
+    sqlx::query("UPDATE tools SET quarantined_qty=GREATEST(0,quarantined_qty-$1) WHERE id=$2")
+        .bind(quantity).bind(tool_id).execute(&mut *conn).await?;
+    Ok((tool_id, quantity))
+}
+
+async fn close_report(
+    conn: &mut PgConnection, id: i32, status: &str, body: &ResolveDamageRequest, actor: &str,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE damage_reports SET status=$1::damage_status, resolution_notes=$2,
+               repair_cost_cents=$3, resolved_by=$4, resolved_at=NOW()
+           WHERE id=$5"#,
+    )
+    .bind(status).bind(&body.notes).bind(body.repair_cost_cents).bind(actor).bind(id)
+    .execute(&mut *conn).await?;
+    Ok(())
+}
+
+// GET /damage-reports?status=&student_id=&tool_id=
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<DamageFilters>,
+) -> Result<Json<Value>> {
+    let reports = sqlx::query_as::<_, DamageReport>(&format!(
+        "{} WHERE ($1::TEXT IS NULL OR r.status::text ILIKE $1)
+              AND ($2::TEXT IS NULL OR r.student_id=$2)
+              AND ($3::INT IS NULL OR r.tool_id=$3)
+            ORDER BY r.reported_at DESC", REPORT_SELECT,
+    ))
+    .bind(&filters.status).bind(&filters.student_id).bind(filters.tool_id)
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": reports })))
+}
+
+// GET /damage-reports/:id
+pub async fn get_one(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<DamageReport>> {
+    fetch_report(&state.db, id).await.map(Json)
+}
+
+// POST /damage-reports/:id/repair — releases the units back to stock
+pub async fn repair(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<ResolveDamageRequest>,
+) -> Result<Json<DamageReport>> {
+    if body.repair_cost_cents.is_some_and(|c| c < 0) {
+        return Err(AppError::Validation("Repair cost cannot be negative".into()));
+    }
+    let mut tx = state.db.begin().await?;
+    let (tool_id, _) = lock_quarantined(&mut tx, id).await?;
+    refresh_status(&mut tx, tool_id).await?;
+    close_report(&mut tx, id, "Repaired", &body, &claims.sub).await?;
+    tx.commit().await?;
+    Ok(Json(fetch_report(&state.db, id).await?))
+}
+
+// POST /damage-reports/:id/write-off — removes the units from stock
+pub async fn write_off(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<ResolveDamageRequest>,
+) -> Result<Json<DamageReport>> {
+    let mut tx = state.db.begin().await?;
+    let (tool_id, quantity) = lock_quarantined(&mut tx, id).await?;
+    record_movement(&mut tx, NewMovement {
+        tool_id, kind: StockMovementKind::WrittenOff, delta: -quantity,
+        reason: &format!("Written off after damage report #{}", id),
+        actor: &claims.sub, delegation_id: None,
+    }).await?;
+    close_report(&mut tx, id, "Written Off", &body, &claims.sub).await?;
+    tx.commit().await?;
+    Ok(Json(fetch_report(&state.db, id).await?))
+}
+
+// POST /damage-reports/:id/charge — charges the responsible student
+pub async fn charge(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<ChargeDamageRequest>,
+) -> Result<(StatusCode, Json<DamageReport>)> {
+    let mut tx = state.db.begin().await?;
+    let report = sqlx::query("SELECT delegation_id,student_id FROM damage_reports WHERE id=$1")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let student_id: String = report.try_get::<Option<String>,_>("student_id")?
+        .ok_or_else(|| AppError::Validation("Damage report has no responsible student".into()))?;
+    raise_charge(
+        &mut tx, id, report.try_get("delegation_id")?, &student_id,
+        body.amount_cents, body.description.as_deref(), &claims.sub,
+    ).await?;
+    evaluate(&mut tx, &student_id, &claims.sub).await?;
+    tx.commit().await?;
+    Ok((StatusCode::CREATED, Json(fetch_report(&state.db, id).await?)))
+}
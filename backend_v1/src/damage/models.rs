@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[sqlx(type_name = "damage_status", rename_all = "PascalCase")]
+pub enum DamageStatus {
+    Quarantined,
+    Repaired,
+    #[serde(rename = "Written Off")]
+    #[sqlx(rename = "Written Off")]
+    WrittenOff,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct DamageReport {
+    pub id:                i32,
+    pub delegation_id:     Option<i32>,
+    pub tool_id:           i32,
+    pub tool_name:         String,
+    pub lab_name:          Option<String>,
+    pub student_id:        Option<String>,
+    pub student_name:      Option<String>,
+    pub quantity:          i32,
+    pub description:       String,
+    pub photo_urls:        Vec<String>,
+    pub status:            DamageStatus,
+    pub reported_by:       String,
+    pub reported_at:       DateTime<Utc>,
+    pub resolved_by:       Option<String>,
+    pub resolved_at:       Option<DateTime<Utc>>,
+    pub resolution_notes:  Option<String>,
+    pub repair_cost_cents: Option<i64>,
+    pub charged_cents:     i64,
+}
+
+/// Damage details supplied with a return whose `condition_after` is Damaged.
+#[derive(Debug, Deserialize, Default)]
+pub struct DamageDetails {
+    pub description:            String,
+    /// Defaults to the whole delegation quantity.
+    pub quantity:               Option<i32>,
+    #[serde(default)]
+    pub photo_urls:             Vec<String>,
+    /// Defaults to the borrowing student.
+    pub responsible_student_id: Option<String>,
+    /// Raises a Damage charge against the responsible student when set.
+    pub charge_cents:           Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveDamageRequest {
+    pub notes:             Option<String>,
+    pub repair_cost_cents: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChargeDamageRequest {
+    pub amount_cents: i64,
+    pub description:  Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct DamageFilters {
+    pub status:     Option<String>,
+    pub student_id: Option<String>,
+    pub tool_id:    Option<i32>,
+}
@@ -1,166 +1,305 @@
-use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
-use chrono::Utc;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde_json::{json, Value};
-use sqlx::Row;
+use sqlx::{postgres::PgArguments, PgConnection, Row};
 
 use crate::{
     auth::middleware::AuthUser,
-    delegations::models::{CreateDelegationRequest, Delegation, DelegationFilters, ReturnRequest},
+    calendar::handlers::check_due,
+    charges::{
+        handlers::{insert_charge, outstanding_balance, replacement_cost},
+        models::{ChargeKind, NewCharge},
+    },
+    config::AppConfig,
+    damage::{handlers::open_report, models::DamageDetails},
+    delegations::models::{
+        ConditionGrade, CreateDelegationRequest, Delegation, DelegationFilters, ReturnRequest,
+    },
     errors::{AppError, Result},
+    export::{self, bind, Export},
+    lecturers::handlers::check_lecturer,
+    limits::handlers::{check_override, limits_hit},
+    partners::handlers::guest_loan,
+    policy::handlers::evaluate,
     state::AppState,
+    stock::{handlers::record_movement, models::{NewMovement, StockMovementKind}},
+    students::models::AccountStatus,
     tools::handlers::compute_status,
+    units::handlers::unit_for_loan,
 };
 
+pub const DELEGATION_SELECT: &str = r#"
+    SELECT d.id,d.tool_id,t.name AS tool_name,d.quantity,
+           d.lecturer_id,l.name AS lecturer_name,d.student_id,
+           s.name AS student_name,s.class_name,d.date_issued,
+           d.expected_return,d.expected_return_time,d.date_returned,
+           d.checked_out_at,d.due_at,d.returned_at,d.status,
+           d.condition_before,d.condition_after,d.is_inter_departmental,
+           d.guest_department,d.guest_lab_project,d.resolution,d.cohort_issue_id,
+           d.unit_id,u.code AS unit_code,d.partner_department_id,
+           d.guest_borrower_id,g.name AS guest_borrower_name,d.overridden_limits,
+           d.limit_override_reason,d.limit_override_by,d.created_at
+    FROM delegations d
+    JOIN tools t ON t.id=d.tool_id
+    JOIN lecturers l ON l.id=d.lecturer_id
+    JOIN students s ON s.student_id=d.student_id
+    LEFT JOIN course_units u ON u.id=d.unit_id
+    LEFT JOIN guest_borrowers g ON g.id=d.guest_borrower_id"#;
+
+// GET /delegations?status=&student_id=&lecturer_id=&search=&inter_dept=&partner_department_id= — also
+// exported as CSV/XLSX
 pub async fn list(
     _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<DelegationFilters>,
-) -> Result<Json<Value>> {
-    let delegations = sqlx::query_as::<_, Delegation>(
-        r#"SELECT d.id,d.tool_id,t.name AS tool_name,d.quantity,
-                  d.lecturer_id,l.name AS lecturer_name,d.student_id,
-                  s.name AS student_name,s.class_name,d.date_issued,
-                  d.expected_return,d.expected_return_time,d.date_returned,
-                  d.actual_checkout_time,d.actual_return_time,d.status,
-                  d.condition_before,d.condition_after,d.is_inter_departmental,
-                  d.guest_department,d.guest_lab_project,d.resolution,d.created_at
-           FROM delegations d
-           JOIN tools t ON t.id=d.tool_id
-           JOIN lecturers l ON l.id=d.lecturer_id
-           JOIN students s ON s.student_id=d.student_id
-           ORDER BY d.created_at DESC"#,
-    )
-    .fetch_all(&state.db).await?;
-
-    let filtered: Vec<&Delegation> = delegations.iter().filter(|d| {
-        if let Some(ref st) = filters.status {
-            let vs = format!("{:?}", d.status);
-            if !vs.eq_ignore_ascii_case(st) { return false; }
-        }
-        if let Some(ref sid) = filters.student_id {
-            if !d.student_id.eq_ignore_ascii_case(sid) { return false; }
-        }
-        if let Some(lid) = filters.lecturer_id { if d.lecturer_id != lid { return false; } }
-        if let Some(ref q) = filters.search {
-            let ql = q.to_lowercase();
-            if !d.student_name.to_lowercase().contains(&ql) &&
-               !d.tool_name.to_lowercase().contains(&ql) &&
-               !d.student_id.to_lowercase().contains(&ql) { return false; }
-        }
-        if filters.inter_dept.as_deref() == Some("true") {
-            if !d.is_inter_departmental { return false; }
-        }
-        true
-    }).collect();
+    export: Export,
+) -> Result<Response> {
+    let sql = format!(
+        r#"{} WHERE ($1::TEXT IS NULL OR LOWER(d.status::text)=LOWER($1))
+             AND ($2::TEXT IS NULL OR UPPER(d.student_id)=UPPER($2))
+             AND ($3::INT IS NULL OR d.lecturer_id=$3)
+             AND ($4::TEXT IS NULL OR STRPOS(LOWER(s.name), LOWER($4)) > 0
+                  OR STRPOS(LOWER(t.name), LOWER($4)) > 0
+                  OR STRPOS(LOWER(d.student_id), LOWER($4)) > 0)
+             AND (NOT $5 OR d.is_inter_departmental)
+             AND ($6::INT IS NULL OR d.partner_department_id=$6)
+           ORDER BY d.created_at DESC"#, DELEGATION_SELECT,
+    );
+    let mut args = PgArguments::default();
+    bind(&mut args, filters.status)?;
+    bind(&mut args, filters.student_id)?;
+    bind(&mut args, filters.lecturer_id)?;
+    bind(&mut args, filters.search)?;
+    bind(&mut args, filters.inter_dept.as_deref() == Some("true"))?;
+    bind(&mut args, filters.partner_department_id)?;
+    if let Some(format) = export.0 {
+        return export::rows::<Delegation>(state.db, sql, args, format, "delegations").await;
+    }
 
-    Ok(Json(json!({ "data": filtered })))
+    let delegations = sqlx::query_as_with::<_, Delegation, _>(&sql, args).fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": delegations })).into_response())
 }
 
 pub async fn get_one(
     _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
 ) -> Result<Json<Delegation>> {
-    sqlx::query_as::<_, Delegation>(
-        r#"SELECT d.id,d.tool_id,t.name AS tool_name,d.quantity,
-                  d.lecturer_id,l.name AS lecturer_name,d.student_id,
-                  s.name AS student_name,s.class_name,d.date_issued,
-                  d.expected_return,d.expected_return_time,d.date_returned,
-                  d.actual_checkout_time,d.actual_return_time,d.status,
-                  d.condition_before,d.condition_after,d.is_inter_departmental,
-                  d.guest_department,d.guest_lab_project,d.resolution,d.created_at
-           FROM delegations d
-           JOIN tools t ON t.id=d.tool_id
-           JOIN lecturers l ON l.id=d.lecturer_id
-           JOIN students s ON s.student_id=d.student_id
-           WHERE d.id=$1"#,
-    )
+    sqlx::query_as::<_, Delegation>(&format!("{} WHERE d.id=$1", DELEGATION_SELECT))
     .bind(id).fetch_optional(&state.db).await?.ok_or(AppError::NotFound).map(Json)
 }
 
-pub async fn issue(
-    _auth: AuthUser, State(state): State<AppState>, Json(body): Json<CreateDelegationRequest>,
-) -> Result<(StatusCode, Json<Value>)> {
-    if body.quantity <= 0 { return Err(AppError::Validation("Quantity must be >= 1".into())); }
-    let is_inter = body.is_inter_departmental.unwrap_or(false);
-    if is_inter && (body.guest_department.is_none() || body.guest_lab_project.is_none()) {
-        return Err(AppError::Validation(
-            "guest_department and guest_lab_project required for inter-departmental borrows".into(),
-        ));
+/// Refuses a loan to a student who is in the trash, inactive, banned,
+/// suspended or over the balance limit. The policy evaluation commits on its
+/// own so a ban it imposes sticks even though the loan is refused.
+pub async fn check_borrower(state: &AppState, student_id: &str, actor: &str) -> Result<()> {
+    if sqlx::query("SELECT student_id FROM students WHERE student_id=$1 AND deleted_at IS NULL")
+        .bind(student_id).fetch_optional(&state.db).await?.is_none() {
+        return Err(AppError::NotFound);
     }
 
-    let mut tx = state.db.begin().await?;
+    let mut ptx = state.db.begin().await?;
+    let standing = evaluate(&mut ptx, student_id, actor).await?;
+    ptx.commit().await?;
+    match standing.account_status {
+        AccountStatus::Active => {}
+        AccountStatus::Graduated => return Err(AppError::StudentInactive("graduated".into())),
+        AccountStatus::Archived  => return Err(AppError::StudentInactive("archived".into())),
+        AccountStatus::Banned | AccountStatus::Suspended => {
+            let stu = sqlx::query("SELECT ban_reason,suspended_until FROM students WHERE student_id=$1")
+                .bind(student_id).fetch_one(&state.db).await?;
+            let reason = stu.try_get::<Option<String>,_>("ban_reason")?
+                .unwrap_or_else(|| "no reason recorded".into());
+            return Err(match stu.try_get::<Option<chrono::DateTime<Utc>>,_>("suspended_until")? {
+                Some(until) => AppError::StudentSuspended(state.config.local_time(until), reason),
+                None        => AppError::StudentBanned(reason),
+            });
+        }
+    }
 
-    // 1. Check student not banned
-    let stu = sqlx::query(
-        "SELECT account_status::text AS account_status FROM students WHERE student_id=$1",
-    )
-    .bind(&body.student_id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
-    if stu.try_get::<String,_>("account_status")?.as_str() == "Banned" {
-        return Err(AppError::StudentBanned);
+    if let Some(limit) = state.config.loan_block_balance_cents {
+        let owed = outstanding_balance(&mut *state.db.acquire().await?, student_id).await?;
+        if owed > limit { return Err(AppError::OutstandingBalance(owed)); }
     }
+    Ok(())
+}
 
-    // 2. Lock tool and check stock
+/// A loan written by `insert_loan`
+pub struct IssuedLoan {
+    pub id:             i32,
+    pub checked_out_at: DateTime<Utc>,
+    /// Units of the tool still available afterwards
+    pub remaining_qty:  i32,
+    /// Borrowing limits issued past with an override
+    pub overridden_limits: Vec<String>,
+}
+
+/// Locks the tool, checks stock and the student's unit enrolment, and
+/// records the loan, taking its units out of stock. Borrower checks are the
+/// caller's job, as is settling `lecturer_id`.
+pub async fn insert_loan(
+    conn: &mut PgConnection, body: &CreateDelegationRequest, cohort_issue_id: Option<i32>, actor: &str,
+) -> Result<IssuedLoan> {
     let tool = sqlx::query(
-        "SELECT id,quantity,issued_qty,is_consumable,low_stock_threshold FROM tools WHERE id=$1 FOR UPDATE",
+        "SELECT id,quantity,issued_qty,maintenance_qty,quarantined_qty,is_consumable,low_stock_threshold,
+                category::TEXT AS category,lab_id
+         FROM tools WHERE id=$1 AND deleted_at IS NULL FOR UPDATE",
     )
-    .bind(body.tool_id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    .bind(body.tool_id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
 
     let t_qty:   i32  = tool.try_get("quantity")?;
     let t_iss:   i32  = tool.try_get("issued_qty")?;
+    let t_maint: i32  = tool.try_get("maintenance_qty")?;
+    let t_quar:  i32  = tool.try_get("quarantined_qty")?;
     let t_cons:  bool = tool.try_get("is_consumable")?;
     let t_thr:   i32  = tool.try_get("low_stock_threshold")?;
+    check_due(conn, tool.try_get("lab_id")?, body.expected_return, body.expected_return_time).await?;
 
-    if (t_qty - t_iss) < body.quantity { return Err(AppError::InsufficientStock); }
+    let unit = unit_for_loan(
+        conn, body.tool_id, &body.student_id, body.unit_id, body.is_inter_departmental.unwrap_or(false),
+    ).await?;
+    // The unit the tool is borrowed for supplies the lecturer if none is given.
+    let lecturer_id = match body.lecturer_id {
+        Some(id) => id,
+        None => {
+            let id = unit.as_ref().and_then(|u| u.lecturer_id).ok_or_else(|| AppError::Validation(
+                "lecturer_id required; the unit has no assigned lecturer".into(),
+            ))?;
+            check_lecturer(conn, id).await?;
+            id
+        }
+    };
+    let guest = match body.is_inter_departmental {
+        Some(true) => Some(guest_loan(conn, body).await?),
+        _ => None,
+    };
 
-    // 3. Update quantities
-    let new_issued = if t_cons {
-        sqlx::query("UPDATE tools SET quantity=quantity-$1 WHERE id=$2")
-            .bind(body.quantity).bind(body.tool_id).execute(&mut *tx).await?;
-        t_iss
-    } else {
-        sqlx::query("UPDATE tools SET issued_qty=issued_qty+$1 WHERE id=$2")
-            .bind(body.quantity).bind(body.tool_id).execute(&mut *tx).await?;
-        t_iss + body.quantity
+    // Borrowing limits stop the loan unless the caller overrides them.
+    let hits = limits_hit(
+        conn, &body.student_id, &tool.try_get::<String,_>("category")?, t_cons,
+        body.quantity, 0, body.expected_return,
+    ).await?;
+    if let (Some(hit), None) = (hits.first(), &body.override_reason) {
+        return Err(AppError::BorrowingLimit(hit.limit.into(), hit.message.clone()));
+    }
+    let overridden: Vec<String> = hits.into_iter().map(|h| h.limit.to_string()).collect();
+    let (override_reason, override_by) = match overridden.is_empty() {
+        true  => (None, None),
+        false => (body.override_reason.as_deref().map(str::trim), Some(actor)),
     };
-    let new_qty    = if t_cons { t_qty - body.quantity } else { t_qty };
-    let new_status = compute_status(new_qty, new_issued, t_thr);
-    sqlx::query("UPDATE tools SET status=$1::tool_status WHERE id=$2")
-        .bind(new_status).bind(body.tool_id).execute(&mut *tx).await?;
 
-    // 4. Insert delegation
+    let available = t_qty - t_iss - t_maint - t_quar;
+    if available < body.quantity {
+        // Say why when the shortfall is units held for maintenance or quarantine.
+        if t_maint > 0 && available + t_maint >= body.quantity {
+            return Err(AppError::UnderMaintenance);
+        }
+        if t_quar > 0 && available + t_maint + t_quar >= body.quantity {
+            return Err(AppError::Quarantined);
+        }
+        return Err(AppError::InsufficientStock);
+    }
+
     let condition_str = body.condition_before.to_string();
     let row = sqlx::query(
         r#"INSERT INTO delegations
                (tool_id,quantity,lecturer_id,student_id,expected_return,
                 expected_return_time,condition_before,is_inter_departmental,
-                guest_department,guest_lab_project)
-           VALUES ($1,$2,$3,$4,$5,$6,$7::condition_grade,$8,$9,$10)
-           RETURNING id, actual_checkout_time"#,
+                guest_department,guest_lab_project,cohort_issue_id,unit_id,
+                partner_department_id,guest_borrower_id,overridden_limits,
+                limit_override_reason,limit_override_by,due_at)
+           VALUES ($1,$2,$3,$4,$5,$6,$7::condition_grade,$8,$9,$10,$11,$12,$13,$14,$15,$16,$17,
+                   -- Due at the given time, else at the end of the day
+                   COALESCE($5::DATE + $6::TIME, ($5::DATE + 1)::TIMESTAMP)::TIMESTAMPTZ)
+           RETURNING id, checked_out_at"#,
     )
-    .bind(body.tool_id).bind(body.quantity).bind(body.lecturer_id).bind(&body.student_id)
+    .bind(body.tool_id).bind(body.quantity).bind(lecturer_id).bind(&body.student_id)
     .bind(body.expected_return).bind(body.expected_return_time)
-    .bind(&condition_str).bind(is_inter)
-    .bind(&body.guest_department).bind(&body.guest_lab_project)
-    .fetch_one(&mut *tx).await?;
+    .bind(&condition_str).bind(body.is_inter_departmental.unwrap_or(false))
+    .bind(guest.as_ref().map(|g| &g.department_name)).bind(&body.guest_lab_project)
+    .bind(cohort_issue_id).bind(unit.map(|u| u.id))
+    .bind(guest.as_ref().map(|g| g.department_id)).bind(guest.as_ref().and_then(|g| g.guest_borrower_id))
+    .bind((!overridden.is_empty()).then_some(&overridden)).bind(override_reason).bind(override_by)
+    .fetch_one(&mut *conn).await?;
+    let delegation_id: i32 = row.try_get("id")?;
+
+    // Consumables leave stock through the ledger
+    let (new_qty, new_issued) = if t_cons {
+        let m = record_movement(conn, NewMovement {
+            tool_id: body.tool_id, kind: StockMovementKind::IssuedConsumed,
+            delta: -body.quantity, reason: &format!("Issued on delegation #{}", delegation_id),
+            actor, delegation_id: Some(delegation_id),
+        }).await?;
+        (m.quantity_after, t_iss)
+    } else {
+        let new_issued = t_iss + body.quantity;
+        sqlx::query("UPDATE tools SET issued_qty=$1, status=$2::tool_status WHERE id=$3")
+            .bind(new_issued).bind(compute_status(t_qty, new_issued, t_maint, t_quar, t_thr)).bind(body.tool_id)
+            .execute(&mut *conn).await?;
+        (t_qty, new_issued)
+    };
 
+    // Loan-count maintenance intervals advance with every issue.
+    sqlx::query(
+        "UPDATE maintenance_plans SET loans_since=loans_since+1 WHERE tool_id=$1 AND is_active",
+    )
+    .bind(body.tool_id).execute(&mut *conn).await?;
+
+    Ok(IssuedLoan {
+        id: delegation_id,
+        checked_out_at: row.try_get("checked_out_at")?,
+        remaining_qty: new_qty - new_issued - t_maint - t_quar,
+        overridden_limits: overridden,
+    })
+}
+
+pub async fn issue(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Json(mut body): Json<CreateDelegationRequest>,
+) -> Result<(StatusCode, Json<Value>)> {
+    if body.quantity <= 0 { return Err(AppError::Validation("Quantity must be >= 1".into())); }
+    check_override(&claims, body.override_reason.as_deref())?;
+    // Naming a partner or guest makes the loan inter-departmental.
+    if body.partner_department_id.is_some() || body.guest_borrower_id.is_some() {
+        body.is_inter_departmental = Some(true);
+    }
+    let is_inter = body.is_inter_departmental.unwrap_or(false);
+    if is_inter && body.guest_lab_project.is_none() {
+        return Err(AppError::Validation("guest_lab_project required for inter-departmental borrows".into()));
+    }
+    if let Some(lecturer_id) = body.lecturer_id {
+        check_lecturer(&mut *state.db.acquire().await?, lecturer_id).await?;
+    }
+    check_borrower(&state, &body.student_id, &claims.sub).await?;
+
+    let mut tx = state.db.begin().await?;
+    let loan = insert_loan(&mut tx, &body, None, &claims.sub).await?;
     tx.commit().await?;
     Ok((StatusCode::CREATED, Json(json!({
-        "id":                 row.try_get::<i32,_>("id")?,
+        "id":                 loan.id,
         "status":             "Issued",
-        "actualCheckoutTime": row.try_get::<chrono::NaiveTime,_>("actual_checkout_time")?.to_string(),
-        "toolRemainingQty":   new_qty - new_issued,
+        "checkedOutAt":       loan.checked_out_at,
+        "toolRemainingQty":   loan.remaining_qty,
+        "overriddenLimits":   loan.overridden_limits,
     }))))
 }
 
-pub async fn return_tool(
-    _auth: AuthUser, State(state): State<AppState>,
-    Path(id): Path<i32>, Json(body): Json<ReturnRequest>,
-) -> Result<Json<Value>> {
-    let mut tx = state.db.begin().await?;
-
+/// Returns a loan, or marks it lost. Lost units raise a replacement charge,
+/// late returns a fee, and damaged units go to quarantine; the student's
+/// standing is re-evaluated either way.
+pub async fn return_loan(
+    conn: &mut PgConnection, config: &AppConfig, id: i32, body: &ReturnRequest, actor: &str,
+) -> Result<Value> {
     let del = sqlx::query(
-        "SELECT tool_id,student_id,quantity FROM delegations
-         WHERE id=$1 AND status IN ('Issued'::delegation_status,'Overdue'::delegation_status)
-         FOR UPDATE",
+        r#"SELECT d.tool_id,d.student_id,d.quantity,
+                  CEIL(EXTRACT(EPOCH FROM NOW() - d.due_at
+                       - (next_open_day(t.lab_id,d.expected_return) - d.expected_return) * INTERVAL '1 day')
+                       / 86400)::BIGINT AS days_late
+           FROM delegations d JOIN tools t ON t.id=d.tool_id
+           WHERE d.id=$1 AND d.status IN ('Issued'::delegation_status,'Overdue'::delegation_status)
+           FOR UPDATE OF d"#,
     )
-    .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    .bind(id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
 
     let tool_id:    i32    = del.try_get("tool_id")?;
     let student_id: String = del.try_get("student_id")?;
@@ -169,63 +308,129 @@ pub async fn return_tool(
 
     if body.mark_as_lost {
         sqlx::query(
-            "UPDATE delegations SET status='Lost'::delegation_status, condition_after=$1::condition_grade WHERE id=$2",
+            "UPDATE delegations SET status='Lost'::delegation_status, condition_after=$1::condition_grade, lost_at=NOW()
+             WHERE id=$2",
         )
-        .bind(&condition_str).bind(id).execute(&mut *tx).await?;
+        .bind(&condition_str).bind(id).execute(&mut *conn).await?;
+
+        // Lost reusable units leave the loan count and are written out of
+        // stock; lost consumables already left through their issue movement.
+        let is_consumable: bool = sqlx::query("SELECT is_consumable FROM tools WHERE id=$1")
+            .bind(tool_id).fetch_one(&mut *conn).await?.try_get("is_consumable")?;
+        if !is_consumable {
+            sqlx::query("UPDATE tools SET issued_qty=GREATEST(0,issued_qty-$1) WHERE id=$2")
+                .bind(quantity).bind(tool_id).execute(&mut *conn).await?;
+            record_movement(conn, NewMovement {
+                tool_id, kind: StockMovementKind::Lost, delta: -quantity,
+                reason: &format!("Lost on delegation #{}", id),
+                actor, delegation_id: Some(id),
+            }).await?;
+        }
 
-        let row = sqlx::query(
+        // Without a known cost the charge is raised when the student pays.
+        let charge_id = match replacement_cost(conn, tool_id).await? {
+            Some(cost) if cost > 0 => Some(insert_charge(conn, NewCharge {
+                student_id: &student_id, kind: ChargeKind::Replacement,
+                amount_cents: cost * i64::from(quantity),
+                description: &format!("Replacement for delegation #{}", id),
+                delegation_id: Some(id), damage_report_id: None, actor,
+            }).await?),
+            _ => None,
+        };
+
+        let ltc: i32 = sqlx::query(
             "UPDATE students SET lost_tool_count=lost_tool_count+1 WHERE student_id=$1
-             RETURNING lost_tool_count, account_status::text AS account_status",
+             RETURNING lost_tool_count",
         )
-        .bind(&student_id).fetch_one(&mut *tx).await?;
-
-        tx.commit().await?;
-        let ltc: i32    = row.try_get("lost_tool_count")?;
-        let ast: String = row.try_get("account_status")?;
-        return Ok(Json(json!({
+        .bind(&student_id).fetch_one(&mut *conn).await?.try_get("lost_tool_count")?;
+        let standing = evaluate(conn, &student_id, actor).await?;
+        return Ok(json!({
             "id":                   id,
             "status":               "Lost",
             "studentLostToolCount": ltc,
-            "studentAccountStatus": ast,
-            "message": if ast == "Banned" {
-                "Student has been automatically banned (5+ lost tools)"
-            } else { "Delegation marked as lost" }
-        })));
+            "studentAccountStatus": standing.account_status,
+            "chargeId":             charge_id,
+            "message": match standing.ban_reason() {
+                Some(reason) => format!("Student is banned: {}", reason),
+                None         => "Delegation marked as lost".to_string(),
+            }
+        }));
     }
 
     // Normal return
-    let now = Utc::now();
-    sqlx::query("UPDATE tools SET issued_qty=GREATEST(0,issued_qty-$1) WHERE id=$2")
-        .bind(quantity).bind(tool_id).execute(&mut *tx).await?;
+    let is_consumable: bool = sqlx::query(
+        "UPDATE tools SET issued_qty=GREATEST(0,issued_qty-$1) WHERE id=$2 RETURNING is_consumable",
+    )
+    .bind(quantity).bind(tool_id).fetch_one(&mut *conn).await?.try_get("is_consumable")?;
+
+    // Damaged reusable units go to quarantine instead of back on the shelf.
+    // Without details the report covers the whole loan, charged to no one yet.
+    let damage_report_id = if body.condition_after == ConditionGrade::Damaged && !is_consumable {
+        let unspecified = DamageDetails {
+            description: "Returned damaged; no details given".into(), ..Default::default()
+        };
+        let details = body.damage.as_ref().unwrap_or(&unspecified);
+        Some(open_report(conn, id, tool_id, &student_id, quantity, details, actor).await?)
+    } else { None };
+
+    // Each started day past the due instant counts; a due day the lab has
+    // since closed on moves to its next open day.
+    let days_late: i64 = del.try_get("days_late")?;
+    let late_fee_id = if days_late > 0 && config.late_fee_cents_per_day > 0 {
+        Some(insert_charge(conn, NewCharge {
+            student_id: &student_id, kind: ChargeKind::LateFee,
+            amount_cents: days_late * config.late_fee_cents_per_day,
+            description: &format!("Returned {} day(s) late on delegation #{}", days_late, id),
+            delegation_id: Some(id), damage_report_id: None, actor,
+        }).await?)
+    } else { None };
 
     let tool_row = sqlx::query(
-        "SELECT quantity,issued_qty,low_stock_threshold FROM tools WHERE id=$1",
+        "SELECT quantity,issued_qty,maintenance_qty,quarantined_qty,low_stock_threshold FROM tools WHERE id=$1",
     )
-    .bind(tool_id).fetch_one(&mut *tx).await?;
+    .bind(tool_id).fetch_one(&mut *conn).await?;
     let tq: i32 = tool_row.try_get("quantity")?;
     let ti: i32 = tool_row.try_get("issued_qty")?;
+    let tm: i32 = tool_row.try_get("maintenance_qty")?;
+    let tz: i32 = tool_row.try_get("quarantined_qty")?;
     let tt: i32 = tool_row.try_get("low_stock_threshold")?;
-    let ns      = compute_status(tq, ti, tt);
+    let ns      = compute_status(tq, ti, tm, tz, tt);
 
     sqlx::query("UPDATE tools SET status=$1::tool_status WHERE id=$2")
-        .bind(ns).bind(tool_id).execute(&mut *tx).await?;
+        .bind(ns).bind(tool_id).execute(&mut *conn).await?;
 
-    sqlx::query(
+    let returned = sqlx::query(
         r#"UPDATE delegations SET
                status='Returned'::delegation_status,
                condition_after=$1::condition_grade,
-               actual_return_time=$2, date_returned=$3
-           WHERE id=$4"#,
+               returned_at=NOW(), date_returned=CURRENT_DATE
+           WHERE id=$2
+           RETURNING returned_at, date_returned"#,
     )
-    .bind(&condition_str).bind(now.time()).bind(now.date_naive()).bind(id)
-    .execute(&mut *tx).await?;
+    .bind(&condition_str).bind(id)
+    .fetch_one(&mut *conn).await?;
+    let returned_at: DateTime<Utc> = returned.try_get("returned_at")?;
+    let date_returned: NaiveDate = returned.try_get("date_returned")?;
 
-    tx.commit().await?;
-    Ok(Json(json!({
+    // Clearing an overdue loan or adding a fee can change the student's standing.
+    evaluate(conn, &student_id, actor).await?;
+    Ok(json!({
         "id":              id,
         "status":          "Returned",
-        "actualReturnTime": now.time().to_string(),
-        "dateReturned":    now.date_naive().to_string(),
-        "toolRestoredQty": tq - ti,
-    })))
+        "returnedAt":      returned_at,
+        "dateReturned":    date_returned,
+        "toolRestoredQty": tq - ti - tm - tz,
+        "damageReportId":  damage_report_id,
+        "lateFeeChargeId": late_fee_id,
+    }))
+}
+
+pub async fn return_tool(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<ReturnRequest>,
+) -> Result<Json<Value>> {
+    let mut tx = state.db.begin().await?;
+    let result = return_loan(&mut tx, &state.config, id, &body, &claims.sub).await?;
+    tx.commit().await?;
+    Ok(Json(result))
 }
@@ -1,19 +1,23 @@
 use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+use crate::damage::models::DamageDetails;
+
+#[derive(Debug, Default, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
 #[sqlx(type_name = "delegation_status", rename_all = "PascalCase")]
 pub enum DelegationStatus {
+    #[default]
     Issued,
     Returned,
     Overdue,
     Lost,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[derive(Debug, Default, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
 #[sqlx(type_name = "condition_grade", rename_all = "PascalCase")]
 pub enum ConditionGrade {
     Excellent,
+    #[default]
     Good,
     Fair,
     Damaged,
@@ -31,7 +35,7 @@ impl std::fmt::Display for ConditionGrade {
 }
 
 /// Full delegation row with joined names
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Default, Serialize, sqlx::FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct Delegation {
     pub id:                     i32,
@@ -47,8 +51,10 @@ pub struct Delegation {
     pub expected_return:        NaiveDate,
     pub expected_return_time:   Option<NaiveTime>,
     pub date_returned:          Option<NaiveDate>,
-    pub actual_checkout_time:   NaiveTime,
-    pub actual_return_time:     Option<NaiveTime>,
+    pub checked_out_at:         DateTime<Utc>,
+    /// End of the `expected_return` day, or its `expected_return_time`
+    pub due_at:                 DateTime<Utc>,
+    pub returned_at:            Option<DateTime<Utc>>,
     pub status:                 DelegationStatus,
     pub condition_before:       ConditionGrade,
     pub condition_after:        Option<ConditionGrade>,
@@ -56,6 +62,20 @@ pub struct Delegation {
     pub guest_department:       Option<String>,
     pub guest_lab_project:      Option<String>,
     pub resolution:             Option<String>,
+    /// Set when the loan was part of a kit issued to a whole cohort
+    pub cohort_issue_id:        Option<i32>,
+    /// The course unit the loan was issued for
+    pub unit_id:                Option<i32>,
+    pub unit_code:              Option<String>,
+    /// Registered partner of an inter-departmental loan; `guest_department`
+    /// holds its name
+    pub partner_department_id:  Option<i32>,
+    pub guest_borrower_id:      Option<i32>,
+    pub guest_borrower_name:    Option<String>,
+    /// Borrowing limits the loan was issued past, and who allowed it
+    pub overridden_limits:      Option<Vec<String>>,
+    pub limit_override_reason:  Option<String>,
+    pub limit_override_by:      Option<String>,
     pub created_at:             DateTime<Utc>,
 }
 
@@ -63,20 +83,36 @@ pub struct Delegation {
 pub struct CreateDelegationRequest {
     pub tool_id:                i32,
     pub quantity:               i32,
-    pub lecturer_id:            i32,
+    /// Defaults to the unit's lecturer
+    pub lecturer_id:            Option<i32>,
     pub student_id:             String,
     pub expected_return:        NaiveDate,
     pub expected_return_time:   Option<NaiveTime>,
     pub condition_before:       ConditionGrade,
     pub is_inter_departmental:  Option<bool>,
+    /// Matched to a registered partner by name when `partner_department_id`
+    /// is not given
     pub guest_department:       Option<String>,
     pub guest_lab_project:      Option<String>,
+    pub partner_department_id:  Option<i32>,
+    /// Person at the partner department the loan is for; implies the
+    /// department
+    pub guest_borrower_id:      Option<i32>,
+    /// Needed only when the student takes several units whose kits reserve
+    /// the tool
+    pub unit_id:                Option<i32>,
+    /// Issues past any borrowing limit the loan would break (lecturers and
+    /// admins only)
+    pub override_reason:        Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ReturnRequest {
     pub condition_after: ConditionGrade,
     pub mark_as_lost:    bool,
+    /// Details for a Damaged return; without them the report covers the
+    /// whole loan with a placeholder description.
+    pub damage:          Option<DamageDetails>,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -87,4 +123,5 @@ pub struct DelegationFilters {
     pub search:      Option<String>,
     /// If "true", only return inter-departmental delegations
     pub inter_dept:  Option<String>,
+    pub partner_department_id: Option<i32>,
 }
@@ -15,12 +15,31 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     Validation(String),
 
-    #[error("Student is banned (5+ lost tools). Cannot issue tools.")]
-    StudentBanned,
+    #[error("Student is banned: {0}. Cannot issue tools.")]
+    StudentBanned(String),
+
+    #[error("Student is suspended until {0}: {1}. Cannot issue tools.")]
+    StudentSuspended(String, String),
+
+    #[error("Student is {0}. Cannot issue tools.")]
+    StudentInactive(String),
 
     #[error("Insufficient stock available")]
     InsufficientStock,
 
+    #[error("Student owes {0} cents in unpaid charges. Cannot issue tools.")]
+    OutstandingBalance(i64),
+
+    #[error("Tool is under maintenance. Remaining units cannot be issued.")]
+    UnderMaintenance,
+
+    #[error("Tool is quarantined after damage. Remaining units cannot be issued.")]
+    Quarantined,
+
+    /// A borrowing limit the loan would break: the limit's name and why.
+    #[error("Borrowing limit reached: {1}")]
+    BorrowingLimit(String, String),
+
     #[error("Invalid credentials")]
     InvalidCredentials,
 
@@ -30,9 +49,22 @@ pub enum AppError {
     #[error("Unauthorized: missing or invalid token")]
     Unauthorized,
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Conflict: {0}")]
     Conflict(String),
 
+    /// A conflict caused by other records; each blocker is listed in the body.
+    #[error("{0}: {}", .1.join("; "))]
+    Blocked(String, Vec<String>),
+
+    #[error("File exceeds the {0}-byte upload limit")]
+    PayloadTooLarge(usize),
+
+    #[error("Unsupported file type: {0}")]
+    UnsupportedMediaType(String),
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
@@ -53,16 +85,52 @@ impl IntoResponse for AppError {
                 "VALIDATION_ERROR",
                 m.clone(),
             ),
-            AppError::StudentBanned => (
+            AppError::StudentBanned(_) => (
                 StatusCode::BAD_REQUEST,
                 "STUDENT_BANNED",
                 self.to_string(),
             ),
+            AppError::StudentSuspended(..) => (
+                StatusCode::BAD_REQUEST,
+                "STUDENT_SUSPENDED",
+                self.to_string(),
+            ),
+            AppError::StudentInactive(_) => (
+                StatusCode::BAD_REQUEST,
+                "STUDENT_INACTIVE",
+                self.to_string(),
+            ),
             AppError::InsufficientStock => (
                 StatusCode::BAD_REQUEST,
                 "INSUFFICIENT_STOCK",
                 self.to_string(),
             ),
+            AppError::OutstandingBalance(_) => (
+                StatusCode::BAD_REQUEST,
+                "OUTSTANDING_BALANCE",
+                self.to_string(),
+            ),
+            AppError::UnderMaintenance => (
+                StatusCode::BAD_REQUEST,
+                "UNDER_MAINTENANCE",
+                self.to_string(),
+            ),
+            AppError::Quarantined => (
+                StatusCode::BAD_REQUEST,
+                "QUARANTINED",
+                self.to_string(),
+            ),
+            AppError::BorrowingLimit(limit, _) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error":   "BORROWING_LIMIT",
+                        "message": self.to_string(),
+                        "limit":   limit,
+                    })),
+                )
+                    .into_response();
+            }
             AppError::InvalidCredentials => (
                 StatusCode::UNAUTHORIZED,
                 "INVALID_CREDENTIALS",
@@ -78,11 +146,37 @@ impl IntoResponse for AppError {
                 "UNAUTHORIZED",
                 self.to_string(),
             ),
+            AppError::Forbidden(m) => (
+                StatusCode::FORBIDDEN,
+                "FORBIDDEN",
+                m.clone(),
+            ),
             AppError::Conflict(m) => (
                 StatusCode::CONFLICT,
                 "CONFLICT",
                 m.clone(),
             ),
+            AppError::Blocked(m, blockers) => {
+                return (
+                    StatusCode::CONFLICT,
+                    Json(json!({
+                        "error":    "CONFLICT",
+                        "message":  m,
+                        "blockers": blockers,
+                    })),
+                )
+                    .into_response();
+            }
+            AppError::PayloadTooLarge(_) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "PAYLOAD_TOO_LARGE",
+                self.to_string(),
+            ),
+            AppError::UnsupportedMediaType(_) => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "UNSUPPORTED_MEDIA_TYPE",
+                self.to_string(),
+            ),
             AppError::Database(e) => {
                 tracing::error!("Database error: {}", e);
                 // Surface unique-violation as a cleaner error
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgListener, PgPool};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::analytics::{handlers::fetch_overview, models::OverviewStats};
+
+/// Postgres channel the notify triggers publish on (see migration 0006).
+pub const CHANNEL: &str = "toolport_events";
+
+/// A change pushed to dashboards. `kind` is one of
+/// `issued`, `returned`, `lost`, `overdue` or `stock_changed`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardEvent {
+    pub kind:          String,
+    pub tool_id:       Option<i32>,
+    pub delegation_id: Option<i32>,
+    pub student_id:    Option<String>,
+    pub quantity:      Option<i32>,
+    pub issued_qty:    Option<i32>,
+}
+
+/// A batch of changes plus the overview totals refreshed after them,
+/// computed once for every subscriber. `overview` is `None` if the refresh
+/// failed.
+#[derive(Debug, Clone)]
+pub struct LiveUpdate {
+    pub events:   Vec<DashboardEvent>,
+    pub overview: Option<OverviewStats>,
+}
+
+/// How long to collect notifications after the first one before refreshing
+/// the overview. Bulk operations fire one trigger per row; batching them keeps
+/// streams from falling behind a queue of identical refreshes.
+const BATCH_WINDOW: Duration = Duration::from_millis(250);
+
+pub fn channel() -> broadcast::Sender<LiveUpdate> {
+    broadcast::channel(256).0
+}
+
+/// LISTENs on `CHANNEL` and fans notifications out to every subscribed
+/// stream. Reconnects on failure so a DB restart doesn't kill live updates.
+pub fn spawn_listener(db: PgPool, tx: broadcast::Sender<LiveUpdate>) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = listen(&db, &tx).await {
+                tracing::error!("Event listener failed: {}", e);
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    });
+}
+
+async fn listen(db: &PgPool, tx: &broadcast::Sender<LiveUpdate>) -> sqlx::Result<()> {
+    let mut listener = PgListener::connect_with(db).await?;
+    listener.listen(CHANNEL).await?;
+    tracing::info!("Listening for dashboard events on '{}'", CHANNEL);
+
+    loop {
+        let mut batch = vec![listener.recv().await?];
+        let deadline = tokio::time::Instant::now() + BATCH_WINDOW;
+        while let Ok(n) = tokio::time::timeout_at(deadline, listener.recv()).await {
+            batch.push(n?);
+        }
+
+        // No subscribers is not an error — nobody has a dashboard open.
+        if tx.receiver_count() == 0 { continue; }
+        let events: Vec<DashboardEvent> = batch.iter()
+            .filter_map(|n| serde_json::from_str(n.payload())
+                .map_err(|e| tracing::warn!("Ignoring malformed event payload: {}", e))
+                .ok())
+            .collect();
+        if events.is_empty() { continue; }
+        let overview = match fetch_overview(db).await {
+            Ok(stats) => Some(stats),
+            Err(e) => {
+                tracing::error!("Overview refresh failed: {}", e);
+                None
+            }
+        };
+        let _ = tx.send(LiveUpdate { events, overview });
+    }
+}
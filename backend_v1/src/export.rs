@@ -0,0 +1,183 @@
+use axum::{
+    async_trait,
+    body::Body,
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use rust_xlsxwriter::{Format, Workbook};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{postgres::{PgArguments, PgRow}, Arguments, FromRow, PgPool};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::errors::{AppError, Result};
+
+const CSV_TYPE:  &str = "text/csv";
+const XLSX_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+}
+
+/// Extractor for the spreadsheet a list request asks for: `?format=csv|xlsx`,
+/// else an `Accept` header naming CSV or XLSX. `None` means the JSON view.
+pub struct Export(pub Option<ExportFormat>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Export {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> std::result::Result<Self, AppError> {
+        let format = parts.uri.query().unwrap_or("").split('&')
+            .find_map(|pair| pair.strip_prefix("format="));
+        if let Some(format) = format {
+            return match format.to_ascii_lowercase().as_str() {
+                "csv"  => Ok(Export(Some(ExportFormat::Csv))),
+                "xlsx" => Ok(Export(Some(ExportFormat::Xlsx))),
+                "json" => Ok(Export(None)),
+                other  => Err(AppError::Validation(format!("Unknown export format '{}'", other))),
+            };
+        }
+        let accept = parts.headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()).unwrap_or("");
+        Ok(Export(if accept.contains(CSV_TYPE) { Some(ExportFormat::Csv) }
+                  else if accept.contains(XLSX_TYPE) { Some(ExportFormat::Xlsx) }
+                  else { None }))
+    }
+}
+
+/// Adds a bind parameter to a list query's arguments.
+pub fn bind<'q, T>(args: &mut PgArguments, value: T) -> Result<()>
+where T: 'q + Send + sqlx::Encode<'q, sqlx::Postgres> + sqlx::Type<sqlx::Postgres> {
+    args.add(value).map_err(|e| AppError::Internal(anyhow::anyhow!(e)))
+}
+
+/// An XLSX export is built in memory, so it stops at this many rows.
+const MAX_XLSX_ROWS: u32 = 50_000;
+
+/// Spreadsheet text for a JSON value: lists are joined with "; ". Text that
+/// a spreadsheet would read as a formula gets a leading `'`.
+fn cell_text(value: &Value) -> String {
+    let text = plain_text(value);
+    let is_text = matches!(value, Value::String(_) | Value::Array(_));
+    if is_text && text.starts_with(['=', '+', '-', '@', '\t', '\r']) { format!("'{}", text) } else { text }
+}
+
+fn plain_text(value: &Value) -> String {
+    match value {
+        Value::Null      => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(a)  => a.iter().map(plain_text).collect::<Vec<_>>().join("; "),
+        other            => other.to_string(),
+    }
+}
+
+/// Flattens a row into (column, value) pairs in field order.
+fn columns<T: Serialize>(row: &T) -> Result<Vec<(String, Value)>> {
+    match serde_json::to_value(row).map_err(anyhow::Error::from)? {
+        Value::Object(map) => Ok(map.into_iter().collect()),
+        _ => Err(AppError::Internal(anyhow::anyhow!("export rows must serialise to objects"))),
+    }
+}
+
+fn csv_record(fields: impl IntoIterator<Item = String>) -> Result<Vec<u8>> {
+    let mut w = csv::Writer::from_writer(Vec::new());
+    w.write_record(fields).map_err(anyhow::Error::from)?;
+    w.into_inner().map_err(|e| AppError::Internal(anyhow::anyhow!(e.to_string())))
+}
+
+fn attachment(content_type: &str, name: &str, ext: &str, body: Body) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.{}\"", name, ext)),
+        ],
+        body,
+    ).into_response()
+}
+
+/// Runs a list query and sends its rows as a spreadsheet, one column per
+/// JSON field; the header comes from the row type, so an empty result still
+/// has one. CSV is streamed as rows arrive; an XLSX file is a zip, so it is
+/// assembled from the row stream before sending, up to `MAX_XLSX_ROWS`.
+pub async fn rows<T>(
+    db: PgPool, sql: String, args: PgArguments, format: ExportFormat, name: &str,
+) -> Result<Response>
+where T: for<'r> FromRow<'r, PgRow> + Serialize + Default + Send + Unpin + 'static {
+    let header: Vec<String> = columns(&T::default())?.into_iter().map(|(k, _)| k).collect();
+    match format {
+        ExportFormat::Csv => {
+            let (tx, rx) = mpsc::channel::<std::result::Result<Vec<u8>, std::io::Error>>(64);
+            let first = csv_record(header)?;
+            tokio::spawn(async move {
+                if tx.send(Ok(first)).await.is_err() { return; }
+                let mut stream = sqlx::query_as_with::<_, T, _>(&sql, args).fetch(&db);
+                while let Some(row) = stream.next().await {
+                    let chunk = row.map_err(AppError::from).and_then(|row| {
+                        csv_record(columns(&row)?.iter().map(|(_, v)| cell_text(v)))
+                    });
+                    let failed = chunk.is_err();
+                    let sent = tx.send(chunk.map_err(|e| std::io::Error::other(e.to_string()))).await;
+                    // Stop on a query error or once the client has gone away.
+                    if failed || sent.is_err() { break; }
+                }
+            });
+            Ok(attachment(CSV_TYPE, name, "csv", Body::from_stream(ReceiverStream::new(rx))))
+        }
+        ExportFormat::Xlsx => {
+            let mut book = Workbook::new();
+            let sheet = book.add_worksheet();
+            let bold = Format::new().set_bold();
+            for (c, key) in header.iter().enumerate() {
+                sheet.write_string_with_format(0, c as u16, key, &bold).map_err(anyhow::Error::from)?;
+            }
+            let mut stream = sqlx::query_as_with::<_, T, _>(&sql, args).fetch(&db);
+            let mut r: u32 = 0;
+            while let Some(row) = stream.next().await {
+                let cols = columns(&row?)?;
+                r += 1;
+                if r > MAX_XLSX_ROWS {
+                    return Err(AppError::Validation(format!(
+                        "XLSX exports are limited to {} rows; narrow the filters or export CSV",
+                        MAX_XLSX_ROWS,
+                    )));
+                }
+                for (c, (_, value)) in cols.iter().enumerate() {
+                    let written = match value {
+                        Value::Number(n) => sheet.write_number(r, c as u16, n.as_f64().unwrap_or_default()),
+                        Value::Bool(b)   => sheet.write_boolean(r, c as u16, *b),
+                        other            => sheet.write_string(r, c as u16, cell_text(other)),
+                    };
+                    written.map_err(anyhow::Error::from)?;
+                }
+            }
+            let bytes = book.save_to_buffer().map_err(anyhow::Error::from)?;
+            Ok(attachment(XLSX_TYPE, name, "xlsx", Body::from(bytes)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn formula_like_text_is_escaped() {
+        assert_eq!(cell_text(&json!("=HYPERLINK(\"x\")")), "'=HYPERLINK(\"x\")");
+        assert_eq!(cell_text(&json!("+254700")), "'+254700");
+        assert_eq!(cell_text(&json!("@SUM(A1)")), "'@SUM(A1)");
+        assert_eq!(cell_text(&json!(["-1", "ok"])), "'-1; ok");
+        assert_eq!(cell_text(&json!(["ok", "-1"])), "ok; -1");
+        assert_eq!(cell_text(&json!("Jane")), "Jane");
+    }
+
+    #[test]
+    fn numbers_are_not_escaped() {
+        assert_eq!(cell_text(&json!(-5)), "-5");
+    }
+}
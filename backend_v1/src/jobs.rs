@@ -1,6 +1,12 @@
 use sqlx::PgPool;
 use std::time::Duration;
 
+use crate::{
+    policy::handlers::{evaluate_all, expire_suspensions},
+    state::AppState,
+    trash::handlers::purge,
+};
+
 pub fn spawn_overdue_checker(db: PgPool) {
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(Duration::from_secs(3_600));
@@ -10,15 +16,80 @@ pub fn spawn_overdue_checker(db: PgPool) {
                 Ok(n)  => tracing::info!("Overdue sweep: {} delegation(s) updated", n),
                 Err(e) => tracing::error!("Overdue sweep failed: {}", e),
             }
+            match expire_suspensions(&db).await {
+                Ok(n)  => tracing::info!("Suspension sweep: {} suspension(s) expired", n),
+                Err(e) => tracing::error!("Suspension sweep failed: {}", e),
+            }
+            // New overdue loans may push students over the ban policy.
+            match evaluate_all(&db, "system").await {
+                Ok(n)  => tracing::info!("Ban policy sweep: {} student(s) changed status", n),
+                Err(e) => tracing::error!("Ban policy sweep failed: {}", e),
+            }
         }
     });
 }
 
+/// Loans whose due day has since become a holiday or closing day fall due on
+/// the lab's next open day instead.
 async fn mark_overdue(db: &PgPool) -> sqlx::Result<u64> {
     let result = sqlx::query(
-        "UPDATE delegations SET status='Overdue'::delegation_status
-         WHERE status='Issued'::delegation_status AND expected_return < CURRENT_DATE",
+        "UPDATE delegations d SET status='Overdue'::delegation_status
+         FROM tools t
+         WHERE t.id=d.tool_id AND d.status='Issued'::delegation_status AND d.due_at <= NOW()
+           AND d.due_at + (next_open_day(t.lab_id,d.expected_return) - d.expected_return)
+                          * INTERVAL '1 day' <= NOW()",
     )
     .execute(db).await?;
     Ok(result.rows_affected())
 }
+
+pub fn spawn_maintenance_checker(db: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(3_600));
+        loop {
+            interval.tick().await;
+            match flag_due_maintenance(&db).await {
+                Ok(n)  => tracing::info!("Maintenance sweep: {} plan(s) newly due", n),
+                Err(e) => tracing::error!("Maintenance sweep failed: {}", e),
+            }
+        }
+    });
+}
+
+/// A plan is due once its day interval has elapsed since it was last
+/// performed, or once the tool has been lent `interval_loans` times.
+async fn flag_due_maintenance(db: &PgPool) -> sqlx::Result<u64> {
+    const IS_DUE: &str = r#"
+        is_active AND (
+            (interval_days IS NOT NULL
+             AND COALESCE(last_performed_at,created_at) + interval_days * INTERVAL '1 day' <= NOW())
+         OR (interval_loans IS NOT NULL AND loans_since >= interval_loans))"#;
+
+    // Plans whose interval was lengthened (or were deactivated) drop off the list.
+    sqlx::query(&format!(
+        "UPDATE maintenance_plans SET due_since=NULL WHERE due_since IS NOT NULL AND NOT ({})",
+        IS_DUE,
+    ))
+    .execute(db).await?;
+
+    let result = sqlx::query(&format!(
+        "UPDATE maintenance_plans SET due_since=NOW() WHERE due_since IS NULL AND {}",
+        IS_DUE,
+    ))
+    .execute(db).await?;
+    Ok(result.rows_affected())
+}
+
+/// Daily: deleted records past `TRASH_RETENTION_DAYS` are removed for good.
+pub fn spawn_trash_purger(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(86_400));
+        loop {
+            interval.tick().await;
+            match purge(&state).await {
+                Ok(n)  => tracing::info!("Trash purge: {} record(s) removed", n),
+                Err(e) => tracing::error!("Trash purge failed: {}", e),
+            }
+        }
+    });
+}
@@ -1,33 +1,42 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
 use serde_json::{json, Value};
-use sqlx::Row;
+use sqlx::{postgres::PgArguments, Row};
 
 use crate::{
     auth::middleware::AuthUser,
     errors::{AppError, Result},
+    export::{self, Export},
     labs::models::{CreateLabRequest, Lab, UpdateLabRequest},
     state::AppState,
+    trash::{handlers::move_to_trash, models::TrashKind},
 };
 
-// GET /labs
+// GET /labs — also exported as CSV/XLSX
 pub async fn list(
     _auth: AuthUser,
     State(state): State<AppState>,
-) -> Result<Json<Value>> {
-    let labs = sqlx::query_as::<_, Lab>(
-        r#"SELECT l.id, l.name, l.location, l.department, l.description,
-                  l.created_at, COUNT(t.id) AS tool_count
+    export: Export,
+) -> Result<Response> {
+    let sql = r#"SELECT l.id, l.name, l.location, l.department, l.description,
+                  l.created_at, l.deleted_at,
+                  COUNT(DISTINCT t.catalogue_item_id) AS tool_count,
+                  COALESCE(SUM(t.quantity),0)::BIGINT AS total_quantity
            FROM labs l
-           LEFT JOIN tools t ON t.lab_id = l.id
-           GROUP BY l.id ORDER BY l.name"#,
-    )
+           LEFT JOIN tools t ON t.lab_id = l.id AND t.deleted_at IS NULL
+           WHERE l.deleted_at IS NULL
+           GROUP BY l.id ORDER BY l.name"#;
+    if let Some(format) = export.0 {
+        return export::rows::<Lab>(state.db, sql.into(), PgArguments::default(), format, "labs").await;
+    }
+    let labs = sqlx::query_as::<_, Lab>(sql)
     .fetch_all(&state.db)
     .await?;
-    Ok(Json(json!({ "data": labs })))
+    Ok(Json(json!({ "data": labs })).into_response())
 }
 
 // GET /labs/:id
@@ -38,9 +47,11 @@ pub async fn get_one(
 ) -> Result<Json<Lab>> {
     let lab = sqlx::query_as::<_, Lab>(
         r#"SELECT l.id, l.name, l.location, l.department, l.description,
-                  l.created_at, COUNT(t.id) AS tool_count
+                  l.created_at, l.deleted_at,
+                  COUNT(DISTINCT t.catalogue_item_id) AS tool_count,
+                  COALESCE(SUM(t.quantity),0)::BIGINT AS total_quantity
            FROM labs l
-           LEFT JOIN tools t ON t.lab_id = l.id
+           LEFT JOIN tools t ON t.lab_id = l.id AND t.deleted_at IS NULL
            WHERE l.id = $1
            GROUP BY l.id"#,
     )
@@ -81,6 +92,7 @@ pub async fn create(
         "department":  row.try_get::<String,_>("department")?,
         "description": row.try_get::<Option<String>,_>("description")?,
         "toolCount":   0i64,
+        "totalQuantity": 0i64,
         "createdAt":   row.try_get::<chrono::DateTime<chrono::Utc>,_>("created_at")?,
     }))))
 }
@@ -92,7 +104,7 @@ pub async fn update(
     Path(id): Path<i32>,
     Json(body): Json<UpdateLabRequest>,
 ) -> Result<Json<Value>> {
-    if sqlx::query("SELECT id FROM labs WHERE id=$1").bind(id)
+    if sqlx::query("SELECT id FROM labs WHERE id=$1 AND deleted_at IS NULL").bind(id)
         .fetch_optional(&state.db).await?.is_none() {
         return Err(AppError::NotFound);
     }
@@ -116,14 +128,32 @@ pub async fn update(
     })))
 }
 
-// DELETE /labs/:id
+// DELETE /labs/:id — moves the lab to the trash once nothing is stocked in it
 pub async fn delete(
-    _auth: AuthUser,
+    AuthUser(claims): AuthUser,
     State(state): State<AppState>,
     Path(id): Path<i32>,
 ) -> Result<StatusCode> {
-    let r = sqlx::query("DELETE FROM labs WHERE id=$1").bind(id)
-        .execute(&state.db).await?;
-    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SELECT id FROM labs WHERE id=$1 AND deleted_at IS NULL FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let uses = sqlx::query(
+        r#"SELECT
+               (SELECT COUNT(*) FROM tools WHERE lab_id=$1 AND deleted_at IS NULL) AS tools,
+               (SELECT COUNT(*) FROM stocktakes WHERE lab_id=$1 AND status='Open') AS stocktakes,
+               (SELECT COUNT(*) FROM stock_transfers
+                WHERE $1 IN (from_lab_id,to_lab_id) AND status='In Transit') AS transfers"#,
+    )
+    .bind(id).fetch_one(&mut *tx).await?;
+
+    let mut blockers = Vec::new();
+    let tools: i64 = uses.try_get("tools")?;
+    if tools > 0 { blockers.push(format!("{} tool(s) still stocked in this lab; move or delete them first", tools)); }
+    if uses.try_get::<i64,_>("stocktakes")? > 0 { blockers.push("A stocktake is open for this lab".into()); }
+    let transfers: i64 = uses.try_get("transfers")?;
+    if transfers > 0 { blockers.push(format!("{} transfer(s) to or from this lab still in transit", transfers)); }
+
+    move_to_trash(&mut tx, TrashKind::Lab, &id.to_string(), blockers, &claims.sub).await?;
+    tx.commit().await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -1,15 +1,20 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Default, Serialize, sqlx::FromRow)]
 pub struct Lab {
     pub id:          i32,
     pub name:        String,
     pub location:    Option<String>,
     pub department:  String,
     pub description: Option<String>,
+    /// Distinct catalogue items held in this lab
     pub tool_count:  Option<i64>,
+    /// Units across all of the lab's stock lines
+    pub total_quantity: Option<i64>,
     pub created_at:  DateTime<Utc>,
+    /// Set while the lab is in the trash
+    pub deleted_at:  Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,26 +1,44 @@
-use axum::{extract::{Path, State}, http::StatusCode, Json};
-use serde_json::{json, Value};
-
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use sqlx::{postgres::PgArguments, PgConnection};
 
 use crate::{
     auth::middleware::AuthUser,
     errors::{AppError, Result},
+    export::{self, Export},
     lecturers::models::{CreateLecturerRequest, Lecturer, UpdateLecturerRequest},
     state::AppState,
+    trash::{handlers::move_to_trash, models::TrashKind},
 };
 
-pub async fn list(_auth: AuthUser, State(state): State<AppState>) -> Result<Json<Value>> {
-    let lecturers = sqlx::query_as::<_, Lecturer>(
-        "SELECT id,name,department,email,created_at FROM lecturers ORDER BY name",
-    )
-    .fetch_all(&state.db).await?;
-    Ok(Json(json!({ "data": lecturers })))
+const LECTURER_COLUMNS: &str = "id,name,department,email,created_at,deleted_at";
+
+/// Rejects a lecturer id that is unknown or in the trash.
+pub async fn check_lecturer(conn: &mut PgConnection, lecturer_id: i32) -> Result<()> {
+    if sqlx::query("SELECT id FROM lecturers WHERE id=$1 AND deleted_at IS NULL")
+        .bind(lecturer_id).fetch_optional(&mut *conn).await?.is_none() {
+        return Err(AppError::Validation("Lecturer not found".into()));
+    }
+    Ok(())
+}
+
+// GET /lecturers — also exported as CSV/XLSX
+pub async fn list(_auth: AuthUser, State(state): State<AppState>, export: Export) -> Result<Response> {
+    let sql = format!("SELECT {} FROM lecturers WHERE deleted_at IS NULL ORDER BY name", LECTURER_COLUMNS);
+    if let Some(format) = export.0 {
+        return export::rows::<Lecturer>(state.db, sql, PgArguments::default(), format, "lecturers").await;
+    }
+    let lecturers = sqlx::query_as::<_, Lecturer>(&sql).fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": lecturers })).into_response())
 }
 
 pub async fn get_one(_auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<Lecturer>> {
-    sqlx::query_as::<_, Lecturer>(
-        "SELECT id,name,department,email,created_at FROM lecturers WHERE id=$1",
-    )
+    sqlx::query_as::<_, Lecturer>(&format!("SELECT {} FROM lecturers WHERE id=$1", LECTURER_COLUMNS))
     .bind(id).fetch_optional(&state.db).await?.ok_or(AppError::NotFound).map(Json)
 }
 
@@ -29,10 +47,10 @@ pub async fn create(
 ) -> Result<(StatusCode, Json<Lecturer>)> {
     if body.name.trim().is_empty() { return Err(AppError::Validation("Name required".into())); }
     if !body.email.contains('@') { return Err(AppError::Validation("Invalid email".into())); }
-    let l = sqlx::query_as::<_, Lecturer>(
+    let l = sqlx::query_as::<_, Lecturer>(&format!(
         "INSERT INTO lecturers (name,department,email) VALUES ($1,$2,$3)
-         RETURNING id,name,department,email,created_at",
-    )
+         RETURNING {}", LECTURER_COLUMNS,
+    ))
     .bind(body.name.trim()).bind(body.department.trim())
     .bind(body.email.trim().to_lowercase())
     .fetch_one(&state.db).await?;
@@ -43,22 +61,31 @@ pub async fn update(
     _auth: AuthUser, State(state): State<AppState>,
     Path(id): Path<i32>, Json(body): Json<UpdateLecturerRequest>,
 ) -> Result<Json<Lecturer>> {
-    if sqlx::query("SELECT id FROM lecturers WHERE id=$1").bind(id)
+    if sqlx::query("SELECT id FROM lecturers WHERE id=$1 AND deleted_at IS NULL").bind(id)
         .fetch_optional(&state.db).await?.is_none() {
         return Err(AppError::NotFound);
     }
-    let l = sqlx::query_as::<_, Lecturer>(
+    let l = sqlx::query_as::<_, Lecturer>(&format!(
         "UPDATE lecturers SET
              name=COALESCE($1,name), department=COALESCE($2,department), email=COALESCE($3,email)
-         WHERE id=$4 RETURNING id,name,department,email,created_at",
-    )
+         WHERE id=$4 RETURNING {}", LECTURER_COLUMNS,
+    ))
     .bind(&body.name).bind(&body.department).bind(&body.email).bind(id)
     .fetch_one(&state.db).await?;
     Ok(Json(l))
 }
 
-pub async fn delete(_auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>) -> Result<StatusCode> {
-    let r = sqlx::query("DELETE FROM lecturers WHERE id=$1").bind(id).execute(&state.db).await?;
-    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+// DELETE /lecturers/:id — moves the lecturer to the trash
+pub async fn delete(AuthUser(claims): AuthUser, State(state): State<AppState>, Path(id): Path<i32>) -> Result<StatusCode> {
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SELECT id FROM lecturers WHERE id=$1 AND deleted_at IS NULL FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let open: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM delegations WHERE lecturer_id=$1 AND status IN ('Issued','Overdue')",
+    )
+    .bind(id).fetch_one(&mut *tx).await?;
+    let blockers = if open > 0 { vec![format!("{} loan(s) they authorised not yet returned", open)] } else { vec![] };
+    move_to_trash(&mut tx, TrashKind::Lecturer, &id.to_string(), blockers, &claims.sub).await?;
+    tx.commit().await?;
     Ok(StatusCode::NO_CONTENT)
 }
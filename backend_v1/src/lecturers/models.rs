@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Default, Serialize, sqlx::FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct Lecturer {
     pub id:         i32,
@@ -9,6 +9,8 @@ pub struct Lecturer {
     pub department: String,
     pub email:      String,
     pub created_at: DateTime<Utc>,
+    /// Set while the lecturer is in the trash
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+
+use axum::{extract::State, Json};
+use chrono::NaiveDate;
+use sqlx::{PgConnection, Row};
+
+use crate::{
+    auth::{middleware::AuthUser, models::Claims},
+    errors::{AppError, Result},
+    limits::models::{BorrowingLimits, CategoryLimit, LimitHit, UpdateLimitsRequest},
+    state::AppState,
+};
+
+/// Roles that may issue past a borrowing limit
+const OVERRIDE_ROLES: &[&str] = &["admin", "lecturer"];
+
+async fn fetch_limits(conn: &mut PgConnection) -> Result<BorrowingLimits> {
+    let row = sqlx::query("SELECT max_items,max_loan_days,updated_by,updated_at FROM borrowing_limits WHERE id=1")
+        .fetch_one(&mut *conn).await?;
+    let categories = sqlx::query_as::<_, CategoryLimit>(
+        "SELECT category,max_items,max_loan_days FROM category_limits ORDER BY category",
+    )
+    .fetch_all(&mut *conn).await?;
+    Ok(BorrowingLimits {
+        max_items:     row.try_get("max_items")?,
+        max_loan_days: row.try_get("max_loan_days")?,
+        categories,
+        updated_by:    row.try_get("updated_by")?,
+        updated_at:    row.try_get("updated_at")?,
+    })
+}
+
+/// An override needs a reason and a caller whose role allows it.
+pub fn check_override(claims: &Claims, reason: Option<&str>) -> Result<()> {
+    let Some(reason) = reason else { return Ok(()) };
+    if reason.trim().is_empty() {
+        return Err(AppError::Validation("override_reason cannot be blank".into()));
+    }
+    if !OVERRIDE_ROLES.contains(&claims.role.as_str()) {
+        return Err(AppError::Forbidden("Only lecturers and admins may override borrowing limits".into()));
+    }
+    Ok(())
+}
+
+/// The limits a loan of `quantity` units of a `category` tool, due on
+/// `expected_return`, would break for the student. Items are counted over
+/// the student's open loans of reusable tools, plus `alongside` reusable
+/// units of other categories issued with this loan (a kit); consumables only
+/// answer to the loan length. Locks the student row so concurrent issues to
+/// the same student are counted one after the other.
+pub async fn limits_hit(
+    conn: &mut PgConnection, student_id: &str, category: &str, is_consumable: bool,
+    quantity: i32, alongside: i32, expected_return: NaiveDate,
+) -> Result<Vec<LimitHit>> {
+    sqlx::query("SELECT student_id FROM students WHERE student_id=$1 FOR UPDATE")
+        .bind(student_id).fetch_optional(&mut *conn).await?;
+    let limits = sqlx::query(
+        r#"SELECT b.max_items, b.max_loan_days, CURRENT_DATE AS today,
+                  c.max_items AS category_max_items, c.max_loan_days AS category_max_loan_days
+           FROM borrowing_limits b
+           LEFT JOIN category_limits c ON c.category=$1::tool_category
+           WHERE b.id=1"#,
+    )
+    .bind(category).fetch_one(&mut *conn).await?;
+
+    let mut hits = Vec::new();
+    if !is_consumable {
+        let held = sqlx::query(
+            r#"SELECT COALESCE(SUM(d.quantity),0) AS total,
+                      COALESCE(SUM(d.quantity) FILTER (WHERE t.category=$2::tool_category),0) AS in_category
+               FROM delegations d JOIN tools t ON t.id=d.tool_id
+               WHERE d.student_id=$1 AND d.status IN ('Issued','Overdue') AND NOT t.is_consumable"#,
+        )
+        .bind(student_id).bind(category).fetch_one(&mut *conn).await?;
+        let total: i64 = held.try_get("total")?;
+        let in_category: i64 = held.try_get("in_category")?;
+        if let Some(max) = limits.try_get::<Option<i32>,_>("max_items")? {
+            if total + i64::from(quantity) + i64::from(alongside) > i64::from(max) {
+                hits.push(LimitHit {
+                    limit: "max_items",
+                    message: format!("{} may have {} item(s) out and has {}", student_id, max, total),
+                });
+            }
+        }
+        if let Some(max) = limits.try_get::<Option<i32>,_>("category_max_items")? {
+            if in_category + i64::from(quantity) > i64::from(max) {
+                hits.push(LimitHit {
+                    limit: "category_max_items",
+                    message: format!("{} may have {} {} item(s) out and has {}", student_id, max, category, in_category),
+                });
+            }
+        }
+    }
+
+    let max_days = match limits.try_get::<Option<i32>,_>("category_max_loan_days")? {
+        Some(days) => Some(("category_max_loan_days", days)),
+        None => limits.try_get::<Option<i32>,_>("max_loan_days")?.map(|days| ("max_loan_days", days)),
+    };
+    if let Some((limit, days)) = max_days {
+        let due_by = limits.try_get::<NaiveDate,_>("today")? + chrono::Duration::days(i64::from(days));
+        if expected_return > due_by {
+            hits.push(LimitHit {
+                limit,
+                message: format!("{} loans run at most {} day(s); due by {}", category, days, due_by),
+            });
+        }
+    }
+    Ok(hits)
+}
+
+// GET /borrowing-limits
+pub async fn get_limits(_auth: AuthUser, State(state): State<AppState>) -> Result<Json<BorrowingLimits>> {
+    let mut conn = state.db.acquire().await?;
+    fetch_limits(&mut conn).await.map(Json)
+}
+
+// PUT /borrowing-limits — replaces every limit
+pub async fn update_limits(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Json(body): Json<UpdateLimitsRequest>,
+) -> Result<Json<BorrowingLimits>> {
+    let values = [body.max_items, body.max_loan_days].into_iter()
+        .chain(body.categories.iter().flat_map(|c| [c.max_items, c.max_loan_days]));
+    if values.flatten().any(|v| v <= 0) {
+        return Err(AppError::Validation("Limits must be >= 1".into()));
+    }
+    let mut seen = HashSet::new();
+    if !body.categories.iter().all(|c| seen.insert(format!("{:?}", c.category))) {
+        return Err(AppError::Validation("Each category may appear once".into()));
+    }
+
+    let mut tx = state.db.begin().await?;
+    sqlx::query(
+        "UPDATE borrowing_limits SET max_items=$1, max_loan_days=$2, updated_by=$3, updated_at=NOW() WHERE id=1",
+    )
+    .bind(body.max_items).bind(body.max_loan_days).bind(&claims.sub)
+    .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM category_limits").execute(&mut *tx).await?;
+    for c in body.categories.iter().filter(|c| c.max_items.is_some() || c.max_loan_days.is_some()) {
+        sqlx::query("INSERT INTO category_limits (category,max_items,max_loan_days) VALUES ($1,$2,$3)")
+            .bind(&c.category).bind(c.max_items).bind(c.max_loan_days)
+            .execute(&mut *tx).await?;
+    }
+    let limits = fetch_limits(&mut tx).await?;
+    tx.commit().await?;
+    Ok(Json(limits))
+}
@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::tools::models::ToolCategory;
+
+/// Borrowing limits. A `None` limit is disabled.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BorrowingLimits {
+    /// Reusable items one student may have out at once
+    pub max_items:     Option<i32>,
+    /// Longest loan for categories without their own
+    pub max_loan_days: Option<i32>,
+    pub categories:    Vec<CategoryLimit>,
+    pub updated_by:    Option<String>,
+    pub updated_at:    DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryLimit {
+    pub category:      ToolCategory,
+    /// Reusable items of the category one student may have out at once
+    pub max_items:     Option<i32>,
+    pub max_loan_days: Option<i32>,
+}
+
+/// Replaces all limits; omitted ones are disabled.
+#[derive(Debug, Deserialize)]
+pub struct UpdateLimitsRequest {
+    pub max_items:     Option<i32>,
+    pub max_loan_days: Option<i32>,
+    #[serde(default)]
+    pub categories:    Vec<CategoryLimit>,
+}
+
+/// A limit a loan would break
+pub struct LimitHit {
+    /// `max_items`, `category_max_items`, `max_loan_days` or `category_max_loan_days`
+    pub limit:   &'static str,
+    pub message: String,
+}
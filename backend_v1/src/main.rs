@@ -1,14 +1,15 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    routing::{delete, get, post, put},
     Router,
 };
 
 #[allow(unused)]
 use axum::routing::get_service;
 
-use sqlx::postgres::PgPoolOptions;
+use sqlx::{postgres::PgPoolOptions, Executor};
 // use tower_http::services::fs::ServeDir;
 use tower_http::{
     cors::{Any, CorsLayer},
@@ -17,16 +18,39 @@ use tower_http::{
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 mod analytics;
+mod appeals;
+mod attachments;
 mod auth;
+mod calendar;
+mod catalogue;
+mod charges;
+mod codes;
+mod cohorts;
 mod config;
+mod damage;
 mod delegations;
 mod errors;
+mod events;
+mod export;
 mod jobs;
 mod labs;
 mod lecturers;
+mod limits;
+mod maintenance;
+mod partners;
+mod pdf;
+mod policy;
+mod purchasing;
+mod spreadsheet;
 mod state;
+mod storage;
+mod stock;
+mod stocktakes;
 mod students;
 mod tools;
+mod transfers;
+mod trash;
+mod units;
 
 use state::AppState;
 
@@ -52,8 +76,15 @@ async fn main() -> anyhow::Result<()> {
     // tracing::info!("Trying to connect to postgres");
 tracing::info!("Newton's Legacy Here! Leave it to Him");
     // ── Database pool ─────────────────────────────────────────────────────────
+    // Sessions run in the institution's zone so CURRENT_DATE and ::DATE casts
+    // give its calendar days.
+    let timezone = config.timezone.name();
     let db = PgPoolOptions::new()
         .max_connections(20)
+        .after_connect(move |conn, _meta| Box::pin(async move {
+            conn.execute(format!("SET TIME ZONE '{}'", timezone).as_str()).await?;
+            Ok(())
+        }))
         .connect(&config.database_url)
         .await?;
 
@@ -61,12 +92,20 @@ tracing::info!("Newton's Legacy Here! Leave it to Him");
 
     // ── Background jobs ───────────────────────────────────────────────────────
     jobs::spawn_overdue_checker(db.clone());
+    jobs::spawn_maintenance_checker(db.clone());
+
+    // ── Live dashboard events (LISTEN/NOTIFY) ─────────────────────────────────
+    let events = events::channel();
+    events::spawn_listener(db.clone(), events.clone());
 
     // ── App state ─────────────────────────────────────────────────────────────
     let state = AppState {
         db: db.clone(),
         config: config.clone(),
+        events,
+        storage: Arc::new(storage::LocalStorage::new(&config.upload_dir)),
     };
+    jobs::spawn_trash_purger(state.clone());
 
     // ── CORS ──────────────────────────────────────────────────────────────────
     let cors = CorsLayer::new()
@@ -80,9 +119,13 @@ tracing::info!("Newton's Legacy Here! Leave it to Him");
         .route(
             "/auth/change-password",
             post(auth::handlers::change_password),
-        );
+        )
+        .route("/auth/stream-ticket", post(auth::handlers::stream_ticket));
 
     // ── Protected routes (JWT required) ───────────────────────────────────────
+    // Uploads are size-checked while streaming; the body limit leaves room
+    // for multipart framing on top of the file itself.
+    let upload_limit = DefaultBodyLimit::max(config.max_upload_bytes + 64 * 1024);
     let protected_routes = Router::new()
         // Labs
         .route(
@@ -95,17 +138,50 @@ tracing::info!("Newton's Legacy Here! Leave it to Him");
                 .put(labs::handlers::update)
                 .delete(labs::handlers::delete),
         )
+        .route("/labs/:id/transfers", get(transfers::handlers::lab_history))
+        .route(
+            "/labs/:id/hours",
+            get(calendar::handlers::get_hours).put(calendar::handlers::set_hours),
+        )
         // Tools
         .route(
             "/tools",
             get(tools::handlers::list).post(tools::handlers::create),
         )
+        .route(
+            "/tools/import",
+            post(tools::handlers::import).layer(upload_limit),
+        )
         .route(
             "/tools/:id",
             get(tools::handlers::get_one)
                 .put(tools::handlers::update)
                 .delete(tools::handlers::delete),
         )
+        .route(
+            "/tools/:id/movements",
+            get(stock::handlers::list).post(stock::handlers::create),
+        )
+        .route(
+            "/tools/:id/maintenance",
+            get(maintenance::handlers::history).post(maintenance::handlers::start),
+        )
+        .route(
+            "/tools/:id/maintenance-plans",
+            get(maintenance::handlers::list_plans).post(maintenance::handlers::create_plan),
+        )
+        .route(
+            "/tools/:id/attachments",
+            get(attachments::handlers::list_tool)
+                .post(attachments::handlers::upload_tool)
+                .layer(upload_limit),
+        )
+        // Catalogue (tool models across labs)
+        .route("/catalogue", get(catalogue::handlers::list))
+        .route(
+            "/catalogue/:id",
+            get(catalogue::handlers::get_one).put(catalogue::handlers::update),
+        )
         // Lecturers
         .route(
             "/lecturers",
@@ -122,6 +198,10 @@ tracing::info!("Newton's Legacy Here! Leave it to Him");
             "/students",
             get(students::handlers::list).post(students::handlers::create),
         )
+        .route(
+            "/students/import",
+            post(students::handlers::import).layer(upload_limit),
+        )
         .route(
             "/students/:id",
             get(students::handlers::profile)
@@ -136,6 +216,101 @@ tracing::info!("Newton's Legacy Here! Leave it to Him");
             "/students/:student_id/lost-tools/:delegation_id/paid",
             post(students::handlers::paid_tool),
         )
+        .route(
+            "/students/:student_id/lost-tools/:delegation_id/attachments",
+            get(attachments::handlers::list_lost)
+                .post(attachments::handlers::upload_lost)
+                .layer(upload_limit),
+        )
+        .route("/students/:id/charges", post(charges::handlers::create))
+        .route("/students/:id/policy-check", get(policy::handlers::policy_check))
+        .route("/students/:id/ban", post(policy::handlers::ban))
+        .route("/students/:id/suspend", post(policy::handlers::suspend))
+        .route("/students/:id/unban", post(policy::handlers::unban))
+        .route("/students/:id/appeals", post(appeals::handlers::file))
+        .route("/students/:id/clearance", get(students::handlers::clearance))
+        .route(
+            "/students/:id/clearance-certificate",
+            post(students::handlers::clearance_certificate),
+        )
+        .route("/clearances/:certificate_no", get(students::handlers::verify_clearance))
+        .route("/students/:id/graduate", post(students::handlers::graduate))
+        .route("/students/:id/archive", post(students::handlers::archive))
+        .route("/students/:id/reactivate", post(students::handlers::reactivate))
+        // Ban appeals
+        .route("/appeals", get(appeals::handlers::list))
+        .route("/appeals/:id", get(appeals::handlers::get_one))
+        .route("/appeals/:id/decide", post(appeals::handlers::decide))
+        // Ban policy
+        .route(
+            "/ban-policy",
+            get(policy::handlers::get_policy).put(policy::handlers::update_policy),
+        )
+        // Calendar
+        .route(
+            "/holidays",
+            get(calendar::handlers::list_holidays).post(calendar::handlers::create_holiday),
+        )
+        .route("/holidays/:id", delete(calendar::handlers::delete_holiday))
+        .route("/calendar/due-date", get(calendar::handlers::suggest_due_date))
+        // Borrowing limits
+        .route(
+            "/borrowing-limits",
+            get(limits::handlers::get_limits).put(limits::handlers::update_limits),
+        )
+        // Charges
+        .route("/charges", get(charges::handlers::list))
+        .route("/charges/:id", get(charges::handlers::get_one))
+        .route("/charges/:id/payments", post(charges::handlers::pay))
+        .route("/charges/:id/waive", post(charges::handlers::waive))
+        .route("/charges/:id/waivers/:waiver_id/decide", post(charges::handlers::decide_waiver))
+        // Cohorts
+        .route(
+            "/cohorts",
+            get(cohorts::handlers::list).post(cohorts::handlers::create),
+        )
+        .route(
+            "/cohorts/:id",
+            get(cohorts::handlers::get_one)
+                .put(cohorts::handlers::update)
+                .delete(cohorts::handlers::delete),
+        )
+        .route("/cohorts/:id/members", post(cohorts::handlers::add_members))
+        .route(
+            "/cohorts/:id/members/:student_id",
+            delete(cohorts::handlers::remove_member),
+        )
+        .route("/cohorts/:id/issues", get(cohorts::handlers::list_issues))
+        .route("/cohorts/:id/issue", post(cohorts::handlers::issue_kit))
+        .route("/cohort-issues/:id", get(cohorts::handlers::get_issue))
+        .route("/cohort-issues/:id/return", post(cohorts::handlers::return_kit))
+        // Partner departments and guest borrowers
+        .route(
+            "/partner-departments",
+            get(partners::handlers::list).post(partners::handlers::create),
+        )
+        .route("/partner-departments/outstanding", get(partners::handlers::outstanding))
+        .route(
+            "/partner-departments/:id",
+            get(partners::handlers::get_one).put(partners::handlers::update),
+        )
+        .route(
+            "/partner-departments/:id/outstanding",
+            get(partners::handlers::department_outstanding),
+        )
+        .route("/partner-departments/:id/guests", post(partners::handlers::create_guest))
+        .route("/guest-borrowers/:id", put(partners::handlers::update_guest))
+        // Course units
+        .route("/units", get(units::handlers::list).post(units::handlers::create))
+        .route(
+            "/units/:id",
+            get(units::handlers::get_one)
+                .put(units::handlers::update)
+                .delete(units::handlers::delete),
+        )
+        .route("/units/:id/kit", put(units::handlers::set_kit))
+        .route("/units/:id/enrolments", post(units::handlers::enrol))
+        .route("/units/:id/enrolments/:student_id", delete(units::handlers::unenrol))
         // Delegations
         .route(
             "/delegations",
@@ -146,9 +321,76 @@ tracing::info!("Newton's Legacy Here! Leave it to Him");
             "/delegations/:id/return",
             post(delegations::handlers::return_tool),
         )
+        // Purchasing
+        .route(
+            "/suppliers",
+            get(purchasing::handlers::list_suppliers).post(purchasing::handlers::create_supplier),
+        )
+        .route(
+            "/suppliers/:id",
+            get(purchasing::handlers::get_supplier).put(purchasing::handlers::update_supplier),
+        )
+        .route(
+            "/purchase-orders",
+            get(purchasing::handlers::list_orders).post(purchasing::handlers::create_order),
+        )
+        .route("/purchase-orders/:id", get(purchasing::handlers::get_order))
+        .route("/purchase-orders/:id/receive", post(purchasing::handlers::receive_order))
+        .route("/purchase-orders/:id/cancel", post(purchasing::handlers::cancel_order))
+        .route("/reorder-suggestions", get(purchasing::handlers::reorder_suggestions))
+        // Damage reports
+        .route("/damage-reports", get(damage::handlers::list))
+        .route("/damage-reports/:id", get(damage::handlers::get_one))
+        .route("/damage-reports/:id/repair", post(damage::handlers::repair))
+        .route("/damage-reports/:id/write-off", post(damage::handlers::write_off))
+        .route("/damage-reports/:id/charge", post(damage::handlers::charge))
+        .route(
+            "/damage-reports/:id/attachments",
+            get(attachments::handlers::list_damage)
+                .post(attachments::handlers::upload_damage)
+                .layer(upload_limit),
+        )
+        // Attachments
+        .route(
+            "/attachments/:id",
+            get(attachments::handlers::get_one).delete(attachments::handlers::delete),
+        )
+        .route("/attachments/:id/download", get(attachments::handlers::download))
+        .route("/attachments/:id/thumbnail", get(attachments::handlers::thumbnail))
+        .route("/attachments/:id/primary", post(attachments::handlers::make_primary))
+        // Maintenance
+        .route("/maintenance/due", get(maintenance::handlers::due))
+        .route("/maintenance/plans/:id", put(maintenance::handlers::update_plan))
+        .route(
+            "/maintenance/records/:id/complete",
+            post(maintenance::handlers::complete),
+        )
+        // Transfers
+        .route(
+            "/transfers",
+            get(transfers::handlers::list).post(transfers::handlers::dispatch),
+        )
+        .route("/transfers/:id", get(transfers::handlers::get_one))
+        .route("/transfers/:id/receive", post(transfers::handlers::receive))
+        .route("/transfers/:id/cancel", post(transfers::handlers::cancel))
+        // Stocktakes
+        .route(
+            "/stocktakes",
+            get(stocktakes::handlers::list).post(stocktakes::handlers::create),
+        )
+        .route("/stocktakes/:id", get(stocktakes::handlers::get_one))
+        .route("/stocktakes/:id/counts", post(stocktakes::handlers::count))
+        .route("/stocktakes/:id/approve", post(stocktakes::handlers::approve))
+        .route("/stocktakes/:id/cancel", post(stocktakes::handlers::cancel))
+        .route("/stocktakes/:id/report", get(stocktakes::handlers::report))
+        // Trash
+        .route("/trash", get(trash::handlers::list))
+        .route("/trash/:kind/:id/restore", post(trash::handlers::restore))
         // Analytics
         .route("/analytics/overview", get(analytics::handlers::overview))
-        .route("/analytics/usage", get(analytics::handlers::usage));
+        .route("/analytics/usage", get(analytics::handlers::usage))
+        .route("/analytics/stream", get(analytics::handlers::stream))
+        .route("/analytics/reorder", get(analytics::handlers::reorder_forecast));
 
     // ── Assemble full router ──────────────────────────────────────────────────
     let api = Router::new().merge(public_routes).merge(protected_routes);
@@ -0,0 +1,234 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use serde_json::{json, Value};
+use sqlx::{PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    errors::{AppError, Result},
+    maintenance::models::{
+        CompleteMaintenanceRequest, CreatePlanRequest, DueFilters, MaintenanceKind,
+        MaintenanceOutcome, MaintenancePlan, MaintenanceRecord, StartMaintenanceRequest,
+        UpdatePlanRequest,
+    },
+    state::AppState,
+    stock::{handlers::record_movement, models::{NewMovement, StockMovementKind}},
+    tools::handlers::refresh_status,
+};
+
+const PLAN_SELECT: &str = r#"
+    SELECT p.id,p.tool_id,t.name AS tool_name,l.name AS lab_name,p.kind,
+           p.interval_days,p.interval_loans,p.last_performed_at,
+           COALESCE(p.last_performed_at,p.created_at) + p.interval_days * INTERVAL '1 day' AS next_due_at,
+           p.loans_since,p.due_since,p.is_active,p.notes,p.created_at
+    FROM maintenance_plans p
+    JOIN tools t ON t.id=p.tool_id
+    LEFT JOIN labs l ON l.id=t.lab_id"#;
+
+const RECORD_SELECT: &str = r#"
+    SELECT r.id,r.tool_id,t.name AS tool_name,r.plan_id,r.kind,r.quantity,r.technician,
+           r.started_by,r.started_at,r.completed_by,r.completed_at,r.outcome,
+           r.cost_cents,r.notes
+    FROM maintenance_records r
+    JOIN tools t ON t.id=r.tool_id"#;
+
+async fn fetch_plan(db: &PgPool, id: i32) -> Result<MaintenancePlan> {
+    sqlx::query_as::<_, MaintenancePlan>(&format!("{} WHERE p.id=$1", PLAN_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+async fn fetch_record(db: &PgPool, id: i32) -> Result<MaintenanceRecord> {
+    sqlx::query_as::<_, MaintenanceRecord>(&format!("{} WHERE r.id=$1", RECORD_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+fn validate_intervals(days: Option<i32>, loans: Option<i32>) -> Result<()> {
+    if days.is_none() && loans.is_none() {
+        return Err(AppError::Validation("interval_days or interval_loans required".into()));
+    }
+    if days.is_some_and(|d| d <= 0) || loans.is_some_and(|n| n <= 0) {
+        return Err(AppError::Validation("Maintenance intervals must be positive".into()));
+    }
+    Ok(())
+}
+
+// GET /tools/:id/maintenance-plans
+pub async fn list_plans(
+    _auth: AuthUser, State(state): State<AppState>, Path(tool_id): Path<i32>,
+) -> Result<Json<Value>> {
+    let plans = sqlx::query_as::<_, MaintenancePlan>(
+        &format!("{} WHERE p.tool_id=$1 ORDER BY p.id", PLAN_SELECT),
+    )
+    .bind(tool_id).fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": plans })))
+}
+
+// POST /tools/:id/maintenance-plans
+pub async fn create_plan(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(tool_id): Path<i32>, Json(body): Json<CreatePlanRequest>,
+) -> Result<(StatusCode, Json<MaintenancePlan>)> {
+    validate_intervals(body.interval_days, body.interval_loans)?;
+    if sqlx::query("SELECT id FROM tools WHERE id=$1 AND deleted_at IS NULL").bind(tool_id)
+        .fetch_optional(&state.db).await?.is_none() { return Err(AppError::NotFound); }
+
+    let id: i32 = sqlx::query(
+        r#"INSERT INTO maintenance_plans (tool_id,kind,interval_days,interval_loans,last_performed_at,notes)
+           VALUES ($1,$2,$3,$4,$5,$6) RETURNING id"#,
+    )
+    .bind(tool_id).bind(&body.kind).bind(body.interval_days).bind(body.interval_loans)
+    .bind(body.last_performed_at).bind(&body.notes)
+    .fetch_one(&state.db).await?.try_get("id")?;
+
+    Ok((StatusCode::CREATED, Json(fetch_plan(&state.db, id).await?)))
+}
+
+// PUT /maintenance/plans/:id
+pub async fn update_plan(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<UpdatePlanRequest>,
+) -> Result<Json<MaintenancePlan>> {
+    let current = fetch_plan(&state.db, id).await?;
+    validate_intervals(
+        body.interval_days.or(current.interval_days),
+        body.interval_loans.or(current.interval_loans),
+    )?;
+
+    sqlx::query(
+        r#"UPDATE maintenance_plans SET
+               interval_days=COALESCE($1,interval_days), interval_loans=COALESCE($2,interval_loans),
+               is_active=COALESCE($3,is_active), notes=COALESCE($4,notes),
+               due_since=CASE WHEN COALESCE($3,is_active) THEN due_since END
+           WHERE id=$5"#,
+    )
+    .bind(body.interval_days).bind(body.interval_loans).bind(body.is_active)
+    .bind(&body.notes).bind(id)
+    .execute(&state.db).await?;
+
+    Ok(Json(fetch_plan(&state.db, id).await?))
+}
+
+// GET /maintenance/due — plans flagged by the scheduler
+pub async fn due(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<DueFilters>,
+) -> Result<Json<Value>> {
+    let plans = sqlx::query_as::<_, MaintenancePlan>(&format!(
+        "{} WHERE p.is_active AND p.due_since IS NOT NULL AND ($1::INT IS NULL OR t.lab_id=$1)
+         ORDER BY p.due_since, p.id",
+        PLAN_SELECT,
+    ))
+    .bind(filters.lab_id).fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": plans, "total": plans.len() })))
+}
+
+// GET /tools/:id/maintenance
+pub async fn history(
+    _auth: AuthUser, State(state): State<AppState>, Path(tool_id): Path<i32>,
+) -> Result<Json<Value>> {
+    let records = sqlx::query_as::<_, MaintenanceRecord>(
+        &format!("{} WHERE r.tool_id=$1 ORDER BY r.started_at DESC, r.id DESC", RECORD_SELECT),
+    )
+    .bind(tool_id).fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": records })))
+}
+
+// POST /tools/:id/maintenance — takes units off the shelf until completed
+pub async fn start(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(tool_id): Path<i32>, Json(body): Json<StartMaintenanceRequest>,
+) -> Result<(StatusCode, Json<MaintenanceRecord>)> {
+    if body.quantity <= 0 { return Err(AppError::Validation("Quantity must be >= 1".into())); }
+    if body.technician.trim().is_empty() {
+        return Err(AppError::Validation("Technician required".into()));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let tool = sqlx::query(
+        "SELECT quantity-issued_qty-held_qty AS available FROM tools WHERE id=$1 FOR UPDATE",
+    )
+    .bind(tool_id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    if tool.try_get::<i32,_>("available")? < body.quantity {
+        return Err(AppError::InsufficientStock);
+    }
+
+    let kind: MaintenanceKind = match (body.plan_id, &body.kind) {
+        (Some(plan_id), _) => sqlx::query("SELECT kind FROM maintenance_plans WHERE id=$1 AND tool_id=$2")
+            .bind(plan_id).bind(tool_id).fetch_optional(&mut *tx).await?
+            .ok_or_else(|| AppError::Validation("Plan does not belong to this tool".into()))?
+            .try_get("kind")?,
+        (None, Some(k)) => k.clone(),
+        (None, None)    => return Err(AppError::Validation("kind or plan_id required".into())),
+    };
+
+    sqlx::query("UPDATE tools SET maintenance_qty=maintenance_qty+$1 WHERE id=$2")
+        .bind(body.quantity).bind(tool_id).execute(&mut *tx).await?;
+    refresh_status(&mut tx, tool_id).await?;
+
+    let id: i32 = sqlx::query(
+        r#"INSERT INTO maintenance_records (tool_id,plan_id,kind,quantity,technician,started_by,notes)
+           VALUES ($1,$2,$3,$4,$5,$6,$7) RETURNING id"#,
+    )
+    .bind(tool_id).bind(body.plan_id).bind(&kind).bind(body.quantity)
+    .bind(body.technician.trim()).bind(&claims.sub).bind(&body.notes)
+    .fetch_one(&mut *tx).await?.try_get("id")?;
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(fetch_record(&state.db, id).await?)))
+}
+
+// POST /maintenance/records/:id/complete
+pub async fn complete(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<CompleteMaintenanceRequest>,
+) -> Result<Json<MaintenanceRecord>> {
+    if body.cost_cents.is_some_and(|c| c < 0) {
+        return Err(AppError::Validation("Cost cannot be negative".into()));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let rec = sqlx::query(
+        "SELECT tool_id,plan_id,quantity,completed_at IS NOT NULL AS done
+         FROM maintenance_records WHERE id=$1 FOR UPDATE",
+    )
+    .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    if rec.try_get::<bool,_>("done")? {
+        return Err(AppError::Conflict("Maintenance record is already completed".into()));
+    }
+    let tool_id:  i32         = rec.try_get("tool_id")?;
+    let plan_id:  Option<i32> = rec.try_get("plan_id")?;
+    let quantity: i32         = rec.try_get("quantity")?;
+
+    sqlx::query("UPDATE tools SET maintenance_qty=GREATEST(0,maintenance_qty-$1) WHERE id=$2")
+        .bind(quantity).bind(tool_id).execute(&mut *tx).await?;
+    if body.outcome == MaintenanceOutcome::Retired {
+        record_movement(&mut tx, NewMovement {
+            tool_id, kind: StockMovementKind::WrittenOff, delta: -quantity,
+            reason: &format!("Retired after maintenance #{}", id),
+            actor: &claims.sub, delegation_id: None,
+        }).await?;
+    } else {
+        refresh_status(&mut tx, tool_id).await?;
+    }
+
+    sqlx::query(
+        r#"UPDATE maintenance_records SET
+               outcome=$1, cost_cents=$2, technician=COALESCE($3,technician),
+               notes=COALESCE($4,notes), completed_by=$5, completed_at=NOW()
+           WHERE id=$6"#,
+    )
+    .bind(&body.outcome).bind(body.cost_cents).bind(&body.technician)
+    .bind(&body.notes).bind(&claims.sub).bind(id)
+    .execute(&mut *tx).await?;
+
+    // A failed check leaves the plan due so it stays on the due list.
+    if let Some(plan_id) = plan_id.filter(|_| body.outcome != MaintenanceOutcome::Failed) {
+        sqlx::query(
+            "UPDATE maintenance_plans SET last_performed_at=NOW(), loans_since=0, due_since=NULL WHERE id=$1",
+        )
+        .bind(plan_id).execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+
+    Ok(Json(fetch_record(&state.db, id).await?))
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[sqlx(type_name = "maintenance_kind", rename_all = "PascalCase")]
+pub enum MaintenanceKind {
+    Calibration,
+    Service,
+    Inspection,
+}
+
+/// `Failed` releases the units but leaves the plan due; `Retired` writes the
+/// units out of stock.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[sqlx(type_name = "maintenance_outcome", rename_all = "PascalCase")]
+pub enum MaintenanceOutcome {
+    Passed,
+    Adjusted,
+    Repaired,
+    Failed,
+    Retired,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenancePlan {
+    pub id:                i32,
+    pub tool_id:           i32,
+    pub tool_name:         String,
+    pub lab_name:          Option<String>,
+    pub kind:              MaintenanceKind,
+    pub interval_days:     Option<i32>,
+    pub interval_loans:    Option<i32>,
+    pub last_performed_at: Option<DateTime<Utc>>,
+    pub next_due_at:       Option<DateTime<Utc>>,
+    pub loans_since:       i32,
+    pub due_since:         Option<DateTime<Utc>>,
+    pub is_active:         bool,
+    pub notes:             Option<String>,
+    pub created_at:        DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePlanRequest {
+    pub kind:              MaintenanceKind,
+    pub interval_days:     Option<i32>,
+    pub interval_loans:    Option<i32>,
+    pub last_performed_at: Option<DateTime<Utc>>,
+    pub notes:             Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePlanRequest {
+    pub interval_days:  Option<i32>,
+    pub interval_loans: Option<i32>,
+    pub is_active:      Option<bool>,
+    pub notes:          Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceRecord {
+    pub id:           i32,
+    pub tool_id:      i32,
+    pub tool_name:    String,
+    pub plan_id:      Option<i32>,
+    pub kind:         MaintenanceKind,
+    pub quantity:     i32,
+    pub technician:   String,
+    pub started_by:   String,
+    pub started_at:   DateTime<Utc>,
+    pub completed_by: Option<String>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub outcome:      Option<MaintenanceOutcome>,
+    pub cost_cents:   Option<i64>,
+    pub notes:        Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartMaintenanceRequest {
+    pub quantity:   i32,
+    pub technician: String,
+    pub plan_id:    Option<i32>,
+    /// Required when `plan_id` is not given; otherwise taken from the plan.
+    pub kind:       Option<MaintenanceKind>,
+    pub notes:      Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompleteMaintenanceRequest {
+    pub outcome:    MaintenanceOutcome,
+    pub cost_cents: Option<i64>,
+    pub technician: Option<String>,
+    pub notes:      Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct DueFilters {
+    pub lab_id: Option<i32>,
+}
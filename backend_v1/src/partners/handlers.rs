@@ -0,0 +1,316 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    delegations::{
+        handlers::DELEGATION_SELECT,
+        models::{CreateDelegationRequest, Delegation},
+    },
+    errors::{AppError, Result},
+    partners::models::{
+        CreateGuestRequest, CreatePartnerRequest, GuestBorrower, GuestLoan, PartnerDepartment,
+        PartnerDetail, PartnerFilters, PartnerLoans, PartnerOutstanding, UpdateGuestRequest,
+        UpdatePartnerRequest,
+    },
+    state::AppState,
+    students::handlers::valid_email,
+};
+
+const PARTNER_SELECT: &str = r#"
+    SELECT p.id,p.name,p.institution,p.contact_name,p.contact_email,p.contact_phone,
+           p.agreement_ref,p.agreement_terms,p.agreement_expires,p.max_items,p.max_loan_days,
+           p.is_active,
+           (SELECT COUNT(*) FROM guest_borrowers g WHERE g.department_id=p.id) AS guest_count,
+           (SELECT COALESCE(SUM(d.quantity),0) FROM delegations d
+            WHERE d.partner_department_id=p.id AND d.status IN ('Issued','Overdue')) AS outstanding_items,
+           p.created_at
+    FROM partner_departments p"#;
+
+const GUEST_SELECT: &str = r#"
+    SELECT g.id,g.department_id,p.name AS department_name,g.name,g.email,g.phone,g.external_ref,
+           g.max_items,g.max_loan_days,g.is_active,
+           (SELECT COALESCE(SUM(d.quantity),0) FROM delegations d
+            WHERE d.guest_borrower_id=g.id AND d.status IN ('Issued','Overdue')) AS outstanding_items,
+           g.created_at
+    FROM guest_borrowers g
+    JOIN partner_departments p ON p.id=g.department_id"#;
+
+/// Outstanding items of a department or guest, summed over open loans.
+async fn items_out(conn: &mut PgConnection, column: &str, id: i32) -> Result<i64> {
+    Ok(sqlx::query_scalar(&format!(
+        "SELECT COALESCE(SUM(quantity),0) FROM delegations
+         WHERE {}=$1 AND status IN ('Issued','Overdue')", column,
+    ))
+    .bind(id).fetch_one(&mut *conn).await?)
+}
+
+/// Settles the partner side of an inter-departmental loan and applies the
+/// agreement: the department (and guest) must be active, the agreement
+/// current, and the loan within their item and loan-length limits. The
+/// department row is locked so concurrent loans count against the same total.
+pub async fn guest_loan(conn: &mut PgConnection, body: &CreateDelegationRequest) -> Result<GuestLoan> {
+    let guest = match body.guest_borrower_id {
+        Some(id) => Some(
+            sqlx::query("SELECT department_id,name,is_active,max_items,max_loan_days FROM guest_borrowers WHERE id=$1")
+                .bind(id).fetch_optional(&mut *conn).await?
+                .ok_or_else(|| AppError::Validation("Guest borrower not found".into()))?,
+        ),
+        None => None,
+    };
+    let department_id = match (&guest, body.partner_department_id) {
+        (Some(g), Some(id)) if g.try_get::<i32,_>("department_id")? != id => {
+            return Err(AppError::Validation("Guest borrower belongs to another department".into()));
+        }
+        (Some(g), _) => g.try_get("department_id")?,
+        (None, Some(id)) => id,
+        (None, None) => {
+            let name = body.guest_department.as_deref().map(str::trim).filter(|n| !n.is_empty())
+                .ok_or_else(|| AppError::Validation("partner_department_id required for inter-departmental borrows".into()))?;
+            sqlx::query_scalar("SELECT id FROM partner_departments WHERE LOWER(name)=LOWER($1)")
+                .bind(name).fetch_optional(&mut *conn).await?
+                .ok_or_else(|| AppError::Validation(format!("'{}' is not a registered partner department", name)))?
+        }
+    };
+
+    let dept = sqlx::query(
+        "SELECT name,is_active,agreement_expires,max_items,max_loan_days,CURRENT_DATE AS today
+         FROM partner_departments WHERE id=$1 FOR UPDATE",
+    )
+    .bind(department_id).fetch_optional(&mut *conn).await?
+    .ok_or_else(|| AppError::Validation("Partner department not found".into()))?;
+    let name: String = dept.try_get("name")?;
+    if !dept.try_get::<bool,_>("is_active")? {
+        return Err(AppError::Validation(format!("{} is not an active partner", name)));
+    }
+    let today: chrono::NaiveDate = dept.try_get("today")?;
+    if let Some(expires) = dept.try_get::<Option<chrono::NaiveDate>,_>("agreement_expires")? {
+        if expires < today {
+            return Err(AppError::Conflict(format!("The agreement with {} expired on {}", name, expires)));
+        }
+        if body.expected_return > expires {
+            return Err(AppError::Validation(format!(
+                "Loans to {} must be back by {}, when the agreement ends", name, expires,
+            )));
+        }
+    }
+
+    // Item limits apply to the department and the guest alike; a guest's own
+    // loan length replaces the department's.
+    let mut item_limits = vec![
+        (dept.try_get::<Option<i32>,_>("max_items")?, "partner_department_id", department_id, name.clone()),
+    ];
+    let mut max_days: Option<i32> = dept.try_get("max_loan_days")?;
+    if let (Some(g), Some(guest_id)) = (&guest, body.guest_borrower_id) {
+        let guest_name: String = g.try_get("name")?;
+        if !g.try_get::<bool,_>("is_active")? {
+            return Err(AppError::Validation(format!("{} is not an active guest borrower", guest_name)));
+        }
+        max_days = g.try_get::<Option<i32>,_>("max_loan_days")?.or(max_days);
+        item_limits.push((g.try_get("max_items")?, "guest_borrower_id", guest_id, guest_name));
+    }
+    if let Some(days) = max_days {
+        let due_by = today + chrono::Duration::days(i64::from(days));
+        if body.expected_return > due_by {
+            return Err(AppError::Validation(format!(
+                "Loans to {} run at most {} day(s); due by {}", name, days, due_by,
+            )));
+        }
+    }
+    for (max, column, id, who) in item_limits {
+        let Some(max) = max else { continue };
+        let out = items_out(conn, column, id).await?;
+        if out + i64::from(body.quantity) > i64::from(max) {
+            return Err(AppError::Conflict(format!(
+                "{} may have {} item(s) out and already has {}", who, max, out,
+            )));
+        }
+    }
+    Ok(GuestLoan { department_id, department_name: name, guest_borrower_id: body.guest_borrower_id })
+}
+
+fn check_contact(email: Option<&str>) -> Result<()> {
+    match email {
+        Some(e) if !valid_email(e.trim()) => Err(AppError::Validation("Invalid email".into())),
+        _ => Ok(()),
+    }
+}
+
+fn check_limits(max_items: Option<i32>, max_loan_days: Option<i32>) -> Result<()> {
+    if max_items.is_some_and(|n| n <= 0) || max_loan_days.is_some_and(|n| n <= 0) {
+        return Err(AppError::Validation("Limits must be >= 1".into()));
+    }
+    Ok(())
+}
+
+async fn fetch_partner(db: &PgPool, id: i32) -> Result<PartnerDepartment> {
+    sqlx::query_as::<_, PartnerDepartment>(&format!("{} WHERE p.id=$1", PARTNER_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+async fn fetch_guest(db: &PgPool, id: i32) -> Result<GuestBorrower> {
+    sqlx::query_as::<_, GuestBorrower>(&format!("{} WHERE g.id=$1", GUEST_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+// GET /partner-departments?search=&active=
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<PartnerFilters>,
+) -> Result<Json<Value>> {
+    let partners = sqlx::query_as::<_, PartnerDepartment>(&format!(
+        "{} WHERE ($1::TEXT IS NULL OR STRPOS(LOWER(p.name), LOWER($1)) > 0
+                   OR STRPOS(LOWER(COALESCE(p.institution,'')), LOWER($1)) > 0)
+              AND (NOT $2 OR p.is_active)
+            ORDER BY p.name", PARTNER_SELECT,
+    ))
+    .bind(&filters.search).bind(filters.active.as_deref() == Some("true"))
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": partners })))
+}
+
+// GET /partner-departments/:id — with its guest borrowers
+pub async fn get_one(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<PartnerDetail>> {
+    let department = fetch_partner(&state.db, id).await?;
+    let guests = sqlx::query_as::<_, GuestBorrower>(&format!(
+        "{} WHERE g.department_id=$1 ORDER BY g.name", GUEST_SELECT,
+    ))
+    .bind(id).fetch_all(&state.db).await?;
+    Ok(Json(PartnerDetail { department, guests }))
+}
+
+// POST /partner-departments
+pub async fn create(
+    _auth: AuthUser, State(state): State<AppState>, Json(body): Json<CreatePartnerRequest>,
+) -> Result<(StatusCode, Json<PartnerDepartment>)> {
+    if body.name.trim().is_empty() { return Err(AppError::Validation("name required".into())); }
+    check_contact(body.contact_email.as_deref())?;
+    check_limits(body.max_items, body.max_loan_days)?;
+    let id: i32 = sqlx::query_scalar(
+        r#"INSERT INTO partner_departments
+               (name,institution,contact_name,contact_email,contact_phone,agreement_ref,
+                agreement_terms,agreement_expires,max_items,max_loan_days)
+           VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10) RETURNING id"#,
+    )
+    .bind(body.name.trim()).bind(&body.institution).bind(&body.contact_name)
+    .bind(body.contact_email.as_deref().map(|e| e.trim().to_lowercase())).bind(&body.contact_phone)
+    .bind(&body.agreement_ref).bind(&body.agreement_terms).bind(body.agreement_expires)
+    .bind(body.max_items).bind(body.max_loan_days)
+    .fetch_one(&state.db).await?;
+    Ok((StatusCode::CREATED, Json(fetch_partner(&state.db, id).await?)))
+}
+
+// PUT /partner-departments/:id — `is_active: false` retires the department
+pub async fn update(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<UpdatePartnerRequest>,
+) -> Result<Json<PartnerDepartment>> {
+    if body.name.as_deref().is_some_and(|n| n.trim().is_empty()) {
+        return Err(AppError::Validation("name required".into()));
+    }
+    check_contact(body.contact_email.as_deref())?;
+    check_limits(body.max_items, body.max_loan_days)?;
+    let r = sqlx::query(
+        r#"UPDATE partner_departments SET
+               name=COALESCE($1,name), institution=COALESCE($2,institution),
+               contact_name=COALESCE($3,contact_name), contact_email=COALESCE($4,contact_email),
+               contact_phone=COALESCE($5,contact_phone), agreement_ref=COALESCE($6,agreement_ref),
+               agreement_terms=COALESCE($7,agreement_terms),
+               agreement_expires=COALESCE($8,agreement_expires),
+               max_items=COALESCE($9,max_items), max_loan_days=COALESCE($10,max_loan_days),
+               is_active=COALESCE($11,is_active)
+           WHERE id=$12"#,
+    )
+    .bind(body.name.as_deref().map(str::trim)).bind(&body.institution).bind(&body.contact_name)
+    .bind(body.contact_email.as_deref().map(|e| e.trim().to_lowercase())).bind(&body.contact_phone)
+    .bind(&body.agreement_ref).bind(&body.agreement_terms).bind(body.agreement_expires)
+    .bind(body.max_items).bind(body.max_loan_days).bind(body.is_active).bind(id)
+    .execute(&state.db).await?;
+    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+    Ok(Json(fetch_partner(&state.db, id).await?))
+}
+
+// POST /partner-departments/:id/guests
+pub async fn create_guest(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<CreateGuestRequest>,
+) -> Result<(StatusCode, Json<GuestBorrower>)> {
+    if body.name.trim().is_empty() { return Err(AppError::Validation("name required".into())); }
+    check_contact(body.email.as_deref())?;
+    check_limits(body.max_items, body.max_loan_days)?;
+    if sqlx::query("SELECT id FROM partner_departments WHERE id=$1")
+        .bind(id).fetch_optional(&state.db).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    let guest_id: i32 = sqlx::query_scalar(
+        r#"INSERT INTO guest_borrowers (department_id,name,email,phone,external_ref,max_items,max_loan_days)
+           VALUES ($1,$2,$3,$4,$5,$6,$7) RETURNING id"#,
+    )
+    .bind(id).bind(body.name.trim()).bind(body.email.as_deref().map(|e| e.trim().to_lowercase()))
+    .bind(&body.phone).bind(&body.external_ref).bind(body.max_items).bind(body.max_loan_days)
+    .fetch_one(&state.db).await?;
+    Ok((StatusCode::CREATED, Json(fetch_guest(&state.db, guest_id).await?)))
+}
+
+// PUT /guest-borrowers/:id
+pub async fn update_guest(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<UpdateGuestRequest>,
+) -> Result<Json<GuestBorrower>> {
+    if body.name.as_deref().is_some_and(|n| n.trim().is_empty()) {
+        return Err(AppError::Validation("name required".into()));
+    }
+    check_contact(body.email.as_deref())?;
+    check_limits(body.max_items, body.max_loan_days)?;
+    let r = sqlx::query(
+        r#"UPDATE guest_borrowers SET
+               name=COALESCE($1,name), email=COALESCE($2,email), phone=COALESCE($3,phone),
+               external_ref=COALESCE($4,external_ref), max_items=COALESCE($5,max_items),
+               max_loan_days=COALESCE($6,max_loan_days), is_active=COALESCE($7,is_active)
+           WHERE id=$8"#,
+    )
+    .bind(body.name.as_deref().map(str::trim)).bind(body.email.as_deref().map(|e| e.trim().to_lowercase()))
+    .bind(&body.phone).bind(&body.external_ref).bind(body.max_items).bind(body.max_loan_days)
+    .bind(body.is_active).bind(id)
+    .execute(&state.db).await?;
+    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+    Ok(Json(fetch_guest(&state.db, id).await?))
+}
+
+// GET /partner-departments/outstanding — open loans per department
+pub async fn outstanding(
+    _auth: AuthUser, State(state): State<AppState>,
+) -> Result<Json<Value>> {
+    let rows = sqlx::query_as::<_, PartnerOutstanding>(
+        r#"SELECT p.id AS department_id,p.name AS department_name,p.contact_name,p.contact_email,
+                  COUNT(d.id) AS loans,
+                  COALESCE(SUM(d.quantity),0) AS items,
+                  COUNT(d.id) FILTER (WHERE d.status='Overdue') AS overdue_loans,
+                  MIN(d.expected_return) AS earliest_due
+           FROM partner_departments p
+           JOIN delegations d ON d.partner_department_id=p.id AND d.status IN ('Issued','Overdue')
+           GROUP BY p.id,p.name,p.contact_name,p.contact_email
+           ORDER BY overdue_loans DESC, items DESC, p.name"#,
+    )
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": rows })))
+}
+
+// GET /partner-departments/:id/outstanding — the department's open loans
+pub async fn department_outstanding(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<PartnerLoans>> {
+    let department = fetch_partner(&state.db, id).await?;
+    let loans = sqlx::query_as::<_, Delegation>(&format!(
+        "{} WHERE d.partner_department_id=$1 AND d.status IN ('Issued','Overdue')
+            ORDER BY d.expected_return, d.id", DELEGATION_SELECT,
+    ))
+    .bind(id).fetch_all(&state.db).await?;
+    Ok(Json(PartnerLoans { department, loans }))
+}
@@ -0,0 +1,135 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::delegations::models::Delegation;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PartnerDepartment {
+    pub id:                i32,
+    pub name:              String,
+    pub institution:       Option<String>,
+    pub contact_name:      Option<String>,
+    pub contact_email:     Option<String>,
+    pub contact_phone:     Option<String>,
+    pub agreement_ref:     Option<String>,
+    pub agreement_terms:   Option<String>,
+    pub agreement_expires: Option<NaiveDate>,
+    /// Items the department may have out at once
+    pub max_items:         Option<i32>,
+    pub max_loan_days:     Option<i32>,
+    pub is_active:         bool,
+    pub guest_count:       i64,
+    pub outstanding_items: i64,
+    pub created_at:        DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct GuestBorrower {
+    pub id:                i32,
+    pub department_id:     i32,
+    pub department_name:   String,
+    pub name:              String,
+    pub email:             Option<String>,
+    pub phone:             Option<String>,
+    pub external_ref:      Option<String>,
+    /// Override the department's limits when set
+    pub max_items:         Option<i32>,
+    pub max_loan_days:     Option<i32>,
+    pub is_active:         bool,
+    pub outstanding_items: i64,
+    pub created_at:        DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartnerDetail {
+    #[serde(flatten)]
+    pub department: PartnerDepartment,
+    pub guests:     Vec<GuestBorrower>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePartnerRequest {
+    pub name:              String,
+    pub institution:       Option<String>,
+    pub contact_name:      Option<String>,
+    pub contact_email:     Option<String>,
+    pub contact_phone:     Option<String>,
+    pub agreement_ref:     Option<String>,
+    pub agreement_terms:   Option<String>,
+    pub agreement_expires: Option<NaiveDate>,
+    pub max_items:         Option<i32>,
+    pub max_loan_days:     Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePartnerRequest {
+    pub name:              Option<String>,
+    pub institution:       Option<String>,
+    pub contact_name:      Option<String>,
+    pub contact_email:     Option<String>,
+    pub contact_phone:     Option<String>,
+    pub agreement_ref:     Option<String>,
+    pub agreement_terms:   Option<String>,
+    pub agreement_expires: Option<NaiveDate>,
+    pub max_items:         Option<i32>,
+    pub max_loan_days:     Option<i32>,
+    pub is_active:         Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct PartnerFilters {
+    pub search: Option<String>,
+    /// If "true", only active departments
+    pub active: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGuestRequest {
+    pub name:          String,
+    pub email:         Option<String>,
+    pub phone:         Option<String>,
+    pub external_ref:  Option<String>,
+    pub max_items:     Option<i32>,
+    pub max_loan_days: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateGuestRequest {
+    pub name:          Option<String>,
+    pub email:         Option<String>,
+    pub phone:         Option<String>,
+    pub external_ref:  Option<String>,
+    pub max_items:     Option<i32>,
+    pub max_loan_days: Option<i32>,
+    pub is_active:     Option<bool>,
+}
+
+/// One row of the outstanding-items report
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PartnerOutstanding {
+    pub department_id:   i32,
+    pub department_name: String,
+    pub contact_name:    Option<String>,
+    pub contact_email:   Option<String>,
+    pub loans:           i64,
+    pub items:           i64,
+    pub overdue_loans:   i64,
+    pub earliest_due:    Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartnerLoans {
+    #[serde(flatten)]
+    pub department: PartnerDepartment,
+    pub loans:      Vec<Delegation>,
+}
+
+/// The partner side of an inter-departmental loan
+pub struct GuestLoan {
+    pub department_id:     i32,
+    pub department_name:   String,
+    pub guest_borrower_id: Option<i32>,
+}
@@ -0,0 +1,69 @@
+use pdf_writer::{Content, Name, Pdf, Rect, Ref, Str};
+
+/// A4 in points
+const PAGE_WIDTH:  f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN:      f32 = 72.0;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD:    Name = Name(b"F2");
+
+/// One line of a generated document
+pub enum Line {
+    Heading(String),
+    Text(String),
+    /// Label and value on the same line, label in bold
+    Field(&'static str, String),
+    Blank,
+}
+
+/// Built-in fonts use WinAnsi encoding; characters outside Latin-1 print as '?'.
+fn latin1(text: &str) -> Vec<u8> {
+    text.chars().map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?')).collect()
+}
+
+/// Renders a single-page document in the built-in Helvetica fonts. Lines
+/// past the bottom margin are dropped, so callers keep documents short.
+pub fn document(title: &str, lines: &[Line]) -> Vec<u8> {
+    let catalog = Ref::new(1);
+    let tree = Ref::new(2);
+    let page = Ref::new(3);
+    let regular = Ref::new(4);
+    let bold = Ref::new(5);
+    let contents = Ref::new(6);
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog).pages(tree);
+    pdf.pages(tree).kids([page]).count(1);
+    {
+        let mut p = pdf.page(page);
+        p.parent(tree).media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT)).contents(contents);
+        p.resources().fonts().pair(REGULAR, regular).pair(BOLD, bold);
+    }
+    for (id, face) in [(regular, Name(b"Helvetica")), (bold, Name(b"Helvetica-Bold"))] {
+        pdf.type1_font(id).base_font(face).encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+
+    let mut content = Content::new();
+    let mut y = PAGE_HEIGHT - MARGIN;
+    let mut write = |font: Name, size: f32, x: f32, y: f32, text: &str| {
+        content.begin_text().set_font(font, size).next_line(x, y).show(Str(&latin1(text))).end_text();
+    };
+    write(BOLD, 20.0, MARGIN, y, title);
+    y -= 40.0;
+    for line in lines {
+        if y < MARGIN { break; }
+        match line {
+            Line::Heading(t) => { y -= 6.0; write(BOLD, 13.0, MARGIN, y, t); y -= 22.0; }
+            Line::Text(t)    => { write(REGULAR, 11.0, MARGIN, y, t); y -= 16.0; }
+            Line::Field(label, value) => {
+                write(BOLD, 11.0, MARGIN, y, label);
+                write(REGULAR, 11.0, MARGIN + 150.0, y, value);
+                y -= 16.0;
+            }
+            Line::Blank => y -= 12.0,
+        }
+    }
+    pdf.stream(contents, &content.finish());
+    pdf.finish()
+}
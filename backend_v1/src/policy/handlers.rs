@@ -0,0 +1,314 @@
+use axum::{extract::{Path, State}, Json};
+use sqlx::{PgConnection, PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    errors::{AppError, Result},
+    policy::models::{
+        BanAction, BanPolicy, BanRequest, PolicyCheck, SuspendRequest, UpdateBanPolicyRequest,
+    },
+    state::AppState,
+    students::{handlers::fetch_student, models::{AccountStatus, Student}},
+};
+
+const POLICY_SELECT: &str = r#"
+    SELECT max_lost_tools,lost_window_months,count_resolved_losses,
+           max_unpaid_cents,max_overdue_loans,updated_by,updated_at
+    FROM ban_policy WHERE id=1"#;
+
+async fn fetch_policy(conn: &mut PgConnection) -> Result<BanPolicy> {
+    Ok(sqlx::query_as::<_, BanPolicy>(POLICY_SELECT).fetch_one(&mut *conn).await?)
+}
+
+/// Measures a student against the policy without changing anything.
+async fn check(conn: &mut PgConnection, policy: &BanPolicy, student_id: &str) -> Result<PolicyCheck> {
+    let row = sqlx::query(
+        r#"SELECT s.account_status,
+                  (SELECT COUNT(*) FROM delegations d
+                    WHERE d.student_id=s.student_id AND d.status='Lost'::delegation_status
+                      AND ($2 OR d.resolution IS NULL)
+                      AND ($3::INT IS NULL OR d.lost_at >= NOW() - make_interval(months => $3)))
+                    AS lost_tools,
+                  (SELECT COALESCE(SUM(b.outstanding_cents),0)::BIGINT FROM charge_balances b
+                    WHERE b.student_id=s.student_id) AS unpaid_cents,
+                  (SELECT COUNT(*) FROM delegations d
+                    WHERE d.student_id=s.student_id AND d.status='Overdue'::delegation_status)
+                    AS overdue_loans
+           FROM students s WHERE s.student_id=$1"#,
+    )
+    .bind(student_id).bind(policy.count_resolved_losses).bind(policy.lost_window_months)
+    .fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
+
+    let lost_tools:    i64 = row.try_get("lost_tools")?;
+    let unpaid_cents:  i64 = row.try_get("unpaid_cents")?;
+    let overdue_loans: i64 = row.try_get("overdue_loans")?;
+
+    let mut violations = Vec::new();
+    if let Some(max) = policy.max_lost_tools.filter(|&m| lost_tools >= i64::from(m)) {
+        let window = policy.lost_window_months
+            .map(|m| format!(" in the last {} month(s)", m)).unwrap_or_default();
+        violations.push(format!("Lost {} tool(s){} (limit {})", lost_tools, window, max));
+    }
+    if let Some(max) = policy.max_unpaid_cents.filter(|&m| unpaid_cents > m) {
+        violations.push(format!("Owes {} cents in unpaid charges (limit {})", unpaid_cents, max));
+    }
+    if let Some(max) = policy.max_overdue_loans.filter(|&m| overdue_loans >= i64::from(m)) {
+        violations.push(format!("Has {} overdue loan(s) (limit {})", overdue_loans, max));
+    }
+
+    Ok(PolicyCheck {
+        student_id: student_id.to_string(),
+        account_status: row.try_get("account_status")?,
+        lost_tools, unpaid_cents, overdue_loans, violations,
+    })
+}
+
+/// The one place bans are decided. Re-measures the student, bans them with
+/// the violated rules as the reason, or lifts a policy ban once they comply.
+/// Staff bans and suspensions are left alone, as are students whose policy
+/// ban staff lifted until they comply again. Every change is written to the
+/// ban history.
+pub async fn evaluate(conn: &mut PgConnection, student_id: &str, actor: &str) -> Result<PolicyCheck> {
+    let row = sqlx::query(
+        "SELECT ban_is_manual,policy_waived FROM students WHERE student_id=$1 FOR UPDATE",
+    )
+    .bind(student_id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
+    let manual: bool = row.try_get("ban_is_manual")?;
+    let waived: bool = row.try_get("policy_waived")?;
+    let policy = fetch_policy(conn).await?;
+    let mut result = check(conn, &policy, student_id).await?;
+
+    match (result.ban_reason(), &result.account_status) {
+        (_, AccountStatus::Suspended | AccountStatus::Graduated | AccountStatus::Archived) => {}
+        (_, AccountStatus::Banned) if manual => {}
+        (Some(_), AccountStatus::Active) if waived => {}
+        (Some(reason), AccountStatus::Active) => {
+            sqlx::query(
+                "UPDATE students SET account_status='Banned', ban_reason=$1, banned_at=NOW() WHERE student_id=$2",
+            )
+            .bind(&reason).bind(student_id).execute(&mut *conn).await?;
+            record_event(conn, student_id, BanAction::Banned, &reason, actor).await?;
+            result.account_status = AccountStatus::Banned;
+        }
+        // Still banned: keep the reason current.
+        (Some(reason), AccountStatus::Banned) => {
+            sqlx::query("UPDATE students SET ban_reason=$1 WHERE student_id=$2")
+                .bind(&reason).bind(student_id).execute(&mut *conn).await?;
+        }
+        (None, AccountStatus::Banned) => {
+            clear_ban(conn, student_id, false).await?;
+            record_event(conn, student_id, BanAction::Unbanned, "No longer violates the ban policy", actor).await?;
+            result.account_status = AccountStatus::Active;
+        }
+        (None, AccountStatus::Active) => {
+            if waived {
+                sqlx::query("UPDATE students SET policy_waived=FALSE WHERE student_id=$1")
+                    .bind(student_id).execute(&mut *conn).await?;
+            }
+        }
+    }
+    Ok(result)
+}
+
+async fn clear_ban(conn: &mut PgConnection, student_id: &str, waive_policy: bool) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE students SET account_status='Active', ban_reason=NULL, banned_at=NULL,
+               suspended_until=NULL, ban_is_manual=FALSE, policy_waived=$1
+           WHERE student_id=$2"#,
+    )
+    .bind(waive_policy).bind(student_id).execute(&mut *conn).await?;
+    Ok(())
+}
+
+/// Returns the id of the new history entry.
+async fn record_event(
+    conn: &mut PgConnection, student_id: &str, action: BanAction, reason: &str, actor: &str,
+) -> Result<i32> {
+    Ok(sqlx::query(
+        "INSERT INTO student_ban_events (student_id,action,reason,actor) VALUES ($1,$2,$3,$4) RETURNING id",
+    )
+    .bind(student_id).bind(action).bind(reason).bind(actor)
+    .fetch_one(&mut *conn).await?.try_get("id")?)
+}
+
+async fn lock_status(conn: &mut PgConnection, student_id: &str) -> Result<AccountStatus> {
+    sqlx::query_scalar("SELECT account_status FROM students WHERE student_id=$1 FOR UPDATE")
+        .bind(student_id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)
+}
+
+/// Lifts any ban or suspension. With `waive_policy` a policy ban stays lifted
+/// until the student complies; otherwise the policy is re-applied at once.
+pub async fn lift_ban(
+    conn: &mut PgConnection, student_id: &str, reason: &str, waive_policy: bool, actor: &str,
+) -> Result<PolicyCheck> {
+    if !matches!(lock_status(conn, student_id).await?, AccountStatus::Banned | AccountStatus::Suspended) {
+        return Err(AppError::Conflict("Student is not banned".into()));
+    }
+    clear_ban(conn, student_id, waive_policy).await?;
+    record_event(conn, student_id, BanAction::Unbanned, reason, actor).await?;
+    evaluate(conn, student_id, actor).await
+}
+
+/// Lifts suspensions that have run out. Returns how many were lifted.
+pub async fn expire_suspensions(db: &PgPool) -> Result<u64> {
+    let ids: Vec<String> = sqlx::query_scalar(
+        "SELECT student_id FROM students WHERE account_status='Suspended' AND suspended_until <= NOW()",
+    )
+    .fetch_all(db).await?;
+
+    let mut lifted = 0;
+    for id in &ids {
+        match expire_suspension(db, id).await {
+            Ok(()) => lifted += 1,
+            Err(e) => tracing::error!("Suspension of {} not lifted: {}", id, e),
+        }
+    }
+    Ok(lifted)
+}
+
+async fn expire_suspension(db: &PgPool, student_id: &str) -> Result<()> {
+    let mut tx = db.begin().await?;
+    lift_ban(&mut tx, student_id, "Suspension expired", false, "system").await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+fn required_reason(reason: &str) -> Result<&str> {
+    let reason = reason.trim();
+    if reason.is_empty() { return Err(AppError::Validation("Reason required".into())); }
+    Ok(reason)
+}
+
+/// Re-evaluates every student whose standing may have changed outside a
+/// request (overdue sweeps, policy edits). Returns how many changed status.
+pub async fn evaluate_all(db: &PgPool, actor: &str) -> Result<u64> {
+    let ids = policy_subjects(&mut *db.acquire().await?).await?;
+    let mut changed = 0;
+    for id in ids {
+        let mut tx = db.begin().await?;
+        if reevaluate(&mut tx, &id, actor).await? { changed += 1; }
+        tx.commit().await?;
+    }
+    Ok(changed)
+}
+
+/// Students the policy could ban or unban.
+async fn policy_subjects(conn: &mut PgConnection) -> Result<Vec<String>> {
+    Ok(sqlx::query_scalar(
+        r#"SELECT student_id FROM students WHERE account_status='Banned'
+           UNION SELECT student_id FROM delegations WHERE status IN ('Lost','Overdue')
+           UNION SELECT student_id FROM charge_balances WHERE outstanding_cents > 0"#,
+    )
+    .fetch_all(&mut *conn).await?)
+}
+
+/// Returns whether the student's status changed.
+async fn reevaluate(conn: &mut PgConnection, student_id: &str, actor: &str) -> Result<bool> {
+    let before: AccountStatus = sqlx::query_scalar(
+        "SELECT account_status FROM students WHERE student_id=$1",
+    )
+    .bind(student_id).fetch_one(&mut *conn).await?;
+    Ok(evaluate(conn, student_id, actor).await?.account_status != before)
+}
+
+// GET /ban-policy
+pub async fn get_policy(_auth: AuthUser, State(state): State<AppState>) -> Result<Json<BanPolicy>> {
+    let mut conn = state.db.acquire().await?;
+    fetch_policy(&mut conn).await.map(Json)
+}
+
+// PUT /ban-policy — replaces the policy and re-evaluates affected students,
+// all in one transaction
+pub async fn update_policy(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Json(body): Json<UpdateBanPolicyRequest>,
+) -> Result<Json<BanPolicy>> {
+    if body.max_lost_tools.is_some_and(|v| v <= 0)
+        || body.lost_window_months.is_some_and(|v| v <= 0)
+        || body.max_overdue_loans.is_some_and(|v| v <= 0)
+        || body.max_unpaid_cents.is_some_and(|v| v < 0)
+    {
+        return Err(AppError::Validation("Policy thresholds must be positive".into()));
+    }
+    let mut tx = state.db.begin().await?;
+    sqlx::query(
+        r#"UPDATE ban_policy SET max_lost_tools=$1, lost_window_months=$2, count_resolved_losses=$3,
+               max_unpaid_cents=$4, max_overdue_loans=$5, updated_by=$6, updated_at=NOW()
+           WHERE id=1"#,
+    )
+    .bind(body.max_lost_tools).bind(body.lost_window_months).bind(body.count_resolved_losses)
+    .bind(body.max_unpaid_cents).bind(body.max_overdue_loans).bind(&claims.sub)
+    .execute(&mut *tx).await?;
+
+    for id in policy_subjects(&mut tx).await? {
+        reevaluate(&mut tx, &id, &claims.sub).await?;
+    }
+    let policy = fetch_policy(&mut tx).await?;
+    tx.commit().await?;
+    Ok(Json(policy))
+}
+
+// GET /students/:id/policy-check — dry run, changes nothing
+pub async fn policy_check(
+    _auth: AuthUser, State(state): State<AppState>, Path(student_id): Path<String>,
+) -> Result<Json<PolicyCheck>> {
+    let mut conn = state.db.acquire().await?;
+    let policy = fetch_policy(&mut conn).await?;
+    check(&mut conn, &policy, &student_id).await.map(Json)
+}
+
+// POST /students/:id/ban — staff ban; only an unban or upheld appeal lifts it
+pub async fn ban(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(student_id): Path<String>, Json(body): Json<BanRequest>,
+) -> Result<Json<Student>> {
+    let reason = required_reason(&body.reason)?;
+    let mut tx = state.db.begin().await?;
+    lock_status(&mut tx, &student_id).await?;
+    sqlx::query(
+        r#"UPDATE students SET account_status='Banned', ban_reason=$1, banned_at=NOW(),
+               suspended_until=NULL, ban_is_manual=TRUE, policy_waived=FALSE
+           WHERE student_id=$2"#,
+    )
+    .bind(reason).bind(&student_id).execute(&mut *tx).await?;
+    record_event(&mut tx, &student_id, BanAction::Banned, reason, &claims.sub).await?;
+    tx.commit().await?;
+    Ok(Json(fetch_student(&state.db, &student_id).await?))
+}
+
+// POST /students/:id/suspend — banned until `until`, then lifted by jobs
+pub async fn suspend(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(student_id): Path<String>, Json(body): Json<SuspendRequest>,
+) -> Result<Json<Student>> {
+    let reason = required_reason(&body.reason)?;
+    if body.until <= chrono::Utc::now() {
+        return Err(AppError::Validation("Suspension must end in the future".into()));
+    }
+    let mut tx = state.db.begin().await?;
+    lock_status(&mut tx, &student_id).await?;
+    sqlx::query(
+        r#"UPDATE students SET account_status='Suspended', ban_reason=$1, banned_at=NOW(),
+               suspended_until=$2, ban_is_manual=TRUE, policy_waived=FALSE
+           WHERE student_id=$3"#,
+    )
+    .bind(reason).bind(body.until).bind(&student_id).execute(&mut *tx).await?;
+    record_event(
+        &mut tx, &student_id, BanAction::Suspended,
+        &format!("{} (until {})", reason, state.config.local_time(body.until)), &claims.sub,
+    ).await?;
+    tx.commit().await?;
+    Ok(Json(fetch_student(&state.db, &student_id).await?))
+}
+
+// POST /students/:id/unban — lifts any ban; a policy ban stays lifted until
+// the student complies again
+pub async fn unban(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(student_id): Path<String>, Json(body): Json<BanRequest>,
+) -> Result<Json<Student>> {
+    let reason = required_reason(&body.reason)?;
+    let mut tx = state.db.begin().await?;
+    lift_ban(&mut tx, &student_id, reason, true, &claims.sub).await?;
+    tx.commit().await?;
+    Ok(Json(fetch_student(&state.db, &student_id).await?))
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::students::models::AccountStatus;
+
+/// Ban rules. A `None` threshold disables that rule.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct BanPolicy {
+    pub max_lost_tools:        Option<i32>,
+    /// Only losses within this many months count; `None` counts all time.
+    pub lost_window_months:    Option<i32>,
+    /// Count losses that were since recovered or paid for.
+    pub count_resolved_losses: bool,
+    pub max_unpaid_cents:      Option<i64>,
+    pub max_overdue_loans:     Option<i32>,
+    pub updated_by:            Option<String>,
+    pub updated_at:            DateTime<Utc>,
+}
+
+/// Replaces the whole policy; omitted thresholds are disabled.
+#[derive(Debug, Deserialize)]
+pub struct UpdateBanPolicyRequest {
+    pub max_lost_tools:        Option<i32>,
+    pub lost_window_months:    Option<i32>,
+    #[serde(default)]
+    pub count_resolved_losses: bool,
+    pub max_unpaid_cents:      Option<i64>,
+    pub max_overdue_loans:     Option<i32>,
+}
+
+/// A student's standing against the policy
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyCheck {
+    pub student_id:     String,
+    pub account_status: AccountStatus,
+    pub lost_tools:     i64,
+    pub unpaid_cents:   i64,
+    pub overdue_loans:  i64,
+    pub violations:     Vec<String>,
+}
+
+impl PolicyCheck {
+    pub fn ban_reason(&self) -> Option<String> {
+        (!self.violations.is_empty()).then(|| self.violations.join("; "))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[sqlx(type_name = "ban_action", rename_all = "PascalCase")]
+pub enum BanAction {
+    Banned,
+    Unbanned,
+    Suspended,
+}
+
+/// One entry in a student's ban history
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct BanEvent {
+    pub id:         i32,
+    pub action:     BanAction,
+    pub reason:     String,
+    pub actor:      String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body for POST /students/:id/ban and /students/:id/unban
+#[derive(Debug, Deserialize)]
+pub struct BanRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuspendRequest {
+    pub reason: String,
+    pub until:  DateTime<Utc>,
+}
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    errors::{AppError, Result},
+    purchasing::models::{
+        ConsumptionWindow, CreatePurchaseOrderRequest, CreateSupplierRequest, PurchaseOrder, PurchaseOrderDetail,
+        PurchaseOrderFilters, PurchaseOrderLine, ReceiveRequest, ReorderQuery, ReorderSuggestion, StockForecast,
+        Supplier, UpdateSupplierRequest,
+    },
+    state::AppState,
+    stock::{handlers::record_movement, models::{NewMovement, StockMovementKind}},
+};
+
+// ── Suppliers ─────────────────────────────────────────────────────────────────
+
+pub async fn list_suppliers(_auth: AuthUser, State(state): State<AppState>) -> Result<Json<Value>> {
+    let suppliers = sqlx::query_as::<_, Supplier>(
+        "SELECT id,name,contact_name,email,phone,address,notes,created_at FROM suppliers ORDER BY name",
+    )
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": suppliers })))
+}
+
+pub async fn get_supplier(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<Supplier>> {
+    sqlx::query_as::<_, Supplier>(
+        "SELECT id,name,contact_name,email,phone,address,notes,created_at FROM suppliers WHERE id=$1",
+    )
+    .bind(id).fetch_optional(&state.db).await?.ok_or(AppError::NotFound).map(Json)
+}
+
+pub async fn create_supplier(
+    _auth: AuthUser, State(state): State<AppState>, Json(body): Json<CreateSupplierRequest>,
+) -> Result<(StatusCode, Json<Supplier>)> {
+    if body.name.trim().is_empty() { return Err(AppError::Validation("Supplier name required".into())); }
+    if body.email.as_deref().is_some_and(|e| !e.contains('@')) {
+        return Err(AppError::Validation("Invalid email".into()));
+    }
+    let s = sqlx::query_as::<_, Supplier>(
+        r#"INSERT INTO suppliers (name,contact_name,email,phone,address,notes)
+           VALUES ($1,$2,$3,$4,$5,$6)
+           RETURNING id,name,contact_name,email,phone,address,notes,created_at"#,
+    )
+    .bind(body.name.trim()).bind(&body.contact_name)
+    .bind(body.email.as_deref().map(|e| e.trim().to_lowercase()))
+    .bind(&body.phone).bind(&body.address).bind(&body.notes)
+    .fetch_one(&state.db).await?;
+    Ok((StatusCode::CREATED, Json(s)))
+}
+
+pub async fn update_supplier(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<UpdateSupplierRequest>,
+) -> Result<Json<Supplier>> {
+    sqlx::query_as::<_, Supplier>(
+        r#"UPDATE suppliers SET
+               name=COALESCE($1,name), contact_name=COALESCE($2,contact_name),
+               email=COALESCE($3,email), phone=COALESCE($4,phone),
+               address=COALESCE($5,address), notes=COALESCE($6,notes)
+           WHERE id=$7
+           RETURNING id,name,contact_name,email,phone,address,notes,created_at"#,
+    )
+    .bind(&body.name).bind(&body.contact_name).bind(&body.email)
+    .bind(&body.phone).bind(&body.address).bind(&body.notes).bind(id)
+    .fetch_optional(&state.db).await?.ok_or(AppError::NotFound).map(Json)
+}
+
+// ── Purchase orders ───────────────────────────────────────────────────────────
+
+const PO_SELECT: &str = r#"
+    SELECT po.id,po.supplier_id,s.name AS supplier_name,po.status,po.order_date,
+           po.expected_delivery,po.notes,
+           COALESCE((SELECT SUM(pl.quantity_ordered*pl.unit_cost_cents)
+                     FROM purchase_order_lines pl
+                     WHERE pl.purchase_order_id=po.id),0)::BIGINT AS total_cost_cents,
+           po.created_by,po.created_at,po.closed_at
+    FROM purchase_orders po JOIN suppliers s ON s.id=po.supplier_id"#;
+
+async fn fetch_detail(db: &PgPool, id: i32) -> Result<PurchaseOrderDetail> {
+    let order = sqlx::query_as::<_, PurchaseOrder>(&format!("{} WHERE po.id=$1", PO_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)?;
+    let lines = sqlx::query_as::<_, PurchaseOrderLine>(
+        r#"SELECT pl.id, pl.tool_id, t.name AS tool_name, l.name AS lab_name,
+                  pl.quantity_ordered, pl.quantity_received, pl.unit_cost_cents
+           FROM purchase_order_lines pl
+           JOIN tools t ON t.id=pl.tool_id
+           LEFT JOIN labs l ON l.id=t.lab_id
+           WHERE pl.purchase_order_id=$1
+           ORDER BY pl.id"#,
+    )
+    .bind(id).fetch_all(db).await?;
+    Ok(PurchaseOrderDetail { order, lines })
+}
+
+pub async fn list_orders(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<PurchaseOrderFilters>,
+) -> Result<Json<Value>> {
+    let orders = sqlx::query_as::<_, PurchaseOrder>(&format!(
+        "{} WHERE ($1::TEXT IS NULL OR po.status::text ILIKE $1)
+              AND ($2::INT IS NULL OR po.supplier_id=$2)
+            ORDER BY po.created_at DESC", PO_SELECT,
+    ))
+    .bind(&filters.status).bind(filters.supplier_id)
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": orders })))
+}
+
+pub async fn get_order(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<PurchaseOrderDetail>> {
+    fetch_detail(&state.db, id).await.map(Json)
+}
+
+pub async fn create_order(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Json(body): Json<CreatePurchaseOrderRequest>,
+) -> Result<(StatusCode, Json<PurchaseOrderDetail>)> {
+    if body.lines.is_empty() { return Err(AppError::Validation("At least one line required".into())); }
+    if body.lines.iter().any(|l| l.quantity <= 0) {
+        return Err(AppError::Validation("Line quantity must be >= 1".into()));
+    }
+    if body.lines.iter().any(|l| l.unit_cost_cents.unwrap_or(0) < 0) {
+        return Err(AppError::Validation("Unit cost cannot be negative".into()));
+    }
+
+    let mut tx = state.db.begin().await?;
+    if sqlx::query("SELECT id FROM suppliers WHERE id=$1").bind(body.supplier_id)
+        .fetch_optional(&mut *tx).await?.is_none() { return Err(AppError::NotFound); }
+
+    let id: i32 = sqlx::query(
+        r#"INSERT INTO purchase_orders (supplier_id,expected_delivery,notes,created_by)
+           VALUES ($1,$2,$3,$4) RETURNING id"#,
+    )
+    .bind(body.supplier_id).bind(body.expected_delivery).bind(&body.notes).bind(&claims.sub)
+    .fetch_one(&mut *tx).await?.try_get("id")?;
+
+    for line in &body.lines {
+        if sqlx::query("SELECT id FROM tools WHERE id=$1 AND deleted_at IS NULL").bind(line.tool_id)
+            .fetch_optional(&mut *tx).await?.is_none() {
+            return Err(AppError::Validation(format!("Tool {} does not exist", line.tool_id)));
+        }
+        sqlx::query(
+            r#"INSERT INTO purchase_order_lines
+                   (purchase_order_id,tool_id,quantity_ordered,unit_cost_cents)
+               VALUES ($1,$2,$3,$4)"#,
+        )
+        .bind(id).bind(line.tool_id).bind(line.quantity).bind(line.unit_cost_cents.unwrap_or(0))
+        .execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(fetch_detail(&state.db, id).await?)))
+}
+
+/// POST /purchase-orders/:id/receive — each received quantity enters stock
+/// as a Received movement; the order closes once every line is complete.
+pub async fn receive_order(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<ReceiveRequest>,
+) -> Result<Json<PurchaseOrderDetail>> {
+    if body.receive_all != body.lines.is_empty() {
+        return Err(AppError::Validation("Send either lines or receive_all".into()));
+    }
+    let mut tx = state.db.begin().await?;
+
+    let po = sqlx::query(
+        r#"SELECT po.status::text AS status, s.name AS supplier_name
+           FROM purchase_orders po JOIN suppliers s ON s.id=po.supplier_id
+           WHERE po.id=$1 FOR UPDATE OF po"#,
+    )
+    .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let status: String = po.try_get("status")?;
+    if status == "Received" || status == "Cancelled" {
+        return Err(AppError::Conflict(format!("Purchase order is already {}", status.to_lowercase())));
+    }
+    let supplier: String = po.try_get("supplier_name")?;
+
+    let lines = sqlx::query(
+        r#"SELECT id,tool_id,quantity_ordered-quantity_received AS outstanding
+           FROM purchase_order_lines WHERE purchase_order_id=$1 ORDER BY id FOR UPDATE"#,
+    )
+    .bind(id).fetch_all(&mut *tx).await?;
+
+    let line_ids = lines.iter().map(|l| l.try_get::<i32,_>("id")).collect::<sqlx::Result<Vec<_>>>()?;
+    if body.lines.iter().any(|r| !line_ids.contains(&r.line_id)) {
+        return Err(AppError::Validation("Line does not belong to this purchase order".into()));
+    }
+
+    let mut receipts: Vec<(i32, i32, i32)> = Vec::new();
+    for line in &lines {
+        let line_id:     i32 = line.try_get("id")?;
+        let tool_id:     i32 = line.try_get("tool_id")?;
+        let outstanding: i32 = line.try_get("outstanding")?;
+        let qty = if body.receive_all {
+            outstanding
+        } else {
+            body.lines.iter().filter(|r| r.line_id == line_id).map(|r| r.quantity).sum()
+        };
+        if qty < 0 || qty > outstanding {
+            return Err(AppError::Validation(format!(
+                "Line {}: can receive at most {} more", line_id, outstanding,
+            )));
+        }
+        if qty > 0 { receipts.push((line_id, tool_id, qty)); }
+    }
+    if receipts.is_empty() { return Err(AppError::Validation("Nothing to receive".into())); }
+
+    let reason = format!("PO #{} from {}", id, supplier);
+    for (line_id, tool_id, qty) in receipts {
+        sqlx::query("UPDATE purchase_order_lines SET quantity_received=quantity_received+$1 WHERE id=$2")
+            .bind(qty).bind(line_id).execute(&mut *tx).await?;
+        record_movement(&mut tx, NewMovement {
+            tool_id, kind: StockMovementKind::Received, delta: qty,
+            reason: &reason, actor: &claims.sub, delegation_id: None,
+        }).await?;
+    }
+
+    sqlx::query(
+        r#"UPDATE purchase_orders SET
+               status = CASE WHEN EXISTS (
+                            SELECT 1 FROM purchase_order_lines
+                            WHERE purchase_order_id=$1 AND quantity_received<quantity_ordered)
+                        THEN 'Partially Received'::po_status ELSE 'Received'::po_status END,
+               closed_at = CASE WHEN EXISTS (
+                            SELECT 1 FROM purchase_order_lines
+                            WHERE purchase_order_id=$1 AND quantity_received<quantity_ordered)
+                        THEN NULL ELSE NOW() END
+           WHERE id=$1"#,
+    )
+    .bind(id).execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    fetch_detail(&state.db, id).await.map(Json)
+}
+
+/// POST /purchase-orders/:id/cancel — closes the order; anything already
+/// received stays in stock.
+pub async fn cancel_order(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<PurchaseOrderDetail>> {
+    let r = sqlx::query(
+        r#"UPDATE purchase_orders SET status='Cancelled'::po_status, closed_at=NOW()
+           WHERE id=$1 AND status IN ('Ordered','Partially Received')"#,
+    )
+    .bind(id).execute(&state.db).await?;
+    if r.rows_affected() == 0 {
+        fetch_detail(&state.db, id).await?;
+        return Err(AppError::Conflict("Purchase order is already closed".into()));
+    }
+    fetch_detail(&state.db, id).await.map(Json)
+}
+
+/// Consumption forecast for every live stock line (optionally consumables
+/// only): consumption per look-back window, the `basis_days` average, days to
+/// stock-out and a suggested order quantity — the larger of twice the
+/// threshold or `coverage_days` of use, less what is available and already on
+/// order. Shared by `/reorder-suggestions` and `/analytics/reorder`.
+pub async fn stock_forecast(
+    db: &PgPool, today: NaiveDate, q: &ReorderQuery, default_coverage: i32, consumables_only: bool,
+) -> Result<StockForecast> {
+    let basis    = q.basis_days.unwrap_or(30);
+    let coverage = q.coverage_days.unwrap_or(default_coverage);
+    let mut windows: Vec<i32> = match q.windows.as_deref() {
+        Some(w) => w.split(',').map(|d| d.trim().parse::<i32>())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| AppError::Validation("windows must be a comma-separated list of days".into()))?,
+        None => vec![7, 30, 90],
+    };
+    if !windows.contains(&basis) { windows.push(basis); }
+    windows.sort_unstable();
+    windows.dedup();
+    if windows.iter().any(|d| *d <= 0 || *d > 3_650) || !(1..=3_650).contains(&coverage) {
+        return Err(AppError::Validation("Windows and coverage must be between 1 and 3650 days".into()));
+    }
+
+    let lines = sqlx::query(
+        r#"WITH on_order AS (
+               SELECT pl.tool_id, SUM(pl.quantity_ordered-pl.quantity_received)::BIGINT AS qty
+               FROM purchase_order_lines pl
+               JOIN purchase_orders po ON po.id=pl.purchase_order_id
+               WHERE po.status IN ('Ordered','Partially Received')
+               GROUP BY pl.tool_id
+           ), last_line AS (
+               SELECT DISTINCT ON (pl.tool_id)
+                      pl.tool_id, po.supplier_id, s.name AS supplier_name, pl.unit_cost_cents
+               FROM purchase_order_lines pl
+               JOIN purchase_orders po ON po.id=pl.purchase_order_id
+               JOIN suppliers s ON s.id=po.supplier_id
+               ORDER BY pl.tool_id, po.created_at DESC
+           )
+           SELECT t.id, t.name, l.name AS lab_name, t.unit,
+                  t.quantity-t.issued_qty-t.held_qty AS available, t.low_stock_threshold,
+                  COALESCE(o.qty,0)::BIGINT AS on_order,
+                  ll.supplier_id, ll.supplier_name, ll.unit_cost_cents
+           FROM tools t
+           LEFT JOIN labs l         ON l.id=t.lab_id
+           LEFT JOIN on_order o     ON o.tool_id=t.id
+           LEFT JOIN last_line ll   ON ll.tool_id=t.id
+           WHERE t.deleted_at IS NULL AND ($1::INT IS NULL OR t.lab_id=$1)
+             AND (NOT $2 OR t.is_consumable)
+           ORDER BY t.name"#,
+    )
+    .bind(q.lab_id).bind(consumables_only).fetch_all(db).await?;
+
+    // Only the longest window's worth of the ledger is read.
+    let consumption = sqlx::query(
+        r#"SELECT m.tool_id, w.days,
+                  COALESCE(SUM(-m.quantity_delta)
+                      FILTER (WHERE m.created_at >= NOW() - make_interval(days => w.days)),0)::BIGINT
+                      AS consumed
+           FROM stock_movements m
+           CROSS JOIN UNNEST($1::INT[]) AS w(days)
+           WHERE m.kind='Issued Consumed'
+             AND m.created_at >= NOW() - make_interval(days => $2)
+           GROUP BY m.tool_id, w.days"#,
+    )
+    .bind(&windows).bind(windows.last().copied().unwrap_or(basis))
+    .fetch_all(db).await?;
+    let mut consumed_by: HashMap<(i32, i32), i64> = HashMap::new();
+    for c in &consumption {
+        consumed_by.insert((c.try_get("tool_id")?, c.try_get("days")?), c.try_get("consumed")?);
+    }
+
+    let mut data = Vec::with_capacity(lines.len());
+    for t in &lines {
+        let tool_id:   i32 = t.try_get("id")?;
+        let available: i32 = t.try_get("available")?;
+        let threshold: i32 = t.try_get("low_stock_threshold")?;
+        let on_order:  i64 = t.try_get("on_order")?;
+
+        let tool_windows: Vec<ConsumptionWindow> = windows.iter().map(|&days| {
+            let consumed = consumed_by.get(&(tool_id, days)).copied().unwrap_or(0);
+            ConsumptionWindow { days, consumed, avg_daily: consumed as f64 / days as f64 }
+        }).collect();
+        let avg_daily = tool_windows.iter().find(|w| w.days == basis).map_or(0.0, |w| w.avg_daily);
+        let days_until_stockout = (avg_daily > 0.0).then(|| available.max(0) as f64 / avg_daily);
+        let target = (2 * i64::from(threshold)).max((avg_daily * coverage as f64).ceil() as i64);
+        data.push(ReorderSuggestion {
+            tool_id,
+            tool_name: t.try_get("name")?,
+            lab_name:  t.try_get("lab_name")?,
+            unit:      t.try_get("unit")?,
+            available,
+            low_stock_threshold: threshold,
+            on_order,
+            windows: tool_windows,
+            avg_daily,
+            days_until_stockout,
+            projected_stockout: days_until_stockout.map(|d| today + chrono::Duration::days(d.floor() as i64)),
+            suggested_quantity: (target - i64::from(available.max(0)) - on_order).max(0),
+            last_supplier_id:     t.try_get("supplier_id")?,
+            last_supplier_name:   t.try_get("supplier_name")?,
+            last_unit_cost_cents: t.try_get("unit_cost_cents")?,
+        });
+    }
+    Ok(StockForecast { basis_days: basis, coverage_days: coverage, windows, data })
+}
+
+/// GET /reorder-suggestions — stock lines to reorder: those at or below
+/// their low-stock threshold, and consumables projected to run out within
+/// `coverage_days` at the `basis_days` average. See [`stock_forecast`].
+pub async fn reorder_suggestions(
+    _auth: AuthUser, State(state): State<AppState>, Query(q): Query<ReorderQuery>,
+) -> Result<Json<StockForecast>> {
+    let mut forecast = stock_forecast(&state.db, state.config.today(), &q, 30, false).await?;
+    let coverage = forecast.coverage_days as f64;
+    forecast.data.retain(|s| {
+        let running_out = s.days_until_stockout.is_some_and(|d| d <= coverage);
+        (s.available <= s.low_stock_threshold || running_out) && s.suggested_quantity > 0
+    });
+
+    // Soonest stock-outs first, then the emptiest lines.
+    forecast.data.sort_by(|a, b| match (a.days_until_stockout, b.days_until_stockout) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        (Some(_), None)    => std::cmp::Ordering::Less,
+        (None, Some(_))    => std::cmp::Ordering::Greater,
+        (None, None)       => a.available.cmp(&b.available).then(b.suggested_quantity.cmp(&a.suggested_quantity)),
+    });
+    Ok(Json(forecast))
+}
@@ -0,0 +1,169 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[sqlx(type_name = "po_status", rename_all = "PascalCase")]
+pub enum PoStatus {
+    Ordered,
+    #[serde(rename = "Partially Received")]
+    #[sqlx(rename = "Partially Received")]
+    PartiallyReceived,
+    Received,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Supplier {
+    pub id:           i32,
+    pub name:         String,
+    pub contact_name: Option<String>,
+    pub email:        Option<String>,
+    pub phone:        Option<String>,
+    pub address:      Option<String>,
+    pub notes:        Option<String>,
+    pub created_at:   DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSupplierRequest {
+    pub name:         String,
+    pub contact_name: Option<String>,
+    pub email:        Option<String>,
+    pub phone:        Option<String>,
+    pub address:      Option<String>,
+    pub notes:        Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSupplierRequest {
+    pub name:         Option<String>,
+    pub contact_name: Option<String>,
+    pub email:        Option<String>,
+    pub phone:        Option<String>,
+    pub address:      Option<String>,
+    pub notes:        Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrder {
+    pub id:                i32,
+    pub supplier_id:       i32,
+    pub supplier_name:     String,
+    pub status:            PoStatus,
+    pub order_date:        NaiveDate,
+    pub expected_delivery: Option<NaiveDate>,
+    pub notes:             Option<String>,
+    pub total_cost_cents:  i64,
+    pub created_by:        String,
+    pub created_at:        DateTime<Utc>,
+    pub closed_at:         Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrderLine {
+    pub id:                i32,
+    pub tool_id:           i32,
+    pub tool_name:         String,
+    pub lab_name:          Option<String>,
+    pub quantity_ordered:  i32,
+    pub quantity_received: i32,
+    pub unit_cost_cents:   i64,
+}
+
+/// Composite response for GET /purchase-orders/:id
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrderDetail {
+    #[serde(flatten)]
+    pub order: PurchaseOrder,
+    pub lines: Vec<PurchaseOrderLine>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePoLine {
+    pub tool_id:         i32,
+    pub quantity:        i32,
+    pub unit_cost_cents: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePurchaseOrderRequest {
+    pub supplier_id:       i32,
+    pub expected_delivery: Option<NaiveDate>,
+    pub notes:             Option<String>,
+    pub lines:             Vec<CreatePoLine>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReceiveLine {
+    pub line_id:  i32,
+    pub quantity: i32,
+}
+
+/// Either list `lines` or set `receive_all` to receive everything outstanding.
+#[derive(Debug, Deserialize)]
+pub struct ReceiveRequest {
+    #[serde(default)]
+    pub lines:       Vec<ReceiveLine>,
+    #[serde(default)]
+    pub receive_all: bool,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct PurchaseOrderFilters {
+    pub status:      Option<String>,
+    pub supplier_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ReorderQuery {
+    /// Comma-separated look-back windows in days, e.g. `7,30,90`
+    pub windows:       Option<String>,
+    /// Window whose average drives the projection (default 30)
+    pub basis_days:    Option<i32>,
+    /// Days of consumption a reorder should leave on hand (default 30)
+    pub coverage_days: Option<i32>,
+    pub lab_id:        Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumptionWindow {
+    pub days:      i32,
+    pub consumed:  i64,
+    pub avg_daily: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderSuggestion {
+    pub tool_id:             i32,
+    pub tool_name:           String,
+    pub lab_name:            Option<String>,
+    pub unit:                String,
+    pub available:           i32,
+    pub low_stock_threshold: i32,
+    pub on_order:            i64,
+    pub windows:             Vec<ConsumptionWindow>,
+    /// Average daily consumption over the basis window
+    pub avg_daily:           f64,
+    /// `None` when nothing was consumed in the basis window
+    pub days_until_stockout: Option<f64>,
+    pub projected_stockout:  Option<NaiveDate>,
+    pub suggested_quantity:  i64,
+    pub last_supplier_id:    Option<i32>,
+    pub last_supplier_name:  Option<String>,
+    pub last_unit_cost_cents: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockForecast {
+    pub basis_days:    i32,
+    pub coverage_days: i32,
+    pub windows:       Vec<i32>,
+    pub data:          Vec<ReorderSuggestion>,
+}
@@ -0,0 +1,239 @@
+use std::{collections::HashMap, io::Cursor};
+
+use axum::extract::Multipart;
+use calamine::{Reader, Xlsx};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{AppError, Result};
+
+/// First sheet of an uploaded CSV or XLSX file, every cell as trimmed text.
+/// Blank rows are dropped; the rest keep their row number in the file.
+pub struct Sheet {
+    pub headers: Vec<String>,
+    pub rows:    Vec<SheetRow>,
+}
+
+pub struct SheetRow {
+    /// 1-based row number in the file, as a spreadsheet shows it
+    pub number: usize,
+    pub cells:  Vec<String>,
+}
+
+/// A column an import understands. Headers match the name or an alias,
+/// ignoring case, spaces and punctuation.
+pub struct Field {
+    pub name:     &'static str,
+    pub aliases:  &'static [&'static str],
+    pub required: bool,
+}
+
+/// Resolved field → column index
+pub struct Columns(HashMap<&'static str, usize>);
+
+/// `file` plus an optional `mapping` JSON object of field → header; any
+/// other text fields are kept for the import to interpret
+pub struct ImportUpload {
+    pub sheet:   Sheet,
+    pub mapping: HashMap<String, String>,
+    pub options: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowError {
+    /// Spreadsheet row number, counting blank rows
+    pub row:     usize,
+    pub field:   Option<&'static str>,
+    pub message: String,
+}
+
+/// Outcome of an import. Nothing is written unless `committed`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport<T: Serialize> {
+    pub dry_run:    bool,
+    pub committed:  bool,
+    pub total_rows: usize,
+    pub created:    usize,
+    pub updated:    usize,
+    /// Field → header each field was read from
+    pub columns:    HashMap<&'static str, String>,
+    pub errors:     Vec<RowError>,
+    pub rows:       Vec<T>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ImportOptions {
+    /// `true` validates and previews without writing
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Lower-case alphanumerics only, for matching headers and labels.
+pub fn normalize(header: &str) -> String {
+    header.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Line a CSV record starts on. The reader skips empty lines and reports
+/// the position before them, so step past any line breaks first.
+fn csv_line(text: &str, byte: usize) -> usize {
+    let rest = text.get(byte..).unwrap_or_default();
+    let start = byte + (rest.len() - rest.trim_start_matches(['\r', '\n']).len());
+    1 + text.as_bytes()[..start].iter().filter(|&&b| b == b'\n').count()
+}
+
+impl Sheet {
+    /// XLSX is recognised by its zip signature; anything else is read as
+    /// UTF-8 CSV.
+    pub fn parse(bytes: &[u8]) -> Result<Sheet> {
+        let table: Vec<SheetRow> = if bytes.starts_with(ZIP_MAGIC) {
+            let mut book = Xlsx::new(Cursor::new(bytes))
+                .map_err(|e| AppError::UnsupportedMediaType(format!("unreadable XLSX: {}", e)))?;
+            let range = book.worksheet_range_at(0)
+                .ok_or_else(|| AppError::Validation("Workbook has no sheets".into()))?
+                .map_err(|e| AppError::Validation(format!("Unreadable sheet: {}", e)))?;
+            // The used range may start below row 1.
+            let first = range.start().map_or(1, |(r, _)| r as usize + 1);
+            range.rows().enumerate().map(|(i, r)| SheetRow {
+                number: first + i,
+                cells:  r.iter().map(|c| c.to_string().trim().to_string()).collect(),
+            }).collect()
+        } else {
+            let text = std::str::from_utf8(bytes)
+                .map_err(|_| AppError::UnsupportedMediaType("CSV must be UTF-8".into()))?;
+            let text = text.trim_start_matches('\u{feff}');
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false).flexible(true).trim(csv::Trim::All)
+                .from_reader(text.as_bytes());
+            reader.records()
+                .map(|r| r.map(|rec| SheetRow {
+                    number: csv_line(text, rec.position().map_or(0, |p| p.byte() as usize)),
+                    cells:  rec.iter().map(str::to_string).collect(),
+                }))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|e| AppError::Validation(format!("Invalid CSV: {}", e)))?
+        };
+        let mut rows = table.into_iter().filter(|r| r.cells.iter().any(|c| !c.is_empty()));
+        let headers = rows.next()
+            .ok_or_else(|| AppError::Validation("File has no header row".into()))?.cells;
+        Ok(Sheet { headers, rows: rows.collect() })
+    }
+
+    pub fn columns(&self, fields: &[Field], mapping: &HashMap<String, String>) -> Result<Columns> {
+        let find = |wanted: &str| {
+            let wanted = normalize(wanted);
+            self.headers.iter().position(|h| normalize(h) == wanted)
+        };
+        let mut columns = HashMap::new();
+        for field in fields {
+            let index = match mapping.get(field.name) {
+                Some(header) => Some(find(header).ok_or_else(|| AppError::Validation(
+                    format!("Mapped column '{}' for {} not found", header, field.name),
+                ))?),
+                None => std::iter::once(&field.name).chain(field.aliases).find_map(|a| find(a)),
+            };
+            match index {
+                Some(i) => { columns.insert(field.name, i); }
+                None if field.required => {
+                    return Err(AppError::Validation(format!("No column found for {}", field.name)));
+                }
+                None => {}
+            }
+        }
+        Ok(Columns(columns))
+    }
+
+    pub fn header_names(&self, columns: &Columns) -> HashMap<&'static str, String> {
+        columns.0.iter().map(|(f, &i)| (*f, self.headers[i].clone())).collect()
+    }
+}
+
+impl Columns {
+    /// The cell for a field, `None` when unmapped or blank.
+    pub fn get<'a>(&self, row: &'a [String], field: &str) -> Option<&'a str> {
+        self.0.get(field).and_then(|&i| row.get(i)).map(String::as_str).filter(|v| !v.is_empty())
+    }
+}
+
+/// Reads the multipart form of an import request.
+pub async fn read_import(multipart: &mut Multipart, max_bytes: usize) -> Result<ImportUpload> {
+    let bad_form = |e: axum::extract::multipart::MultipartError| AppError::Validation(e.body_text());
+    let mut sheet = None;
+    let mut mapping = HashMap::new();
+    let mut options = HashMap::new();
+    while let Some(mut field) = multipart.next_field().await.map_err(bad_form)? {
+        match field.name() {
+            Some("file") => {
+                let mut bytes = Vec::new();
+                while let Some(chunk) = field.chunk().await.map_err(bad_form)? {
+                    if bytes.len() + chunk.len() > max_bytes { return Err(AppError::PayloadTooLarge(max_bytes)); }
+                    bytes.extend_from_slice(&chunk);
+                }
+                sheet = Some(Sheet::parse(&bytes)?);
+            }
+            Some("mapping") => {
+                let text = field.text().await.map_err(bad_form)?;
+                mapping = serde_json::from_str(&text).map_err(|e| AppError::Validation(
+                    format!("mapping must be a JSON object of field to column: {}", e),
+                ))?;
+            }
+            Some(name) => {
+                let name = name.to_string();
+                options.insert(name, field.text().await.map_err(bad_form)?);
+            }
+            None => {}
+        }
+    }
+    let sheet = sheet.ok_or_else(|| AppError::Validation("Multipart field 'file' required".into()))?;
+    Ok(ImportUpload { sheet, mapping, options })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(sheet: &Sheet) -> Vec<usize> {
+        sheet.rows.iter().map(|r| r.number).collect()
+    }
+
+    #[test]
+    fn csv_rows_keep_their_file_row_numbers() {
+        let sheet = Sheet::parse(b"id,name\r\nS1,Ann\r\n,\r\n\r\nS2,Bob\r\n\"S3\",\"Cy\nand co\"\r\nS4,Di\r\n").unwrap();
+        assert_eq!(sheet.headers, ["id", "name"]);
+        assert_eq!(numbers(&sheet), [2, 5, 6, 8]);
+        assert_eq!(sheet.rows[1].cells, ["S2", "Bob"]);
+    }
+
+    #[test]
+    fn xlsx_rows_keep_their_sheet_row_numbers() {
+        let mut book = rust_xlsxwriter::Workbook::new();
+        let ws = book.add_worksheet();
+        ws.write(1, 0, "id").unwrap();
+        ws.write(2, 0, "S1").unwrap();
+        ws.write(4, 0, "S2").unwrap();
+        let sheet = Sheet::parse(&book.save_to_buffer().unwrap()).unwrap();
+        assert_eq!(sheet.headers, ["id"]);
+        assert_eq!(numbers(&sheet), [3, 5]);
+    }
+
+    #[test]
+    fn blank_rows_before_the_header_are_counted() {
+        let sheet = Sheet::parse(b",,\nid,name\nS1,Ann\n").unwrap();
+        assert_eq!(sheet.headers, ["id", "name"]);
+        assert_eq!(numbers(&sheet), [3]);
+    }
+
+    #[test]
+    fn byte_order_mark_and_padding_are_stripped() {
+        let sheet = Sheet::parse("\u{feff}id , name\n S1 ,Ann\n".as_bytes()).unwrap();
+        assert_eq!(sheet.headers, ["id", "name"]);
+        assert_eq!(sheet.rows[0].cells, ["S1", "Ann"]);
+    }
+
+    #[test]
+    fn empty_file_has_no_header_row() {
+        assert!(matches!(Sheet::parse(b"\n,\n"), Err(AppError::Validation(_))));
+    }
+}
@@ -1,8 +1,14 @@
+use std::sync::Arc;
+
 use sqlx::PgPool;
-use crate::config::AppConfig;
+use tokio::sync::broadcast;
+
+use crate::{config::AppConfig, events::LiveUpdate, storage::Storage};
 
 #[derive(Clone)]
 pub struct AppState {
-    pub db:     PgPool,
-    pub config: AppConfig,
+    pub db:      PgPool,
+    pub config:  AppConfig,
+    pub events:  broadcast::Sender<LiveUpdate>,
+    pub storage: Arc<dyn Storage>,
 }
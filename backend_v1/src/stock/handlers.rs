@@ -0,0 +1,106 @@
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+use sqlx::{PgConnection, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    errors::{AppError, Result},
+    state::AppState,
+    stock::models::{
+        CreateMovementRequest, MovementHistory, NewMovement, StockMovement, StockMovementKind,
+    },
+    tools::handlers::compute_status,
+};
+
+/// The only path that changes `tools.quantity`. Locks the tool row, applies
+/// the signed delta, recomputes status and appends to the ledger. Stock on
+/// loan or held for maintenance cannot be moved out: the new quantity must
+/// still cover `issued_qty + held_qty`.
+pub async fn record_movement(conn: &mut PgConnection, m: NewMovement<'_>) -> Result<StockMovement> {
+    if m.delta == 0 { return Err(AppError::Validation("Movement quantity cannot be zero".into())); }
+    if m.reason.trim().is_empty() { return Err(AppError::Validation("Movement reason required".into())); }
+
+    let tool = sqlx::query(
+        "SELECT quantity,issued_qty,maintenance_qty,quarantined_qty,low_stock_threshold
+         FROM tools WHERE id=$1 FOR UPDATE",
+    )
+    .bind(m.tool_id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
+
+    let qty: i32 = tool.try_get("quantity")?;
+    let iss: i32 = tool.try_get("issued_qty")?;
+    let maint: i32 = tool.try_get("maintenance_qty")?;
+    let quar: i32 = tool.try_get("quarantined_qty")?;
+    let thr: i32 = tool.try_get("low_stock_threshold")?;
+
+    let new_qty = qty + m.delta;
+    if new_qty < 0 || new_qty < iss + maint + quar { return Err(AppError::InsufficientStock); }
+
+    sqlx::query("UPDATE tools SET quantity=$1, status=$2::tool_status WHERE id=$3")
+        .bind(new_qty).bind(compute_status(new_qty, iss, maint, quar, thr)).bind(m.tool_id)
+        .execute(&mut *conn).await?;
+
+    let movement = sqlx::query_as::<_, StockMovement>(
+        r#"INSERT INTO stock_movements
+               (tool_id,kind,quantity_delta,quantity_after,reason,actor,delegation_id)
+           VALUES ($1,$2,$3,$4,$5,$6,$7)
+           RETURNING id,tool_id,kind,quantity_delta,quantity_after,reason,actor,
+                     delegation_id,created_at"#,
+    )
+    .bind(m.tool_id).bind(m.kind).bind(m.delta).bind(new_qty)
+    .bind(m.reason.trim()).bind(m.actor).bind(m.delegation_id)
+    .fetch_one(&mut *conn).await?;
+
+    Ok(movement)
+}
+
+// GET /tools/:id/movements
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Path(tool_id): Path<i32>,
+) -> Result<Json<MovementHistory>> {
+    let current_quantity: i32 = sqlx::query("SELECT quantity FROM tools WHERE id=$1")
+        .bind(tool_id).fetch_optional(&state.db).await?.ok_or(AppError::NotFound)?
+        .try_get("quantity")?;
+
+    let data = sqlx::query_as::<_, StockMovement>(
+        r#"SELECT id,tool_id,kind,quantity_delta,quantity_after,reason,actor,
+                  delegation_id,created_at
+           FROM stock_movements WHERE tool_id=$1
+           ORDER BY created_at, id"#,
+    )
+    .bind(tool_id).fetch_all(&state.db).await?;
+
+    let ledger_quantity: i64 = data.iter().map(|m| m.quantity_delta as i64).sum();
+    Ok(Json(MovementHistory {
+        tool_id,
+        current_quantity,
+        ledger_quantity,
+        reconciled: ledger_quantity == current_quantity as i64,
+        data,
+    }))
+}
+
+// POST /tools/:id/movements
+pub async fn create(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(tool_id): Path<i32>, Json(body): Json<CreateMovementRequest>,
+) -> Result<(StatusCode, Json<StockMovement>)> {
+    if !body.kind.is_manual() {
+        return Err(AppError::Validation(format!("{:?} movements cannot be posted manually", body.kind)));
+    }
+    let delta = match body.kind {
+        StockMovementKind::Correction => body.quantity,
+        _ if body.quantity <= 0 => {
+            return Err(AppError::Validation("Quantity must be >= 1".into()));
+        }
+        StockMovementKind::WrittenOff | StockMovementKind::Lost => -body.quantity,
+        _ => body.quantity,
+    };
+
+    let mut tx = state.db.begin().await?;
+    let movement = record_movement(&mut tx, NewMovement {
+        tool_id, kind: body.kind, delta,
+        reason: &body.reason, actor: &claims.sub, delegation_id: None,
+    }).await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(movement)))
+}
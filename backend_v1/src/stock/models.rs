@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq)]
+#[sqlx(type_name = "stock_movement_kind", rename_all = "PascalCase")]
+pub enum StockMovementKind {
+    Received,
+    #[serde(rename = "Issued Consumed")]
+    #[sqlx(rename = "Issued Consumed")]
+    IssuedConsumed,
+    #[serde(rename = "Written Off")]
+    #[sqlx(rename = "Written Off")]
+    WrittenOff,
+    Lost,
+    Found,
+    Correction,
+    Transfer,
+}
+
+impl StockMovementKind {
+    /// Kinds that may be posted by hand through POST /tools/:id/movements.
+    /// Consumption and transfers are only ever written by their workflows.
+    pub fn is_manual(self) -> bool {
+        !matches!(self, StockMovementKind::IssuedConsumed | StockMovementKind::Transfer)
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct StockMovement {
+    pub id:             i32,
+    pub tool_id:        i32,
+    pub kind:           StockMovementKind,
+    pub quantity_delta: i32,
+    pub quantity_after: i32,
+    pub reason:         String,
+    pub actor:          String,
+    pub delegation_id:  Option<i32>,
+    pub created_at:     DateTime<Utc>,
+}
+
+/// A movement about to be written. `delta` is signed.
+#[derive(Debug)]
+pub struct NewMovement<'a> {
+    pub tool_id:       i32,
+    pub kind:          StockMovementKind,
+    pub delta:         i32,
+    pub reason:        &'a str,
+    pub actor:         &'a str,
+    pub delegation_id: Option<i32>,
+}
+
+/// `quantity` is a positive amount for Received/Found (in) and
+/// Written Off/Lost (out); for Correction it is the signed delta.
+#[derive(Debug, Deserialize)]
+pub struct CreateMovementRequest {
+    pub kind:     StockMovementKind,
+    pub quantity: i32,
+    pub reason:   String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MovementHistory {
+    pub tool_id:          i32,
+    pub current_quantity: i32,
+    pub ledger_quantity:  i64,
+    pub reconciled:       bool,
+    pub data:             Vec<StockMovement>,
+}
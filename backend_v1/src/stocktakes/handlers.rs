@@ -0,0 +1,209 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    errors::{AppError, Result},
+    state::AppState,
+    stock::{handlers::record_movement, models::{NewMovement, StockMovementKind}},
+    stocktakes::models::{
+        CountRequest, CreateStocktakeRequest, Stocktake, StocktakeDetail, StocktakeFilters,
+        StocktakeLine, StocktakeStatus, VarianceReport,
+    },
+};
+
+const STOCKTAKE_SELECT: &str = r#"
+    SELECT s.id,s.lab_id,l.name AS lab_name,s.status,s.notes,s.started_by,
+           s.started_at,s.approved_by,s.approved_at
+    FROM stocktakes s JOIN labs l ON l.id=s.lab_id"#;
+
+async fn fetch_stocktake(db: &PgPool, id: i32) -> Result<Stocktake> {
+    sqlx::query_as::<_, Stocktake>(&format!("{} WHERE s.id=$1", STOCKTAKE_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+async fn fetch_lines(db: &PgPool, id: i32) -> Result<Vec<StocktakeLine>> {
+    Ok(sqlx::query_as::<_, StocktakeLine>(
+        r#"SELECT sl.tool_id, t.name AS tool_name, sl.expected_qty, sl.counted_qty,
+                  sl.counted_qty - sl.expected_qty AS variance,
+                  sl.counted_by, sl.counted_at
+           FROM stocktake_lines sl JOIN tools t ON t.id=sl.tool_id
+           WHERE sl.stocktake_id=$1
+           ORDER BY t.name"#,
+    )
+    .bind(id).fetch_all(db).await?)
+}
+
+/// Locks an open session, rejecting approved/cancelled ones.
+async fn lock_open(conn: &mut PgConnection, id: i32) -> Result<i32> {
+    let row = sqlx::query(
+        "SELECT lab_id, status::text AS status FROM stocktakes WHERE id=$1 FOR UPDATE",
+    )
+    .bind(id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
+    if row.try_get::<String,_>("status")? != "Open" {
+        return Err(AppError::Conflict("Stocktake is no longer open".into()));
+    }
+    Ok(row.try_get("lab_id")?)
+}
+
+fn build_report(stocktake_id: i32, status: StocktakeStatus, lines: Vec<StocktakeLine>) -> VarianceReport {
+    let (counted, uncounted): (Vec<_>, Vec<_>) = lines.into_iter().partition(|l| l.counted_qty.is_some());
+    let lines_counted = counted.len();
+    let variances: Vec<StocktakeLine> = counted.into_iter().filter(|l| l.variance != Some(0)).collect();
+    let total_shortage = variances.iter().filter_map(|l| l.variance).filter(|v| *v < 0).map(|v| -v as i64).sum();
+    let total_surplus  = variances.iter().filter_map(|l| l.variance).filter(|v| *v > 0).map(|v| v as i64).sum();
+    VarianceReport { stocktake_id, status, lines_counted, uncounted, variances, total_shortage, total_surplus }
+}
+
+// GET /stocktakes
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<StocktakeFilters>,
+) -> Result<Json<Value>> {
+    let stocktakes = sqlx::query_as::<_, Stocktake>(&format!(
+        "{} WHERE ($1::INT IS NULL OR s.lab_id=$1)
+              AND ($2::TEXT IS NULL OR s.status::text ILIKE $2)
+            ORDER BY s.started_at DESC", STOCKTAKE_SELECT,
+    ))
+    .bind(filters.lab_id).bind(&filters.status)
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": stocktakes })))
+}
+
+// GET /stocktakes/:id
+pub async fn get_one(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<StocktakeDetail>> {
+    let stocktake = fetch_stocktake(&state.db, id).await?;
+    let lines     = fetch_lines(&state.db, id).await?;
+    Ok(Json(StocktakeDetail { stocktake, lines }))
+}
+
+// POST /stocktakes — opens a session and snapshots every tool in the lab
+pub async fn create(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Json(body): Json<CreateStocktakeRequest>,
+) -> Result<(StatusCode, Json<StocktakeDetail>)> {
+    let mut tx = state.db.begin().await?;
+
+    if sqlx::query("SELECT id FROM labs WHERE id=$1 AND deleted_at IS NULL").bind(body.lab_id)
+        .fetch_optional(&mut *tx).await?.is_none() { return Err(AppError::NotFound); }
+    if sqlx::query("SELECT id FROM stocktakes WHERE lab_id=$1 AND status='Open'::stocktake_status")
+        .bind(body.lab_id).fetch_optional(&mut *tx).await?.is_some() {
+        return Err(AppError::Conflict("This lab already has an open stocktake".into()));
+    }
+
+    let id: i32 = sqlx::query(
+        "INSERT INTO stocktakes (lab_id,notes,started_by) VALUES ($1,$2,$3) RETURNING id",
+    )
+    .bind(body.lab_id).bind(&body.notes).bind(&claims.sub)
+    .fetch_one(&mut *tx).await?.try_get("id")?;
+
+    sqlx::query(
+        r#"INSERT INTO stocktake_lines (stocktake_id,tool_id,expected_qty)
+           SELECT $1, id, quantity-issued_qty-held_qty FROM tools WHERE lab_id=$2 AND deleted_at IS NULL"#,
+    )
+    .bind(id).bind(body.lab_id).execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    let stocktake = fetch_stocktake(&state.db, id).await?;
+    let lines     = fetch_lines(&state.db, id).await?;
+    Ok((StatusCode::CREATED, Json(StocktakeDetail { stocktake, lines })))
+}
+
+// POST /stocktakes/:id/counts
+pub async fn count(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<CountRequest>,
+) -> Result<Json<StocktakeLine>> {
+    if body.quantity < 0 { return Err(AppError::Validation("Counted quantity cannot be negative".into())); }
+
+    let mut tx = state.db.begin().await?;
+    let lab_id = lock_open(&mut tx, id).await?;
+
+    // Tools added to the lab after the snapshot get a line on first count.
+    sqlx::query(
+        r#"INSERT INTO stocktake_lines (stocktake_id,tool_id,expected_qty)
+           SELECT $1, id, quantity-issued_qty-held_qty FROM tools
+           WHERE id=$2 AND lab_id=$3 AND deleted_at IS NULL
+           ON CONFLICT (stocktake_id,tool_id) DO NOTHING"#,
+    )
+    .bind(id).bind(body.tool_id).bind(lab_id).execute(&mut *tx).await?;
+
+    // The count is compared with what should be on the shelf now, so loans,
+    // receipts and transfers since the session opened are not corrected twice.
+    let r = sqlx::query(
+        r#"UPDATE stocktake_lines sl SET
+               counted_qty  = CASE WHEN $1 THEN COALESCE(sl.counted_qty,0)+$2 ELSE $2 END,
+               expected_qty = t.quantity-t.issued_qty-t.held_qty,
+               counted_by   = $3, counted_at = NOW()
+           FROM tools t
+           WHERE sl.stocktake_id=$4 AND sl.tool_id=$5 AND t.id=sl.tool_id"#,
+    )
+    .bind(body.add).bind(body.quantity).bind(&claims.sub).bind(id).bind(body.tool_id)
+    .execute(&mut *tx).await?;
+    if r.rows_affected() == 0 {
+        return Err(AppError::Validation("Tool does not belong to this stocktake's lab".into()));
+    }
+    tx.commit().await?;
+
+    fetch_lines(&state.db, id).await?
+        .into_iter().find(|l| l.tool_id == body.tool_id)
+        .ok_or(AppError::NotFound).map(Json)
+}
+
+// POST /stocktakes/:id/approve — posts a Correction movement per variance
+pub async fn approve(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<VarianceReport>> {
+    let mut tx = state.db.begin().await?;
+    lock_open(&mut tx, id).await?;
+
+    let variances = sqlx::query(
+        r#"SELECT tool_id, counted_qty-expected_qty AS variance
+           FROM stocktake_lines
+           WHERE stocktake_id=$1 AND counted_qty IS NOT NULL AND counted_qty<>expected_qty
+           ORDER BY tool_id"#,
+    )
+    .bind(id).fetch_all(&mut *tx).await?;
+
+    let reason = format!("Stocktake #{} variance", id);
+    for v in &variances {
+        record_movement(&mut tx, NewMovement {
+            tool_id: v.try_get("tool_id")?, kind: StockMovementKind::Correction,
+            delta: v.try_get("variance")?, reason: &reason,
+            actor: &claims.sub, delegation_id: None,
+        }).await?;
+    }
+
+    sqlx::query(
+        r#"UPDATE stocktakes SET status='Approved'::stocktake_status,
+               approved_by=$1, approved_at=NOW()
+           WHERE id=$2"#,
+    )
+    .bind(&claims.sub).bind(id).execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    let lines = fetch_lines(&state.db, id).await?;
+    Ok(Json(build_report(id, StocktakeStatus::Approved, lines)))
+}
+
+// POST /stocktakes/:id/cancel
+pub async fn cancel(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    let mut tx = state.db.begin().await?;
+    lock_open(&mut tx, id).await?;
+    sqlx::query("UPDATE stocktakes SET status='Cancelled'::stocktake_status WHERE id=$1")
+        .bind(id).execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// GET /stocktakes/:id/report
+pub async fn report(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<VarianceReport>> {
+    let stocktake = fetch_stocktake(&state.db, id).await?;
+    let lines     = fetch_lines(&state.db, id).await?;
+    Ok(Json(build_report(id, stocktake.status, lines)))
+}
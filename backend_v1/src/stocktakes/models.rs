@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[sqlx(type_name = "stocktake_status", rename_all = "PascalCase")]
+pub enum StocktakeStatus {
+    Open,
+    Approved,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Stocktake {
+    pub id:          i32,
+    pub lab_id:      i32,
+    pub lab_name:    String,
+    pub status:      StocktakeStatus,
+    pub notes:       Option<String>,
+    pub started_by:  String,
+    pub started_at:  DateTime<Utc>,
+    pub approved_by: Option<String>,
+    pub approved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct StocktakeLine {
+    pub tool_id:      i32,
+    pub tool_name:    String,
+    pub expected_qty: i32,
+    pub counted_qty:  Option<i32>,
+    pub variance:     Option<i32>,
+    pub counted_by:   Option<String>,
+    pub counted_at:   Option<DateTime<Utc>>,
+}
+
+/// Composite response for GET /stocktakes/:id
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StocktakeDetail {
+    #[serde(flatten)]
+    pub stocktake: Stocktake,
+    pub lines:     Vec<StocktakeLine>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VarianceReport {
+    pub stocktake_id:   i32,
+    pub status:         StocktakeStatus,
+    pub lines_counted:  usize,
+    pub uncounted:      Vec<StocktakeLine>,
+    pub variances:      Vec<StocktakeLine>,
+    pub total_shortage: i64,
+    pub total_surplus:  i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateStocktakeRequest {
+    pub lab_id: i32,
+    pub notes:  Option<String>,
+}
+
+/// Record a count. With `add: true` the quantity is added to the running
+/// count, so a barcode scanner can post `{ tool_id, quantity: 1, add: true }`
+/// per scan; otherwise it replaces the count.
+#[derive(Debug, Deserialize)]
+pub struct CountRequest {
+    pub tool_id:  i32,
+    pub quantity: i32,
+    #[serde(default)]
+    pub add:      bool,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct StocktakeFilters {
+    pub lab_id: Option<i32>,
+    pub status: Option<String>,
+}
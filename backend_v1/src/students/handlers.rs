@@ -1,57 +1,79 @@
-use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde_json::{json, Value};
-use sqlx::Row;
+use sqlx::{postgres::PgArguments, PgConnection, PgPool, Row};
 
 use crate::{
+    appeals::{handlers::APPEAL_SELECT, models::BanAppeal},
     auth::middleware::AuthUser,
+    charges::{
+        handlers::{
+            apply_payment, apply_waiver, insert_charge, outstanding_balance, replacement_cost,
+            CHARGE_SELECT,
+        },
+        models::{Charge, ChargeKind, NewCharge, PaymentRequest},
+    },
+    cohorts::handlers::{check_class, unknown_classes},
     errors::{AppError, Result},
+    export::{self, bind, Export},
+    pdf::{self, Line},
+    policy::{handlers::evaluate, models::BanEvent},
+    spreadsheet::{read_import, Field, ImportOptions, ImportReport, RowError},
     state::AppState,
+    stock::{handlers::record_movement, models::{NewMovement, StockMovementKind}},
     students::models::{
-        CreateStudentRequest, DelegationSummary, LostToolRecord,
-        PaidRequest, Student, StudentFilters, StudentProfile, UpdateStudentRequest,
+        AccountStatus, Clearance, ClearanceRecord, CreateStudentRequest, DelegationSummary, LostToolRecord,
+        PaidRequest, Student, StudentFilters, StudentImportRow, StudentProfile, UpdateStudentRequest,
     },
+    trash::{handlers::move_to_trash, models::TrashKind},
+    units::handlers::{check_units, unknown_units},
 };
 
-pub async fn list(
-    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<StudentFilters>,
-) -> Result<Json<Value>> {
-    let students = sqlx::query_as::<_, Student>(
-        r#"SELECT student_id,name,class_name,department,email,
-                  account_status,lost_tool_count,units,created_at
-           FROM students ORDER BY name"#,
-    )
-    .fetch_all(&state.db).await?;
+pub const STUDENT_COLUMNS: &str = "student_id,name,class_name,cohort_id,department,email,account_status,\
+    lost_tool_count,ban_reason,banned_at,suspended_until,ban_is_manual,left_at,units,created_at,deleted_at";
 
-    let filtered: Vec<&Student> = students.iter().filter(|s| {
-        if let Some(ref st) = filters.status {
-            let vs = format!("{:?}", s.account_status);
-            if !vs.eq_ignore_ascii_case(st) { return false; }
-        }
-        if let Some(ref q) = filters.search {
-            let ql = q.to_lowercase();
-            if !s.name.to_lowercase().contains(&ql) && !s.student_id.to_lowercase().contains(&ql) {
-                return false;
-            }
-        }
-        true
-    }).collect();
+pub async fn fetch_student(db: &PgPool, student_id: &str) -> Result<Student> {
+    sqlx::query_as::<_, Student>(&format!("SELECT {} FROM students WHERE student_id=$1", STUDENT_COLUMNS))
+        .bind(student_id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
 
-    Ok(Json(json!({ "data": filtered })))
+// GET /students?status=&search= — also exported as CSV/XLSX
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<StudentFilters>, export: Export,
+) -> Result<Response> {
+    let sql = format!(
+        "SELECT {} FROM students
+         WHERE deleted_at IS NULL
+           AND ($1::TEXT IS NULL OR LOWER(account_status::text)=LOWER($1))
+           AND ($2::TEXT IS NULL OR STRPOS(LOWER(name), LOWER($2)) > 0
+                OR STRPOS(LOWER(student_id), LOWER($2)) > 0)
+         ORDER BY name", STUDENT_COLUMNS,
+    );
+    let mut args = PgArguments::default();
+    bind(&mut args, filters.status)?;
+    bind(&mut args, filters.search)?;
+    if let Some(format) = export.0 {
+        return export::rows::<Student>(state.db, sql, args, format, "students").await;
+    }
+
+    let students = sqlx::query_as_with::<_, Student, _>(&sql, args).fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": students })).into_response())
 }
 
 pub async fn profile(
     _auth: AuthUser, State(state): State<AppState>, Path(student_id): Path<String>,
 ) -> Result<Json<StudentProfile>> {
-    let student = sqlx::query_as::<_, Student>(
-        r#"SELECT student_id,name,class_name,department,email,
-                  account_status,lost_tool_count,units,created_at
-           FROM students WHERE student_id=$1"#,
-    )
-    .bind(&student_id).fetch_optional(&state.db).await?.ok_or(AppError::NotFound)?;
+    let student = fetch_student(&state.db, &student_id).await?;
 
     let current_holdings = sqlx::query_as::<_, DelegationSummary>(
         r#"SELECT d.id, t.name AS tool_name, d.quantity, d.date_issued,
-                  d.expected_return, d.actual_checkout_time, d.actual_return_time,
+                  d.expected_return, d.checked_out_at, d.returned_at,
                   d.status::text AS status
            FROM delegations d JOIN tools t ON t.id=d.tool_id
            WHERE d.student_id=$1 AND d.status IN ('Issued','Overdue')
@@ -61,7 +83,7 @@ pub async fn profile(
 
     let history = sqlx::query_as::<_, DelegationSummary>(
         r#"SELECT d.id, t.name AS tool_name, d.quantity, d.date_issued,
-                  d.expected_return, d.actual_checkout_time, d.actual_return_time,
+                  d.expected_return, d.checked_out_at, d.returned_at,
                   d.status::text AS status
            FROM delegations d JOIN tools t ON t.id=d.tool_id
            WHERE d.student_id=$1 AND d.status='Returned'
@@ -80,46 +102,237 @@ pub async fn profile(
     )
     .bind(&student_id).fetch_all(&state.db).await?;
 
-    Ok(Json(StudentProfile { student, current_holdings, history, lost_tools }))
+    let charges = sqlx::query_as::<_, Charge>(
+        &format!("{} WHERE c.student_id=$1 ORDER BY c.created_at DESC", CHARGE_SELECT),
+    )
+    .bind(&student_id).fetch_all(&state.db).await?;
+    let outstanding_balance_cents = charges.iter().map(|c| c.outstanding_cents).sum();
+
+    let ban_history = sqlx::query_as::<_, BanEvent>(
+        "SELECT id,action,reason,actor,created_at FROM student_ban_events
+         WHERE student_id=$1 ORDER BY created_at DESC",
+    )
+    .bind(&student_id).fetch_all(&state.db).await?;
+
+    let appeals = sqlx::query_as::<_, BanAppeal>(
+        &format!("{} WHERE a.student_id=$1 ORDER BY a.filed_at DESC", APPEAL_SELECT),
+    )
+    .bind(&student_id).fetch_all(&state.db).await?;
+
+    Ok(Json(StudentProfile {
+        student, current_holdings, history, lost_tools, charges, outstanding_balance_cents,
+        ban_history, appeals,
+    }))
+}
+
+pub fn valid_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty() && !domain.contains('@') && !email.contains(char::is_whitespace)
+                && domain.split('.').count() > 1 && domain.split('.').all(|p| !p.is_empty())
+        }
+        None => false,
+    }
+}
+
+/// Trims and normalises a new student (upper-case id, lower-case email) and
+/// applies the rules every entry path shares. Errors name the offending field.
+fn normalize_student(body: CreateStudentRequest) -> std::result::Result<CreateStudentRequest, (&'static str, String)> {
+    let student_id = body.student_id.trim().to_uppercase();
+    let class_name = body.class_name.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+    let email = body.email.trim().to_lowercase();
+    if student_id.is_empty()             { return Err(("student_id", "student_id required".into())); }
+    if student_id.len() > 30             { return Err(("student_id", "student_id is longer than 30 characters".into())); }
+    if body.name.trim().is_empty()       { return Err(("name", "name required".into())); }
+    if body.department.trim().is_empty() { return Err(("department", "department required".into())); }
+    if !valid_email(&email)              { return Err(("email", "Invalid email".into())); }
+    if class_name.as_ref().is_some_and(|c| c.len() > 20) {
+        return Err(("class_name", "class_name is longer than 20 characters".into()));
+    }
+    Ok(CreateStudentRequest {
+        student_id, class_name, email,
+        name: body.name.trim().to_string(),
+        department: body.department.trim().to_string(),
+        units: body.units,
+    })
 }
 
 pub async fn create(
     _auth: AuthUser, State(state): State<AppState>, Json(body): Json<CreateStudentRequest>,
 ) -> Result<(StatusCode, Json<Student>)> {
-    if body.student_id.trim().is_empty() { return Err(AppError::Validation("student_id required".into())); }
-    if body.name.trim().is_empty()       { return Err(AppError::Validation("name required".into())); }
-    if !body.email.contains('@')         { return Err(AppError::Validation("Invalid email".into())); }
+    let body = normalize_student(body).map_err(|(_, m)| AppError::Validation(m))?;
+    let mut conn = state.db.acquire().await?;
+    check_units(&mut conn, body.units.as_deref()).await?;
+    check_class(&mut conn, body.class_name.as_deref()).await?;
+    drop(conn);
 
-    let s = sqlx::query_as::<_, Student>(
+    let s = sqlx::query_as::<_, Student>(&format!(
         r#"INSERT INTO students (student_id,name,class_name,department,email,units)
            VALUES ($1,$2,$3,$4,$5,$6)
-           RETURNING student_id,name,class_name,department,email,
-                     account_status,lost_tool_count,units,created_at"#,
-    )
-    .bind(body.student_id.trim().to_uppercase()).bind(body.name.trim())
-    .bind(&body.class_name).bind(body.department.trim())
-    .bind(body.email.trim().to_lowercase())
+           RETURNING {}"#, STUDENT_COLUMNS,
+    ))
+    .bind(&body.student_id).bind(&body.name)
+    .bind(&body.class_name).bind(&body.department)
+    .bind(&body.email)
     .bind(body.units.as_deref())
     .fetch_one(&state.db).await?;
     Ok((StatusCode::CREATED, Json(s)))
 }
 
+const IMPORT_FIELDS: &[Field] = &[
+    Field { name: "student_id", aliases: &["reg no", "registration number", "admission number", "adm no", "id"], required: true },
+    Field { name: "name",       aliases: &["full name", "student name", "names"], required: true },
+    Field { name: "class_name", aliases: &["class", "stream", "cohort"], required: false },
+    Field { name: "department", aliases: &["dept", "programme", "program"], required: true },
+    Field { name: "email",      aliases: &["email address", "e-mail"], required: true },
+    Field { name: "units",      aliases: &["courses", "unit codes"], required: false },
+];
+
+// POST /students/import?dry_run=true — multipart `file` (CSV/XLSX) and optional
+// `mapping`; upserts every row in one transaction, or none if any row fails
+pub async fn import(
+    _auth: AuthUser, State(state): State<AppState>,
+    Query(opts): Query<ImportOptions>, mut multipart: Multipart,
+) -> Result<(StatusCode, Json<ImportReport<StudentImportRow>>)> {
+    let dry_run = opts.dry_run;
+    let upload = read_import(&mut multipart, state.config.max_upload_bytes).await?;
+    let sheet = &upload.sheet;
+    let cols = sheet.columns(IMPORT_FIELDS, &upload.mapping)?;
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let mut seen_ids = HashMap::new();
+    let mut seen_emails = HashMap::new();
+    for sheet_row in &sheet.rows {
+        let (row, cells) = (sheet_row.number, &sheet_row.cells);
+        let cell = |f| cols.get(cells, f).unwrap_or_default().to_string();
+        let req = CreateStudentRequest {
+            student_id: cell("student_id"), name: cell("name"), department: cell("department"),
+            email: cell("email"), class_name: cols.get(cells, "class_name").map(str::to_string),
+            units: cols.get(cells, "units").map(|u| {
+                u.split([',', ';']).map(str::trim).filter(|u| !u.is_empty()).map(str::to_string).collect()
+            }),
+        };
+        let s = match normalize_student(req) {
+            Ok(s) => s,
+            Err((field, message)) => { errors.push(RowError { row, field: Some(field), message }); continue; }
+        };
+        if let Some(first) = seen_ids.insert(s.student_id.clone(), row) {
+            errors.push(RowError { row, field: Some("student_id"), message: format!("Duplicate of row {}", first) });
+            continue;
+        }
+        if let Some(first) = seen_emails.insert(s.email.clone(), row) {
+            errors.push(RowError { row, field: Some("email"), message: format!("Email already used on row {}", first) });
+            continue;
+        }
+        rows.push(StudentImportRow {
+            row, action: "Create", student_id: s.student_id, name: s.name, class_name: s.class_name,
+            department: s.department, email: s.email, units: s.units,
+        });
+    }
+
+    // Every unit must be in the catalogue and every class an existing cohort.
+    let mut conn = state.db.acquire().await?;
+    let codes: Vec<String> = rows.iter().flat_map(|r| r.units.iter().flatten().cloned()).collect();
+    let unknown: HashSet<String> = unknown_units(&mut conn, &codes).await?.into_iter().collect();
+    let classes: Vec<String> = rows.iter().filter_map(|r| r.class_name.clone()).collect();
+    let unknown_class: HashSet<String> = unknown_classes(&mut conn, &classes).await?.into_iter().collect();
+    drop(conn);
+    rows.retain(|r| {
+        if let Some(class) = r.class_name.as_ref().filter(|c| unknown_class.contains(*c)) {
+            errors.push(RowError { row: r.row, field: Some("class_name"), message: format!("Unknown class '{}'", class) });
+            return false;
+        }
+        let missing: Vec<&str> = r.units.iter().flatten().filter(|u| unknown.contains(*u)).map(String::as_str).collect();
+        if missing.is_empty() { return true; }
+        errors.push(RowError { row: r.row, field: Some("units"), message: format!("Unknown units: {}", missing.join(", ")) });
+        false
+    });
+
+    // Existing students are updated; an email may not move between students.
+    let ids: Vec<&str> = rows.iter().map(|r| r.student_id.as_str()).collect();
+    let emails: Vec<&str> = rows.iter().map(|r| r.email.as_str()).collect();
+    let existing = sqlx::query(
+        "SELECT student_id,email,deleted_at IS NOT NULL AS trashed FROM students
+         WHERE student_id=ANY($1) OR email=ANY($2)",
+    )
+    .bind(&ids).bind(&emails).fetch_all(&state.db).await?;
+    let mut existing_ids = HashSet::new();
+    let mut email_owner = HashMap::new();
+    let mut trashed = HashSet::new();
+    for r in &existing {
+        let id: String = r.try_get("student_id")?;
+        if r.try_get("trashed")? { trashed.insert(id.clone()); }
+        email_owner.insert(r.try_get::<String,_>("email")?, id.clone());
+        existing_ids.insert(id);
+    }
+    rows.retain_mut(|r| {
+        if let Some(owner) = email_owner.get(&r.email).filter(|o| **o != r.student_id) {
+            errors.push(RowError { row: r.row, field: Some("email"), message: format!("Email belongs to {}", owner) });
+            return false;
+        }
+        if trashed.contains(&r.student_id) {
+            errors.push(RowError {
+                row: r.row, field: Some("student_id"),
+                message: format!("{} is in the trash; restore them first", r.student_id),
+            });
+            return false;
+        }
+        if existing_ids.contains(&r.student_id) { r.action = "Update"; }
+        true
+    });
+    errors.sort_by_key(|e| e.row);
+
+    let updated = rows.iter().filter(|r| r.action == "Update").count();
+    let mut report = ImportReport {
+        dry_run, committed: false, total_rows: sheet.rows.len(),
+        created: rows.len() - updated, updated,
+        columns: sheet.header_names(&cols), errors, rows,
+    };
+    if !report.errors.is_empty() {
+        let status = if dry_run { StatusCode::OK } else { StatusCode::UNPROCESSABLE_ENTITY };
+        return Ok((status, Json(report)));
+    }
+    if dry_run { return Ok((StatusCode::OK, Json(report))); }
+
+    let mut tx = state.db.begin().await?;
+    for r in &report.rows {
+        sqlx::query(
+            r#"INSERT INTO students (student_id,name,class_name,department,email,units)
+               VALUES ($1,$2,$3,$4,$5,$6)
+               ON CONFLICT (student_id) DO UPDATE SET
+                   name=EXCLUDED.name, class_name=COALESCE(EXCLUDED.class_name,students.class_name),
+                   department=EXCLUDED.department, email=EXCLUDED.email,
+                   units=COALESCE(EXCLUDED.units,students.units)"#,
+        )
+        .bind(&r.student_id).bind(&r.name).bind(&r.class_name).bind(&r.department)
+        .bind(&r.email).bind(r.units.as_deref())
+        .execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    report.committed = true;
+    Ok((StatusCode::OK, Json(report)))
+}
+
 pub async fn update(
     _auth: AuthUser, State(state): State<AppState>,
     Path(student_id): Path<String>, Json(body): Json<UpdateStudentRequest>,
 ) -> Result<Json<Student>> {
-    if sqlx::query("SELECT student_id FROM students WHERE student_id=$1").bind(&student_id)
+    if sqlx::query("SELECT student_id FROM students WHERE student_id=$1 AND deleted_at IS NULL").bind(&student_id)
         .fetch_optional(&state.db).await?.is_none() { return Err(AppError::NotFound); }
+    let mut conn = state.db.acquire().await?;
+    check_units(&mut conn, body.units.as_deref()).await?;
+    check_class(&mut conn, body.class_name.as_deref()).await?;
+    drop(conn);
 
-    let s = sqlx::query_as::<_, Student>(
+    let s = sqlx::query_as::<_, Student>(&format!(
         r#"UPDATE students SET
                name=COALESCE($1,name), class_name=COALESCE($2,class_name),
                department=COALESCE($3,department), email=COALESCE($4,email),
                units=COALESCE($5,units)
            WHERE student_id=$6
-           RETURNING student_id,name,class_name,department,email,
-                     account_status,lost_tool_count,units,created_at"#,
-    )
+           RETURNING {}"#, STUDENT_COLUMNS,
+    ))
     .bind(&body.name).bind(&body.class_name).bind(&body.department)
     .bind(&body.email).bind(body.units.as_deref()).bind(&student_id)
     .fetch_one(&state.db).await?;
@@ -127,16 +340,199 @@ pub async fn update(
 }
 
 pub async fn delete(
-    _auth: AuthUser, State(state): State<AppState>, Path(student_id): Path<String>,
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(student_id): Path<String>,
 ) -> Result<StatusCode> {
-    let r = sqlx::query("DELETE FROM students WHERE student_id=$1")
-        .bind(student_id).execute(&state.db).await?;
-    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+    // The same conditions as clearance: nothing may be left outstanding.
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SELECT student_id FROM students WHERE student_id=$1 AND deleted_at IS NULL FOR UPDATE")
+        .bind(&student_id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let c = clearance_for(&mut tx, &student_id).await?;
+    move_to_trash(&mut tx, TrashKind::Student, &student_id, c.blockers, &claims.sub).await?;
+    tx.commit().await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Checks that a student holds nothing, has no unresolved lost tools and
+/// owes nothing.
+async fn clearance_for(conn: &mut PgConnection, student_id: &str) -> Result<Clearance> {
+    let stu = sqlx::query("SELECT name,account_status FROM students WHERE student_id=$1 AND deleted_at IS NULL")
+        .bind(student_id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
+
+    let current_holdings = sqlx::query_as::<_, DelegationSummary>(
+        r#"SELECT d.id, t.name AS tool_name, d.quantity, d.date_issued,
+                  d.expected_return, d.checked_out_at, d.returned_at,
+                  d.status::text AS status
+           FROM delegations d JOIN tools t ON t.id=d.tool_id
+           WHERE d.student_id=$1 AND d.status IN ('Issued','Overdue')
+           ORDER BY d.date_issued"#,
+    )
+    .bind(student_id).fetch_all(&mut *conn).await?;
+
+    let unresolved_lost_tools = sqlx::query_as::<_, LostToolRecord>(
+        r#"SELECT d.id AS delegation_id, t.name AS tool_name, d.quantity,
+                  d.date_issued AS date_lost, FALSE AS resolved, d.resolution
+           FROM delegations d JOIN tools t ON t.id=d.tool_id
+           WHERE d.student_id=$1 AND d.status='Lost' AND d.resolution IS NULL
+           ORDER BY d.date_issued"#,
+    )
+    .bind(student_id).fetch_all(&mut *conn).await?;
+
+    let outstanding_balance_cents = outstanding_balance(conn, student_id).await?;
+
+    let mut blockers = Vec::new();
+    if !current_holdings.is_empty() {
+        blockers.push(format!("{} tool loan(s) not returned", current_holdings.len()));
+    }
+    if !unresolved_lost_tools.is_empty() {
+        blockers.push(format!("{} lost tool(s) not recovered or paid for", unresolved_lost_tools.len()));
+    }
+    if outstanding_balance_cents > 0 {
+        blockers.push(format!("{} cents owed in unpaid charges", outstanding_balance_cents));
+    }
+    Ok(Clearance {
+        student_id: student_id.to_string(), name: stu.try_get("name")?,
+        account_status: stu.try_get("account_status")?, cleared: blockers.is_empty(), blockers,
+        current_holdings, unresolved_lost_tools, outstanding_balance_cents,
+    })
+}
+
+fn require_cleared(c: &Clearance) -> Result<()> {
+    if c.cleared { return Ok(()); }
+    Err(AppError::Blocked("Student is not cleared".into(), c.blockers.clone()))
+}
+
+// GET /students/:id/clearance
+pub async fn clearance(
+    _auth: AuthUser, State(state): State<AppState>, Path(student_id): Path<String>,
+) -> Result<Json<Clearance>> {
+    let mut conn = state.db.acquire().await?;
+    clearance_for(&mut conn, &student_id).await.map(Json)
+}
+
+// POST /students/:id/clearance-certificate — issues a PDF; each one is
+// numbered and recorded
+pub async fn clearance_certificate(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(student_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    let mut tx = state.db.begin().await?;
+    let c = clearance_for(&mut tx, &student_id).await?;
+    require_cleared(&c)?;
+    let student = sqlx::query("SELECT department,class_name FROM students WHERE student_id=$1")
+        .bind(&student_id).fetch_one(&mut *tx).await?;
+
+    let id: i64 = sqlx::query_scalar("SELECT nextval(pg_get_serial_sequence('student_clearances','id'))")
+        .fetch_one(&mut *tx).await?;
+    // Numbered and dated in the institution's zone, not UTC.
+    let now = chrono::Utc::now();
+    let local = now.with_timezone(&state.config.timezone);
+    let certificate_no = format!("CLR-{}-{:06}", local.format("%Y"), id);
+    sqlx::query(
+        "INSERT INTO student_clearances (id,student_id,certificate_no,issued_by,issued_at) VALUES ($1,$2,$3,$4,$5)",
+    )
+    .bind(id as i32).bind(&student_id).bind(&certificate_no).bind(&claims.sub).bind(now)
+    .execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    let pdf = pdf::document("Laboratory Clearance Certificate", &[
+        Line::Field("Certificate No.", certificate_no.clone()),
+        Line::Field("Issued", local.format("%d %B %Y").to_string()),
+        Line::Blank,
+        Line::Field("Student", c.name.clone()),
+        Line::Field("Student ID", student_id.clone()),
+        Line::Field("Department", student.try_get("department")?),
+        Line::Field("Class", student.try_get::<Option<String>,_>("class_name")?.unwrap_or_default()),
+        Line::Blank,
+        Line::Heading("Declaration".into()),
+        Line::Text("The student named above has returned every tool borrowed from the".into()),
+        Line::Text("laboratories, has no unresolved lost tools and owes no outstanding charges.".into()),
+        Line::Blank,
+        Line::Field("Issued by", claims.sub.clone()),
+    ]);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.pdf\"", certificate_no)),
+        ],
+        pdf,
+    ))
+}
+
+// GET /clearances/:certificate_no — verifies a printed certificate number
+pub async fn verify_clearance(
+    _auth: AuthUser, State(state): State<AppState>, Path(certificate_no): Path<String>,
+) -> Result<Json<ClearanceRecord>> {
+    sqlx::query_as::<_, ClearanceRecord>(
+        r#"SELECT c.certificate_no, c.student_id, s.name AS student_name, c.issued_by, c.issued_at
+           FROM student_clearances c JOIN students s ON s.student_id=c.student_id
+           WHERE c.certificate_no=UPPER(TRIM($1))"#,
+    )
+    .bind(&certificate_no).fetch_optional(&state.db).await?.ok_or(AppError::NotFound).map(Json)
+}
+
+/// Moves a student out of the active roll, keeping their history.
+async fn leave(
+    state: &AppState, student_id: &str, status: AccountStatus, actor: &str,
+) -> Result<Student> {
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SELECT student_id FROM students WHERE student_id=$1 AND deleted_at IS NULL FOR UPDATE")
+        .bind(student_id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let c = clearance_for(&mut tx, student_id).await?;
+    if status == AccountStatus::Graduated {
+        require_cleared(&c)?;
+    } else if !c.current_holdings.is_empty() {
+        return Err(AppError::Conflict(format!("{} tool loan(s) must be returned first", c.current_holdings.len())));
+    }
+    // Any ban or suspension stays on record and comes back on reactivation.
+    sqlx::query("UPDATE students SET account_status=$1, left_at=NOW(), left_by=$2 WHERE student_id=$3")
+    .bind(status).bind(actor).bind(student_id).execute(&mut *tx).await?;
+    tx.commit().await?;
+    fetch_student(&state.db, student_id).await
+}
+
+// POST /students/:id/graduate — requires clearance
+pub async fn graduate(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(student_id): Path<String>,
+) -> Result<Json<Student>> {
+    leave(&state, &student_id, AccountStatus::Graduated, &claims.sub).await.map(Json)
+}
+
+// POST /students/:id/archive — for students leaving without graduating;
+// nothing may be on loan, unresolved losses and charges stay on record
+pub async fn archive(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(student_id): Path<String>,
+) -> Result<Json<Student>> {
+    leave(&state, &student_id, AccountStatus::Archived, &claims.sub).await.map(Json)
+}
+
+// POST /students/:id/reactivate — back on the roll with any ban or suspension
+// they left with, subject to the ban policy
+pub async fn reactivate(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(student_id): Path<String>,
+) -> Result<Json<Student>> {
+    let mut tx = state.db.begin().await?;
+    let status: AccountStatus = sqlx::query_scalar(
+        "SELECT account_status FROM students WHERE student_id=$1 AND deleted_at IS NULL FOR UPDATE",
+    )
+    .bind(&student_id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    if !matches!(status, AccountStatus::Graduated | AccountStatus::Archived) {
+        return Err(AppError::Conflict("Student is already on the active roll".into()));
+    }
+    // A suspension that ran out meanwhile is lifted by the next sweep.
+    sqlx::query(
+        r#"UPDATE students SET left_at=NULL, left_by=NULL,
+               account_status = CASE WHEN suspended_until IS NOT NULL THEN 'Suspended'
+                                     WHEN banned_at IS NOT NULL THEN 'Banned'
+                                     ELSE 'Active' END::account_status
+           WHERE student_id=$1"#,
+    )
+    .bind(&student_id).execute(&mut *tx).await?;
+    evaluate(&mut tx, &student_id, &claims.sub).await?;
+    tx.commit().await?;
+    Ok(Json(fetch_student(&state.db, &student_id).await?))
+}
+
 pub async fn recover_tool(
-    _auth: AuthUser, State(state): State<AppState>,
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
     Path((student_id, delegation_id)): Path<(String, i32)>,
 ) -> Result<Json<Value>> {
     let mut tx = state.db.begin().await?;
@@ -155,60 +551,120 @@ pub async fn recover_tool(
     sqlx::query("UPDATE delegations SET resolution='Recovered' WHERE id=$1")
         .bind(delegation_id).execute(&mut *tx).await?;
 
-    sqlx::query("UPDATE tools SET issued_qty=GREATEST(0,issued_qty-$1) WHERE id=$2")
-        .bind(quantity).bind(tool_id).execute(&mut *tx).await?;
+    // Nothing is owed for a tool that came back.
+    if let Some(charge_id) = replacement_charge(&mut tx, delegation_id).await? {
+        let owed: i64 = sqlx::query("SELECT outstanding_cents FROM charge_balances WHERE charge_id=$1")
+            .bind(charge_id).fetch_one(&mut *tx).await?.try_get("outstanding_cents")?;
+        if owed > 0 {
+            apply_waiver(&mut tx, charge_id, None, "Tool recovered", &claims.sub).await?;
+        }
+    }
+
+    // The unit was written out of stock when lost; bring it back in.
+    record_movement(&mut tx, NewMovement {
+        tool_id, kind: StockMovementKind::Found, delta: quantity,
+        reason: &format!("Recovered from delegation #{}", delegation_id),
+        actor: &claims.sub, delegation_id: Some(delegation_id),
+    }).await?;
 
-    let row = sqlx::query(
-        r#"UPDATE students
-           SET lost_tool_count=GREATEST(0,lost_tool_count-1),
-               account_status=CASE WHEN lost_tool_count-1<5 THEN 'Active'::account_status ELSE account_status END
-           WHERE student_id=$1
-           RETURNING lost_tool_count, account_status::text AS account_status"#,
+    let lost_count: i32 = sqlx::query(
+        "UPDATE students SET lost_tool_count=GREATEST(0,lost_tool_count-1) WHERE student_id=$1
+         RETURNING lost_tool_count",
     )
-    .bind(&student_id).fetch_one(&mut *tx).await?;
+    .bind(&student_id).fetch_one(&mut *tx).await?.try_get("lost_tool_count")?;
+    let standing = evaluate(&mut tx, &student_id, &claims.sub).await?;
 
     tx.commit().await?;
     Ok(Json(json!({
         "message":       "Tool marked as recovered",
-        "newLostCount":  row.try_get::<i32,_>("lost_tool_count")?,
-        "accountStatus": row.try_get::<String,_>("account_status")?,
+        "newLostCount":  lost_count,
+        "accountStatus": standing.account_status,
     })))
 }
 
+/// The replacement charge raised when a delegation was marked lost, if any.
+async fn replacement_charge(conn: &mut PgConnection, delegation_id: i32) -> Result<Option<i32>> {
+    Ok(sqlx::query(
+        "SELECT id FROM student_charges WHERE delegation_id=$1 AND kind='Replacement'::charge_kind
+         ORDER BY id LIMIT 1",
+    )
+    .bind(delegation_id).fetch_optional(&mut *conn).await?
+    .map(|r| r.try_get("id")).transpose()?)
+}
+
 pub async fn paid_tool(
-    _auth: AuthUser, State(state): State<AppState>,
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
     Path((student_id, delegation_id)): Path<(String, i32)>,
     body: Option<Json<PaidRequest>>,
 ) -> Result<Json<Value>> {
+    let body = body.map(|Json(b)| b).unwrap_or_default();
     let mut tx = state.db.begin().await?;
 
-    sqlx::query(
-        "SELECT id FROM delegations
+    let del = sqlx::query(
+        "SELECT tool_id,quantity FROM delegations
          WHERE id=$1 AND student_id=$2 AND status='Lost'::delegation_status AND resolution IS NULL
          FOR UPDATE",
     )
     .bind(delegation_id).bind(&student_id)
     .fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
 
+    // Lost before a replacement cost was known: the paid amount sets the charge.
+    let charge_id = match replacement_charge(&mut tx, delegation_id).await? {
+        Some(id) => id,
+        None => {
+            let tool_id: i32 = del.try_get("tool_id")?;
+            let quantity: i32 = del.try_get("quantity")?;
+            let amount = match replacement_cost(&mut tx, tool_id).await? {
+                Some(cost) if cost > 0 => cost * i64::from(quantity),
+                _ => body.amount_cents.ok_or_else(|| AppError::Validation(
+                    "No replacement cost on record; amount_cents required".into(),
+                ))?,
+            };
+            insert_charge(&mut tx, NewCharge {
+                student_id: &student_id, kind: ChargeKind::Replacement, amount_cents: amount,
+                description: &format!("Replacement for delegation #{}", delegation_id),
+                delegation_id: Some(delegation_id), damage_report_id: None, actor: &claims.sub,
+            }).await?
+        }
+    };
+
+    let payment = PaymentRequest {
+        amount_cents: body.amount_cents, method: body.method, reference: body.reference,
+        receipt_attachment_id: body.receipt_attachment_id,
+    };
+    let remaining = apply_payment(&mut tx, charge_id, &payment, &claims.sub).await?;
+
+    // A part-payment leaves the lost tool unresolved until the balance clears.
+    if remaining > 0 {
+        evaluate(&mut tx, &student_id, &claims.sub).await?;
+        tx.commit().await?;
+        return Ok(Json(json!({
+            "message":             "Partial payment recorded",
+            "resolution":          null,
+            "chargeId":            charge_id,
+            "outstandingCents":    remaining,
+            "receiptAttachmentId": payment.receipt_attachment_id,
+        })));
+    }
+
     sqlx::query("UPDATE delegations SET resolution='Paid' WHERE id=$1")
         .bind(delegation_id).execute(&mut *tx).await?;
 
-    let row = sqlx::query(
-        r#"UPDATE students
-           SET lost_tool_count=GREATEST(0,lost_tool_count-1),
-               account_status=CASE WHEN lost_tool_count-1<5 THEN 'Active'::account_status ELSE account_status END
-           WHERE student_id=$1
-           RETURNING lost_tool_count, account_status::text AS account_status"#,
+    let lost_count: i32 = sqlx::query(
+        "UPDATE students SET lost_tool_count=GREATEST(0,lost_tool_count-1) WHERE student_id=$1
+         RETURNING lost_tool_count",
     )
-    .bind(&student_id).fetch_one(&mut *tx).await?;
+    .bind(&student_id).fetch_one(&mut *tx).await?.try_get("lost_tool_count")?;
+    let standing = evaluate(&mut tx, &student_id, &claims.sub).await?;
 
     tx.commit().await?;
-    let receipt = body.map(|b| b.receipt_uploaded.unwrap_or(false)).unwrap_or(false);
     Ok(Json(json!({
-        "message":        "Tool marked as paid",
-        "resolution":     "Paid",
-        "receiptUploaded": receipt,
-        "newLostCount":   row.try_get::<i32,_>("lost_tool_count")?,
-        "accountStatus":  row.try_get::<String,_>("account_status")?,
+        "message":             "Tool marked as paid",
+        "resolution":          "Paid",
+        "chargeId":            charge_id,
+        "outstandingCents":    0,
+        "receiptAttachmentId": payment.receipt_attachment_id,
+        "newLostCount":        lost_count,
+        "accountStatus":       standing.account_status,
     })))
 }
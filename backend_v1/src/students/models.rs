@@ -1,25 +1,44 @@
-use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+use crate::{
+    appeals::models::BanAppeal,
+    charges::models::Charge,
+    policy::models::BanEvent,
+};
+
+#[derive(Debug, Default, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
 #[sqlx(type_name = "account_status", rename_all = "PascalCase")]
 pub enum AccountStatus {
+    #[default]
     Active,
     Banned,
+    Suspended,
+    Graduated,
+    Archived,
 }
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Default, Serialize, sqlx::FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct Student {
     pub student_id:      String,
     pub name:            String,
     pub class_name:      Option<String>,
+    pub cohort_id:       Option<i32>,
     pub department:      String,
     pub email:           String,
     pub account_status:  AccountStatus,
     pub lost_tool_count: i32,
+    pub ban_reason:      Option<String>,
+    pub banned_at:       Option<DateTime<Utc>>,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub ban_is_manual:   bool,
+    /// When the student graduated or was archived
+    pub left_at:         Option<DateTime<Utc>>,
     pub units:           Option<Vec<String>>,
     pub created_at:      DateTime<Utc>,
+    /// Set while the student is in the trash
+    pub deleted_at:      Option<DateTime<Utc>>,
 }
 
 /// Lightweight row returned inside StudentProfile
@@ -31,8 +50,8 @@ pub struct DelegationSummary {
     pub quantity:         i32,
     pub date_issued:      NaiveDate,
     pub expected_return:  NaiveDate,
-    pub actual_checkout_time: NaiveTime,
-    pub actual_return_time:   Option<NaiveTime>,
+    pub checked_out_at:   DateTime<Utc>,
+    pub returned_at:      Option<DateTime<Utc>>,
     pub status:           String,
 }
 
@@ -55,6 +74,10 @@ pub struct StudentProfile {
     pub current_holdings: Vec<DelegationSummary>,
     pub history:          Vec<DelegationSummary>,
     pub lost_tools:       Vec<LostToolRecord>,
+    pub charges:          Vec<Charge>,
+    pub outstanding_balance_cents: i64,
+    pub ban_history:      Vec<BanEvent>,
+    pub appeals:          Vec<BanAppeal>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -82,7 +105,54 @@ pub struct StudentFilters {
     pub search: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Payment towards a lost tool's replacement charge. `amount_cents`
+/// defaults to the balance owed; it is also the charge amount when no
+/// replacement cost is on record.
+#[derive(Debug, Deserialize, Default)]
 pub struct PaidRequest {
-    pub receipt_uploaded: Option<bool>,
+    pub amount_cents:     Option<i64>,
+    pub method:           Option<String>,
+    pub reference:        Option<String>,
+    /// Receipt uploaded to /students/:id/lost-tools/:did/attachments
+    pub receipt_attachment_id: Option<i32>,
+}
+
+/// A validated row of a student import
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StudentImportRow {
+    pub row:        usize,
+    /// "Create" or "Update"
+    pub action:     &'static str,
+    pub student_id: String,
+    pub name:       String,
+    pub class_name: Option<String>,
+    pub department: String,
+    pub email:      String,
+    pub units:      Option<Vec<String>>,
+}
+
+/// What stands between a student and clearance
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Clearance {
+    pub student_id:                String,
+    pub name:                      String,
+    pub account_status:            AccountStatus,
+    pub cleared:                   bool,
+    pub blockers:                  Vec<String>,
+    pub current_holdings:          Vec<DelegationSummary>,
+    pub unresolved_lost_tools:     Vec<LostToolRecord>,
+    pub outstanding_balance_cents: i64,
+}
+
+/// An issued clearance certificate, looked up by its printed number
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearanceRecord {
+    pub certificate_no: String,
+    pub student_id:     String,
+    pub student_name:   String,
+    pub issued_by:      String,
+    pub issued_at:      DateTime<Utc>,
 }
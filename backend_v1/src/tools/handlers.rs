@@ -1,147 +1,461 @@
-use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde_json::{json, Value};
-use sqlx::Row;
+use sqlx::{postgres::PgArguments, PgConnection, Row};
 
 use crate::{
     auth::middleware::AuthUser,
+    catalogue::handlers::{ensure_single_holding, find_or_create_item},
     errors::{AppError, Result},
+    export::{self, bind, Export},
+    spreadsheet::{normalize, read_import, Field, ImportReport, RowError},
     state::AppState,
-    tools::models::{CreateToolRequest, Tool, ToolFilters, UpdateToolRequest},
+    stock::{handlers::record_movement, models::{NewMovement, StockMovementKind}},
+    tools::models::{
+        CreateToolRequest, SimilarTool, Tool, ToolCategory, ToolFilters, ToolImportOptions,
+        ToolImportRow, UpdateToolRequest,
+    },
+    trash::{handlers::move_to_trash, models::TrashKind},
 };
 
-pub fn compute_status(quantity: i32, issued_qty: i32, threshold: i32) -> &'static str {
-    let available = quantity - issued_qty;
-    if available <= 0           { "Out of Stock"     }
-    else if available <= threshold { "Low Stock"      }
-    else if issued_qty > 0      { "Partially Issued" }
-    else                        { "Available"        }
+const TOOL_SELECT: &str = r#"
+    SELECT t.id,t.catalogue_item_id,t.name,t.category,t.subcategory,t.quantity,t.issued_qty,
+           t.maintenance_qty,t.quarantined_qty,t.unit,
+           t.lab_id, l.name AS lab_name, t.description,t.is_consumable,
+           t.consumable_type,t.low_stock_threshold,t.status,
+           '/v1/attachments/' || img.id || '/download'  AS primary_image_url,
+           '/v1/attachments/' || img.id || '/thumbnail' AS thumbnail_url,
+           t.date_added,t.created_at,t.updated_at,t.deleted_at
+    FROM tools t
+    LEFT JOIN labs l ON l.id=t.lab_id
+    LEFT JOIN attachments img ON img.owner_type='Tool' AND img.owner_id=t.id AND img.is_primary"#;
+
+/// Held stock is neither on loan nor issuable: out for maintenance or
+/// quarantined after damage. A line whose only remaining units are held
+/// reports "Under Maintenance" (or "Quarantined" when none are out for
+/// maintenance) rather than "Out of Stock".
+pub fn compute_status(
+    quantity: i32, issued_qty: i32, maintenance_qty: i32, quarantined_qty: i32, threshold: i32,
+) -> &'static str {
+    let available = quantity - issued_qty - maintenance_qty - quarantined_qty;
+    if available <= 0 && maintenance_qty > 0      { "Under Maintenance" }
+    else if available <= 0 && quarantined_qty > 0 { "Quarantined"       }
+    else if available <= 0                        { "Out of Stock"      }
+    else if available <= threshold   { "Low Stock"         }
+    else if issued_qty > 0           { "Partially Issued"  }
+    else                             { "Available"         }
 }
 
-pub async fn list(
-    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<ToolFilters>,
-) -> Result<Json<Value>> {
-    let tools = sqlx::query_as::<_, Tool>(
-        r#"SELECT t.id,t.name,t.category,t.subcategory,t.quantity,t.issued_qty,t.unit,
-                  t.lab_id, l.name AS lab_name, t.description,t.is_consumable,
-                  t.consumable_type,t.low_stock_threshold,t.status,t.date_added,
-                  t.created_at,t.updated_at
-           FROM tools t LEFT JOIN labs l ON l.id=t.lab_id
-           ORDER BY t.name"#,
+/// Recomputes and stores a stock line's status from its current counts.
+pub async fn refresh_status(conn: &mut PgConnection, tool_id: i32) -> Result<&'static str> {
+    let row = sqlx::query(
+        "SELECT quantity,issued_qty,maintenance_qty,quarantined_qty,low_stock_threshold FROM tools WHERE id=$1",
     )
-    .fetch_all(&state.db).await?;
+    .bind(tool_id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
+    let status = compute_status(
+        row.try_get("quantity")?, row.try_get("issued_qty")?, row.try_get("maintenance_qty")?,
+        row.try_get("quarantined_qty")?, row.try_get("low_stock_threshold")?,
+    );
+    sqlx::query("UPDATE tools SET status=$1::tool_status WHERE id=$2")
+        .bind(status).bind(tool_id).execute(&mut *conn).await?;
+    Ok(status)
+}
 
-    let filtered: Vec<&Tool> = tools.iter().filter(|t| {
-        if let Some(ref c) = filters.category {
-            let cs = format!("{:?}", t.category);
-            if !cs.to_lowercase().contains(&c.to_lowercase()) { return false; }
-        }
-        if let Some(ref s) = filters.search {
-            let q = s.to_lowercase();
-            if !t.name.to_lowercase().contains(&q) &&
-               !t.description.as_deref().unwrap_or("").to_lowercase().contains(&q) {
-                return false;
-            }
-        }
-        true
-    }).collect();
+// GET /tools?category=&search= — also exported as CSV/XLSX
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<ToolFilters>, export: Export,
+) -> Result<Response> {
+    let sql = format!(
+        "{} WHERE t.deleted_at IS NULL
+              AND ($1::TEXT IS NULL OR STRPOS(LOWER(REPLACE(t.category::text,' ','')), LOWER($1)) > 0)
+              AND ($2::TEXT IS NULL OR STRPOS(LOWER(t.name), LOWER($2)) > 0
+                   OR STRPOS(LOWER(COALESCE(t.description,'')), LOWER($2)) > 0)
+            ORDER BY t.name", TOOL_SELECT,
+    );
+    let mut args = PgArguments::default();
+    bind(&mut args, filters.category)?;
+    bind(&mut args, filters.search)?;
+    if let Some(format) = export.0 {
+        return export::rows::<Tool>(state.db, sql, args, format, "tools").await;
+    }
 
-    Ok(Json(json!({ "data": filtered, "total": filtered.len() })))
+    let tools = sqlx::query_as_with::<_, Tool, _>(&sql, args).fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": tools, "total": tools.len() })).into_response())
 }
 
 pub async fn get_one(
     _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
 ) -> Result<Json<Tool>> {
-    sqlx::query_as::<_, Tool>(
-        r#"SELECT t.id,t.name,t.category,t.subcategory,t.quantity,t.issued_qty,t.unit,
-                  t.lab_id, l.name AS lab_name, t.description,t.is_consumable,
-                  t.consumable_type,t.low_stock_threshold,t.status,t.date_added,
-                  t.created_at,t.updated_at
-           FROM tools t LEFT JOIN labs l ON l.id=t.lab_id
-           WHERE t.id=$1"#,
-    )
+    sqlx::query_as::<_, Tool>(&format!("{} WHERE t.id=$1", TOOL_SELECT))
     .bind(id).fetch_optional(&state.db).await?.ok_or(AppError::NotFound).map(Json)
 }
 
-pub async fn create(
-    _auth: AuthUser, State(state): State<AppState>, Json(body): Json<CreateToolRequest>,
-) -> Result<(StatusCode, Json<Value>)> {
-    if body.name.trim().is_empty() { return Err(AppError::Validation("Tool name required".into())); }
-    if body.quantity < 0           { return Err(AppError::Validation("Quantity cannot be negative".into())); }
+/// Rules every way of adding a tool shares. Errors name the offending field.
+fn check_new_tool(body: &CreateToolRequest) -> std::result::Result<(), (&'static str, String)> {
+    if body.name.trim().is_empty() { return Err(("name", "Tool name required".into())); }
+    if body.quantity < 0           { return Err(("quantity", "Quantity cannot be negative".into())); }
+    if body.low_stock_threshold.is_some_and(|t| t < 0) {
+        return Err(("low_stock_threshold", "Low-stock threshold cannot be negative".into()));
+    }
+    Ok(())
+}
 
-    let is_consumable   = body.is_consumable.unwrap_or(false);
-    let unit            = body.unit.clone().unwrap_or_else(|| "pcs".into());
-    let threshold       = body.low_stock_threshold.unwrap_or(5);
-    let status          = compute_status(body.quantity, 0, threshold);
-    let category_str    = serde_json::to_string(&body.category).unwrap_or_default().trim_matches('"').to_string();
+/// Adds a lab's stock line for a tool. Returns (tool id, catalogue item id).
+async fn insert_tool(conn: &mut PgConnection, body: &CreateToolRequest, actor: &str) -> Result<(i32, i32)> {
+    let threshold = body.low_stock_threshold.unwrap_or(5);
 
-    let row = sqlx::query(
+    // A tool row is one lab's holding of a catalogue item; reuse the item if
+    // another lab already stocks the same model.
+    let item_id = find_or_create_item(conn, body).await?;
+    ensure_single_holding(conn, item_id, body.lab_id, None).await?;
+
+    // Stock line starts empty; the initial quantity enters through the ledger.
+    let id: i32 = sqlx::query(
         r#"INSERT INTO tools
-               (name,category,subcategory,quantity,unit,lab_id,description,
+               (catalogue_item_id,name,category,subcategory,quantity,unit,lab_id,description,
                 is_consumable,consumable_type,low_stock_threshold,status)
-           VALUES ($1,$2::tool_category,$3,$4,$5,$6,$7,$8,$9,$10,$11::tool_status)
-           RETURNING id, status::text AS status, created_at"#,
+           SELECT id,name,category,subcategory,0,unit,$2,description,
+                  is_consumable,consumable_type,$3,$4::tool_status
+           FROM catalogue_items WHERE id=$1
+           RETURNING id"#,
     )
-    .bind(body.name.trim()).bind(&category_str).bind(&body.subcategory).bind(body.quantity)
-    .bind(&unit).bind(body.lab_id).bind(&body.description).bind(is_consumable)
-    .bind(&body.consumable_type).bind(threshold).bind(status)
-    .fetch_one(&state.db).await?;
+    .bind(item_id).bind(body.lab_id).bind(threshold).bind(compute_status(0, 0, 0, 0, threshold))
+    .fetch_one(&mut *conn).await?.try_get("id")?;
+
+    if body.quantity > 0 {
+        record_movement(conn, NewMovement {
+            tool_id: id, kind: StockMovementKind::Received, delta: body.quantity,
+            reason: "Initial stock", actor, delegation_id: None,
+        }).await?;
+    }
+    Ok((id, item_id))
+}
+
+pub async fn create(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Json(body): Json<CreateToolRequest>,
+) -> Result<(StatusCode, Json<Value>)> {
+    check_new_tool(&body).map_err(|(_, m)| AppError::Validation(m))?;
+
+    let mut tx = state.db.begin().await?;
+    let (id, item_id) = insert_tool(&mut tx, &body, &claims.sub).await?;
+    let row = sqlx::query("SELECT status::text AS status, created_at FROM tools WHERE id=$1")
+        .bind(id).fetch_one(&mut *tx).await?;
+    tx.commit().await?;
 
     Ok((StatusCode::CREATED, Json(json!({
-        "id":        row.try_get::<i32,_>("id")?,
-        "status":    row.try_get::<String,_>("status")?,
-        "createdAt": row.try_get::<chrono::DateTime<chrono::Utc>,_>("created_at")?,
+        "id":              id,
+        "catalogueItemId": item_id,
+        "status":          row.try_get::<String,_>("status")?,
+        "createdAt":       row.try_get::<chrono::DateTime<chrono::Utc>,_>("created_at")?,
     }))))
 }
 
+const IMPORT_FIELDS: &[Field] = &[
+    Field { name: "name",                aliases: &["tool", "tool name", "item", "item name"], required: true },
+    Field { name: "category",            aliases: &["type", "tool category"], required: true },
+    Field { name: "subcategory",         aliases: &["sub category"], required: false },
+    Field { name: "quantity",            aliases: &["qty", "count", "stock"], required: false },
+    Field { name: "unit",                aliases: &["uom", "units"], required: false },
+    Field { name: "lab",                 aliases: &["lab name", "laboratory", "location"], required: false },
+    Field { name: "description",         aliases: &["details", "notes"], required: false },
+    Field { name: "is_consumable",       aliases: &["consumable"], required: false },
+    Field { name: "consumable_type",     aliases: &[], required: false },
+    Field { name: "low_stock_threshold", aliases: &["reorder level", "threshold", "min stock"], required: false },
+];
+
+/// Names at least this similar (normalised Levenshtein) to another tool in
+/// the same lab are flagged as likely duplicates.
+const SIMILAR_NAME: f64 = 0.85;
+
+/// Reads a spreadsheet category: a `categories` override (label → category
+/// name) first, then the category names themselves and common short forms.
+fn parse_category(raw: &str, overrides: &HashMap<String, String>) -> Option<ToolCategory> {
+    let key = normalize(raw);
+    let target = overrides.iter().find(|(k, _)| normalize(k) == key)
+        .map(|(_, v)| normalize(v)).unwrap_or(key);
+    let target = target.strip_suffix('s').unwrap_or(&target);
+    match target {
+        "handtool" | "hand"                                => Some(ToolCategory::HandTool),
+        "electricaltool" | "electrical" | "powertool"      => Some(ToolCategory::ElectricalTool),
+        "electroniccomponent" | "electronic" | "component" => Some(ToolCategory::ElectronicComponent),
+        "mechatronic"                                      => Some(ToolCategory::Mechatronics),
+        "consumable"                                       => Some(ToolCategory::Consumable),
+        _                                                  => None,
+    }
+}
+
+fn parse_flag(raw: &str) -> Option<bool> {
+    match raw.to_ascii_lowercase().as_str() {
+        "yes" | "y" | "true" | "1" => Some(true),
+        "no" | "n" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Lower-cased words of a name, for comparing near-duplicates.
+fn name_key(name: &str) -> String {
+    name.to_lowercase().split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+// POST /tools/import?dry_run=true&allow_similar=true — multipart `file`
+// (CSV/XLSX), optional `mapping` and `categories` JSON; adds every row in one
+// transaction, or none if any row fails
+pub async fn import(
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
+    Query(opts): Query<ToolImportOptions>, mut multipart: Multipart,
+) -> Result<(StatusCode, Json<ImportReport<ToolImportRow>>)> {
+    let dry_run = opts.dry_run;
+    let allow_similar = opts.allow_similar;
+    let upload = read_import(&mut multipart, state.config.max_upload_bytes).await?;
+    let sheet = &upload.sheet;
+    let cols = sheet.columns(IMPORT_FIELDS, &upload.mapping)?;
+    let categories: HashMap<String, String> = match upload.options.get("categories") {
+        Some(json) => serde_json::from_str(json).map_err(|e| AppError::Validation(
+            format!("categories must be a JSON object of label to category: {}", e),
+        ))?,
+        None => HashMap::new(),
+    };
+
+    let labs: HashMap<String, (i32, String)> = sqlx::query("SELECT id,name FROM labs WHERE deleted_at IS NULL")
+        .fetch_all(&state.db).await?.iter()
+        .map(|r| Ok((normalize(&r.try_get::<String,_>("name")?), (r.try_get("id")?, r.try_get("name")?))))
+        .collect::<Result<_>>()?;
+    let mut existing: HashMap<Option<i32>, Vec<(i32, String)>> = HashMap::new();
+    for r in sqlx::query("SELECT id,lab_id,name FROM tools").fetch_all(&state.db).await? {
+        existing.entry(r.try_get("lab_id")?).or_default().push((r.try_get("id")?, r.try_get("name")?));
+    }
+
+    let mut rows: Vec<ToolImportRow> = Vec::new();
+    let mut errors = Vec::new();
+    for sheet_row in &sheet.rows {
+        let (row, cells) = (sheet_row.number, &sheet_row.cells);
+        let text = |f| cols.get(cells, f).map(str::to_string);
+        let mut fail = |field, message: String| errors.push(RowError { row, field: Some(field), message });
+
+        let Some(category) = cols.get(cells, "category").and_then(|c| parse_category(c, &categories)) else {
+            fail("category", format!("Unknown category '{}'", cols.get(cells, "category").unwrap_or("")));
+            continue;
+        };
+        let int = |f| cols.get(cells, f).map(|v| v.parse::<f64>().ok()
+            .filter(|n| n.fract() == 0.0).map(|n| n as i32).ok_or(f)).transpose();
+        let (quantity, threshold) = match (int("quantity"), int("low_stock_threshold")) {
+            (Ok(q), Ok(t)) => (q.unwrap_or(0), t),
+            (Err(f), _) | (_, Err(f)) => { fail(f, format!("{} must be a whole number", f)); continue; }
+        };
+        let is_consumable = match cols.get(cells, "is_consumable") {
+            Some(v) => match parse_flag(v) {
+                Some(b) => b,
+                None => { fail("is_consumable", format!("'{}' is not yes/no", v)); continue; }
+            },
+            None => category == ToolCategory::Consumable,
+        };
+        let lab = match cols.get(cells, "lab") {
+            Some(name) => match labs.get(&normalize(name)) {
+                Some(lab) => Some(lab.clone()),
+                None => { fail("lab", format!("No lab named '{}'", name)); continue; }
+            },
+            None => None,
+        };
+
+        let candidate = ToolImportRow {
+            row, name: text("name").unwrap_or_default(), category, subcategory: text("subcategory"),
+            quantity, unit: text("unit"), lab_id: lab.as_ref().map(|l| l.0),
+            lab_name: lab.map(|l| l.1), description: text("description"), is_consumable,
+            consumable_type: text("consumable_type"), low_stock_threshold: threshold, similar_to: None,
+        };
+        if let Err((field, message)) = check_new_tool(&candidate.request()) {
+            fail(field, message);
+            continue;
+        }
+
+        // Closest existing tool in the lab, or earlier row for the same lab.
+        let key = name_key(&candidate.name);
+        let in_lab = existing.get(&candidate.lab_id).into_iter().flatten()
+            .map(|(id, name)| (Some(*id), None, name));
+        let in_file = rows.iter().filter(|r| r.lab_id == candidate.lab_id)
+            .map(|r| (None, Some(r.row), &r.name));
+        let closest = in_lab.chain(in_file)
+            .map(|(tool_id, row, name)| (tool_id, row, name, strsim::normalized_levenshtein(&key, &name_key(name))))
+            .max_by(|a, b| a.3.total_cmp(&b.3));
+        let mut candidate = candidate;
+        if let Some((tool_id, other_row, name, similarity)) = closest {
+            if similarity >= 1.0 {
+                let what = tool_id.map(|id| format!("tool #{}", id))
+                    .unwrap_or_else(|| format!("row {}", other_row.unwrap_or_default()));
+                fail("name", format!("'{}' is already listed in this lab as {}", candidate.name, what));
+                continue;
+            }
+            if similarity >= SIMILAR_NAME {
+                if !allow_similar {
+                    fail("name", format!("'{}' looks like a duplicate of '{}'", candidate.name, name));
+                }
+                candidate.similar_to = Some(SimilarTool {
+                    tool_id, row: other_row, name: name.clone(), similarity: (similarity * 100.0).round() / 100.0,
+                });
+            }
+        }
+        rows.push(candidate);
+    }
+
+    let mut report = ImportReport {
+        dry_run, committed: false, total_rows: sheet.rows.len(), created: rows.len(), updated: 0,
+        columns: sheet.header_names(&cols), errors, rows,
+    };
+    if !report.errors.is_empty() {
+        let status = if dry_run { StatusCode::OK } else { StatusCode::UNPROCESSABLE_ENTITY };
+        return Ok((status, Json(report)));
+    }
+    if dry_run { return Ok((StatusCode::OK, Json(report))); }
+
+    let mut tx = state.db.begin().await?;
+    for r in &report.rows {
+        insert_tool(&mut tx, &r.request(), &claims.sub).await.map_err(|e| match e {
+            AppError::Conflict(m) | AppError::Validation(m) => AppError::Validation(format!("Row {}: {}", r.row, m)),
+            other => other,
+        })?;
+    }
+    tx.commit().await?;
+    report.committed = true;
+    Ok((StatusCode::OK, Json(report)))
+}
+
 pub async fn update(
-    _auth: AuthUser, State(state): State<AppState>,
+    AuthUser(claims): AuthUser, State(state): State<AppState>,
     Path(id): Path<i32>, Json(body): Json<UpdateToolRequest>,
 ) -> Result<Json<Value>> {
+    let mut tx = state.db.begin().await?;
+
     let current = sqlx::query(
-        "SELECT quantity,issued_qty,low_stock_threshold FROM tools WHERE id=$1",
+        "SELECT catalogue_item_id,lab_id,quantity,issued_qty,maintenance_qty,quarantined_qty,
+                low_stock_threshold
+         FROM tools WHERE id=$1 AND deleted_at IS NULL FOR UPDATE",
     )
-    .bind(id).fetch_optional(&state.db).await?.ok_or(AppError::NotFound)?;
+    .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
 
+    let item_id:   i32 = current.try_get("catalogue_item_id")?;
+    let cur_lab:   Option<i32> = current.try_get("lab_id")?;
     let cur_qty:   i32 = current.try_get("quantity")?;
     let cur_iss:   i32 = current.try_get("issued_qty")?;
+    let cur_maint: i32 = current.try_get("maintenance_qty")?;
+    let cur_quar:  i32 = current.try_get("quarantined_qty")?;
     let cur_thr:   i32 = current.try_get("low_stock_threshold")?;
 
     let new_qty   = body.quantity.unwrap_or(cur_qty);
     let new_thr   = body.low_stock_threshold.unwrap_or(cur_thr);
-    let new_status = compute_status(new_qty, cur_iss, new_thr);
-    let cat_str   = body.category.as_ref().map(|c| serde_json::to_string(c).unwrap_or_default().trim_matches('"').to_string());
+    let new_status = compute_status(new_qty, cur_iss, cur_maint, cur_quar, new_thr);
+
+    if new_qty < 0 { return Err(AppError::Validation("Quantity cannot be negative".into())); }
+    if new_qty != cur_qty {
+        let reason = body.quantity_reason.as_deref().filter(|r| !r.trim().is_empty())
+            .ok_or_else(|| AppError::Validation("quantity_reason required when changing quantity".into()))?;
+        record_movement(&mut tx, NewMovement {
+            tool_id: id, kind: StockMovementKind::Correction, delta: new_qty - cur_qty,
+            reason, actor: &claims.sub, delegation_id: None,
+        }).await?;
+    }
+
+    if body.lab_id.is_some() && body.lab_id != cur_lab {
+        ensure_single_holding(&mut tx, item_id, body.lab_id, Some(id)).await?;
+    }
+
+    // Descriptive fields belong to the catalogue item and reach every lab's
+    // holding through the sync trigger, so the caller must mean it.
+    let describes = body.name.is_some() || body.category.is_some() || body.subcategory.is_some()
+        || body.unit.is_some() || body.description.is_some() || body.is_consumable.is_some()
+        || body.consumable_type.is_some();
+    let mut other_lines: i64 = 0;
+    if describes {
+        other_lines = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM tools WHERE catalogue_item_id=$1 AND id<>$2 AND deleted_at IS NULL",
+        )
+        .bind(item_id).bind(id).fetch_one(&mut *tx).await?;
+        if other_lines > 0 && !body.apply_to_all_labs {
+            return Err(AppError::Conflict(format!(
+                "Catalogue item #{} is also held by {} other stock line(s); pass apply_to_all_labs to change it everywhere",
+                item_id, other_lines,
+            )));
+        }
+        sqlx::query(
+            r#"UPDATE catalogue_items SET
+                   name=COALESCE($1,name), category=COALESCE($2,category),
+                   subcategory=COALESCE($3,subcategory), unit=COALESCE($4,unit),
+                   description=COALESCE($5,description), is_consumable=COALESCE($6,is_consumable),
+                   consumable_type=COALESCE($7,consumable_type)
+               WHERE id=$8"#,
+        )
+        .bind(&body.name).bind(&body.category).bind(&body.subcategory)
+        .bind(&body.unit).bind(&body.description).bind(body.is_consumable)
+        .bind(&body.consumable_type).bind(item_id)
+        .execute(&mut *tx).await?;
+    }
 
     let row = sqlx::query(
         r#"UPDATE tools SET
-               name=COALESCE($1,name), category=COALESCE($2::tool_category,category),
-               subcategory=COALESCE($3,subcategory), quantity=COALESCE($4,quantity),
-               unit=COALESCE($5,unit), lab_id=COALESCE($6,lab_id),
-               description=COALESCE($7,description), is_consumable=COALESCE($8,is_consumable),
-               consumable_type=COALESCE($9,consumable_type),
-               low_stock_threshold=COALESCE($10,low_stock_threshold),
-               status=$11::tool_status
-           WHERE id=$12
+               lab_id=COALESCE($1,lab_id),
+               low_stock_threshold=COALESCE($2,low_stock_threshold),
+               status=$3::tool_status
+           WHERE id=$4
            RETURNING id,name,quantity,issued_qty,status::text AS status,updated_at"#,
     )
-    .bind(&body.name).bind(&cat_str).bind(&body.subcategory).bind(body.quantity)
-    .bind(&body.unit).bind(body.lab_id).bind(&body.description).bind(body.is_consumable)
-    .bind(&body.consumable_type).bind(body.low_stock_threshold).bind(new_status).bind(id)
-    .fetch_one(&state.db).await?;
+    .bind(body.lab_id).bind(body.low_stock_threshold).bind(new_status).bind(id)
+    .fetch_one(&mut *tx).await?;
+    tx.commit().await?;
 
     Ok(Json(json!({
-        "id":        row.try_get::<i32,_>("id")?,
-        "name":      row.try_get::<String,_>("name")?,
-        "quantity":  row.try_get::<i32,_>("quantity")?,
-        "issuedQty": row.try_get::<i32,_>("issued_qty")?,
-        "status":    row.try_get::<String,_>("status")?,
-        "updatedAt": row.try_get::<chrono::DateTime<chrono::Utc>,_>("updated_at")?,
+        "id":                row.try_get::<i32,_>("id")?,
+        "catalogueItemId":   item_id,
+        "name":              row.try_get::<String,_>("name")?,
+        "quantity":          row.try_get::<i32,_>("quantity")?,
+        "issuedQty":         row.try_get::<i32,_>("issued_qty")?,
+        "status":            row.try_get::<String,_>("status")?,
+        "otherLinesUpdated": other_lines,
+        "updatedAt":         row.try_get::<chrono::DateTime<chrono::Utc>,_>("updated_at")?,
     })))
 }
 
+// DELETE /tools/:id — moves the stock line to the trash once nothing is
+// outstanding against it; its images go when it is purged
 pub async fn delete(
-    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
 ) -> Result<StatusCode> {
-    let r = sqlx::query("DELETE FROM tools WHERE id=$1").bind(id).execute(&state.db).await?;
-    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+    let mut tx = state.db.begin().await?;
+    let tool = sqlx::query(
+        "SELECT maintenance_qty,quarantined_qty FROM tools WHERE id=$1 AND deleted_at IS NULL FOR UPDATE",
+    )
+    .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let uses = sqlx::query(
+        r#"SELECT
+               (SELECT COUNT(*) FROM delegations
+                WHERE tool_id=$1 AND status IN ('Issued','Overdue')) AS loans,
+               (SELECT COUNT(*) FROM stock_transfers
+                WHERE $1 IN (source_tool_id,dest_tool_id) AND status='In Transit') AS transfers,
+               (SELECT COUNT(DISTINCT po.id) FROM purchase_order_lines pl
+                JOIN purchase_orders po ON po.id=pl.purchase_order_id
+                WHERE pl.tool_id=$1 AND po.status IN ('Ordered','Partially Received')) AS orders,
+               (SELECT COUNT(*) FROM stocktake_lines sl JOIN stocktakes st ON st.id=sl.stocktake_id
+                WHERE sl.tool_id=$1 AND st.status='Open') AS stocktakes"#,
+    )
+    .bind(id).fetch_one(&mut *tx).await?;
+
+    let mut blockers = Vec::new();
+    let count = |col: &str| uses.try_get::<i64,_>(col);
+    if count("loans")? > 0 { blockers.push(format!("{} loan(s) not yet returned", count("loans")?)); }
+    let maintenance: i32 = tool.try_get("maintenance_qty")?;
+    if maintenance > 0 { blockers.push(format!("{} unit(s) out for maintenance", maintenance)); }
+    let quarantined: i32 = tool.try_get("quarantined_qty")?;
+    if quarantined > 0 { blockers.push(format!("{} unit(s) quarantined after damage", quarantined)); }
+    if count("transfers")? > 0 { blockers.push(format!("{} transfer(s) still in transit", count("transfers")?)); }
+    if count("orders")? > 0 { blockers.push(format!("{} purchase order(s) awaiting delivery", count("orders")?)); }
+    if count("stocktakes")? > 0 { blockers.push("On the count sheet of an open stocktake".into()); }
+
+    move_to_trash(&mut tx, TrashKind::Tool, &id.to_string(), blockers, &claims.sub).await?;
+    tx.commit().await?;
     Ok(StatusCode::NO_CONTENT)
 }
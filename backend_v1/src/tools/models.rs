@@ -1,9 +1,10 @@
 use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[derive(Debug, Default, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
 #[sqlx(type_name = "tool_category", rename_all = "PascalCase")]
 pub enum ToolCategory {
+    #[default]
     #[serde(rename = "Hand Tool")]
     #[sqlx(rename = "Hand Tool")]
     HandTool,
@@ -17,9 +18,10 @@ pub enum ToolCategory {
     Consumable,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[derive(Debug, Default, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
 #[sqlx(type_name = "tool_status", rename_all = "PascalCase")]
 pub enum ToolStatus {
+    #[default]
     Available,
     #[serde(rename = "Partially Issued")]
     #[sqlx(rename = "Partially Issued")]
@@ -30,17 +32,24 @@ pub enum ToolStatus {
     #[serde(rename = "Out of Stock")]
     #[sqlx(rename = "Out of Stock")]
     OutOfStock,
+    #[serde(rename = "Under Maintenance")]
+    #[sqlx(rename = "Under Maintenance")]
+    UnderMaintenance,
+    Quarantined,
 }
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Default, Serialize, sqlx::FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct Tool {
     pub id:                   i32,
+    pub catalogue_item_id:    i32,
     pub name:                 String,
     pub category:             ToolCategory,
     pub subcategory:          Option<String>,
     pub quantity:             i32,
     pub issued_qty:           i32,
+    pub maintenance_qty:      i32,
+    pub quarantined_qty:      i32,
     pub unit:                 String,
     pub lab_id:               Option<i32>,
     pub lab_name:             Option<String>,
@@ -49,9 +58,13 @@ pub struct Tool {
     pub consumable_type:      Option<String>,
     pub low_stock_threshold:  i32,
     pub status:               ToolStatus,
+    pub primary_image_url:    Option<String>,
+    pub thumbnail_url:        Option<String>,
     pub date_added:           NaiveDate,
     pub created_at:           DateTime<Utc>,
     pub updated_at:           DateTime<Utc>,
+    /// Set while the stock line is in the trash
+    pub deleted_at:           Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -74,12 +87,19 @@ pub struct UpdateToolRequest {
     pub category:             Option<ToolCategory>,
     pub subcategory:          Option<String>,
     pub quantity:             Option<i32>,
+    /// Required when `quantity` changes; recorded on the Correction movement
+    pub quantity_reason:      Option<String>,
     pub unit:                 Option<String>,
     pub lab_id:               Option<i32>,
     pub description:          Option<String>,
     pub is_consumable:        Option<bool>,
     pub consumable_type:      Option<String>,
     pub low_stock_threshold:  Option<i32>,
+    /// Descriptive fields belong to the catalogue item; when other labs hold
+    /// it too, changing them needs this set to confirm the change reaches
+    /// every lab
+    #[serde(default)]
+    pub apply_to_all_labs:    bool,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -93,3 +113,54 @@ pub struct ToolFilters {
     pub sort:        Option<String>,
     pub order:       Option<String>,
 }
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ToolImportOptions {
+    /// `true` validates and previews without writing
+    #[serde(default)]
+    pub dry_run:       bool,
+    /// `true` imports rows whose names resemble an existing tool in the lab
+    #[serde(default)]
+    pub allow_similar: bool,
+}
+
+/// An existing tool, or an earlier row of the file, with a similar name
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarTool {
+    pub tool_id:    Option<i32>,
+    pub row:        Option<usize>,
+    pub name:       String,
+    pub similarity: f64,
+}
+
+/// A validated row of a tool import
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolImportRow {
+    pub row:                  usize,
+    pub name:                 String,
+    pub category:             ToolCategory,
+    pub subcategory:          Option<String>,
+    pub quantity:             i32,
+    pub unit:                 Option<String>,
+    pub lab_id:               Option<i32>,
+    pub lab_name:             Option<String>,
+    pub description:          Option<String>,
+    pub is_consumable:        bool,
+    pub consumable_type:      Option<String>,
+    pub low_stock_threshold:  Option<i32>,
+    pub similar_to:           Option<SimilarTool>,
+}
+
+impl ToolImportRow {
+    pub fn request(&self) -> CreateToolRequest {
+        CreateToolRequest {
+            name: self.name.clone(), category: self.category.clone(),
+            subcategory: self.subcategory.clone(), quantity: self.quantity, unit: self.unit.clone(),
+            lab_id: self.lab_id, description: self.description.clone(),
+            is_consumable: Some(self.is_consumable), consumable_type: self.consumable_type.clone(),
+            low_stock_threshold: self.low_stock_threshold,
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    errors::{AppError, Result},
+    state::AppState,
+    stock::{handlers::record_movement, models::{NewMovement, StockMovementKind}},
+    transfers::models::{CreateTransferRequest, Transfer, TransferFilters},
+};
+
+const TRANSFER_SELECT: &str = r#"
+    SELECT x.id,x.source_tool_id,x.dest_tool_id,t.name AS tool_name,
+           x.from_lab_id,fl.name AS from_lab_name,x.to_lab_id,tl.name AS to_lab_name,
+           x.quantity,x.status,x.notes,x.dispatched_by,x.dispatched_at,
+           x.received_by,x.received_at
+    FROM stock_transfers x
+    JOIN tools t  ON t.id=x.source_tool_id
+    JOIN labs  fl ON fl.id=x.from_lab_id
+    JOIN labs  tl ON tl.id=x.to_lab_id"#;
+
+async fn fetch_transfer(db: &PgPool, id: i32) -> Result<Transfer> {
+    sqlx::query_as::<_, Transfer>(&format!("{} WHERE x.id=$1", TRANSFER_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)
+}
+
+/// Locks an in-transit transfer; returns (source_tool_id, to_lab_id, quantity).
+async fn lock_in_transit(conn: &mut PgConnection, id: i32) -> Result<(i32, i32, i32)> {
+    let row = sqlx::query(
+        r#"SELECT source_tool_id,to_lab_id,quantity,status::text AS status
+           FROM stock_transfers WHERE id=$1 FOR UPDATE"#,
+    )
+    .bind(id).fetch_optional(&mut *conn).await?.ok_or(AppError::NotFound)?;
+    if row.try_get::<String,_>("status")? != "In Transit" {
+        return Err(AppError::Conflict("Transfer is no longer in transit".into()));
+    }
+    Ok((row.try_get("source_tool_id")?, row.try_get("to_lab_id")?, row.try_get("quantity")?))
+}
+
+/// The destination lab's holding of the same catalogue item, created empty
+/// if the lab has never stocked it.
+async fn destination_line(conn: &mut PgConnection, source_tool_id: i32, to_lab_id: i32) -> Result<i32> {
+    let existing = sqlx::query(
+        r#"SELECT d.id, d.deleted_at IS NOT NULL AS trashed FROM tools d JOIN tools s ON s.id=$1
+           WHERE d.lab_id=$2 AND d.catalogue_item_id=s.catalogue_item_id
+           ORDER BY d.id LIMIT 1"#,
+    )
+    .bind(source_tool_id).bind(to_lab_id).fetch_optional(&mut *conn).await?;
+    if let Some(r) = existing {
+        let id: i32 = r.try_get("id")?;
+        if r.try_get("trashed")? {
+            return Err(AppError::Conflict(format!(
+                "The destination lab's stock line, tool #{}, is in the trash; restore it first", id,
+            )));
+        }
+        return Ok(id);
+    }
+
+    let row = sqlx::query(
+        r#"INSERT INTO tools
+               (catalogue_item_id,name,category,subcategory,quantity,unit,lab_id,description,
+                is_consumable,consumable_type,low_stock_threshold,status)
+           SELECT catalogue_item_id,name,category,subcategory,0,unit,$2,description,
+                  is_consumable,consumable_type,low_stock_threshold,'Out of Stock'::tool_status
+           FROM tools WHERE id=$1
+           RETURNING id"#,
+    )
+    .bind(source_tool_id).bind(to_lab_id).fetch_one(&mut *conn).await?;
+    Ok(row.try_get("id")?)
+}
+
+// GET /transfers
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<TransferFilters>,
+) -> Result<Json<Value>> {
+    let transfers = sqlx::query_as::<_, Transfer>(&format!(
+        "{} WHERE ($1::INT IS NULL OR x.from_lab_id=$1 OR x.to_lab_id=$1)
+              AND ($2::TEXT IS NULL OR x.status::text ILIKE $2)
+            ORDER BY x.dispatched_at DESC", TRANSFER_SELECT,
+    ))
+    .bind(filters.lab_id).bind(&filters.status)
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": transfers })))
+}
+
+// GET /labs/:id/transfers — inbound and outbound history for one lab
+pub async fn lab_history(
+    _auth: AuthUser, State(state): State<AppState>, Path(lab_id): Path<i32>,
+) -> Result<Json<Value>> {
+    if sqlx::query("SELECT id FROM labs WHERE id=$1").bind(lab_id)
+        .fetch_optional(&state.db).await?.is_none() { return Err(AppError::NotFound); }
+
+    let transfers = sqlx::query_as::<_, Transfer>(&format!(
+        "{} WHERE x.from_lab_id=$1 OR x.to_lab_id=$1 ORDER BY x.dispatched_at DESC",
+        TRANSFER_SELECT,
+    ))
+    .bind(lab_id).fetch_all(&state.db).await?;
+
+    let (outbound, inbound): (Vec<Transfer>, Vec<Transfer>) =
+        transfers.into_iter().partition(|t| t.from_lab_id == lab_id);
+    Ok(Json(json!({ "labId": lab_id, "outbound": outbound, "inbound": inbound })))
+}
+
+// GET /transfers/:id
+pub async fn get_one(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<Transfer>> {
+    fetch_transfer(&state.db, id).await.map(Json)
+}
+
+// POST /transfers — dispatch: units leave the source line immediately
+pub async fn dispatch(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Json(body): Json<CreateTransferRequest>,
+) -> Result<(StatusCode, Json<Transfer>)> {
+    if body.quantity <= 0 { return Err(AppError::Validation("Quantity must be >= 1".into())); }
+
+    let mut tx = state.db.begin().await?;
+
+    let tool = sqlx::query("SELECT lab_id FROM tools WHERE id=$1 AND deleted_at IS NULL FOR UPDATE")
+        .bind(body.tool_id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let from_lab_id: i32 = tool.try_get::<Option<i32>,_>("lab_id")?
+        .ok_or_else(|| AppError::Validation("Tool is not assigned to a lab".into()))?;
+    if from_lab_id == body.to_lab_id {
+        return Err(AppError::Validation("Destination lab must differ from the source lab".into()));
+    }
+    let to_lab_name: String = sqlx::query("SELECT name FROM labs WHERE id=$1 AND deleted_at IS NULL")
+        .bind(body.to_lab_id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?
+        .try_get("name")?;
+
+    let id: i32 = sqlx::query(
+        r#"INSERT INTO stock_transfers
+               (source_tool_id,from_lab_id,to_lab_id,quantity,notes,dispatched_by)
+           VALUES ($1,$2,$3,$4,$5,$6) RETURNING id"#,
+    )
+    .bind(body.tool_id).bind(from_lab_id).bind(body.to_lab_id).bind(body.quantity)
+    .bind(&body.notes).bind(&claims.sub)
+    .fetch_one(&mut *tx).await?.try_get("id")?;
+
+    record_movement(&mut tx, NewMovement {
+        tool_id: body.tool_id, kind: StockMovementKind::Transfer, delta: -body.quantity,
+        reason: &format!("Transfer #{} dispatched to {}", id, to_lab_name),
+        actor: &claims.sub, delegation_id: None,
+    }).await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(fetch_transfer(&state.db, id).await?)))
+}
+
+// POST /transfers/:id/receive
+pub async fn receive(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<Transfer>> {
+    let mut tx = state.db.begin().await?;
+    let (source_tool_id, to_lab_id, quantity) = lock_in_transit(&mut tx, id).await?;
+
+    let dest_tool_id = destination_line(&mut tx, source_tool_id, to_lab_id).await?;
+    record_movement(&mut tx, NewMovement {
+        tool_id: dest_tool_id, kind: StockMovementKind::Transfer, delta: quantity,
+        reason: &format!("Transfer #{} received", id),
+        actor: &claims.sub, delegation_id: None,
+    }).await?;
+
+    sqlx::query(
+        r#"UPDATE stock_transfers SET status='Received'::transfer_status,
+               dest_tool_id=$1, received_by=$2, received_at=NOW()
+           WHERE id=$3"#,
+    )
+    .bind(dest_tool_id).bind(&claims.sub).bind(id).execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    fetch_transfer(&state.db, id).await.map(Json)
+}
+
+// POST /transfers/:id/cancel — units in transit go back to the source line
+pub async fn cancel(
+    AuthUser(claims): AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<Transfer>> {
+    let mut tx = state.db.begin().await?;
+    let (source_tool_id, _, quantity) = lock_in_transit(&mut tx, id).await?;
+
+    record_movement(&mut tx, NewMovement {
+        tool_id: source_tool_id, kind: StockMovementKind::Transfer, delta: quantity,
+        reason: &format!("Transfer #{} cancelled", id),
+        actor: &claims.sub, delegation_id: None,
+    }).await?;
+
+    sqlx::query("UPDATE stock_transfers SET status='Cancelled'::transfer_status WHERE id=$1")
+        .bind(id).execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    fetch_transfer(&state.db, id).await.map(Json)
+}
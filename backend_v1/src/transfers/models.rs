@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[sqlx(type_name = "transfer_status", rename_all = "PascalCase")]
+pub enum TransferStatus {
+    #[serde(rename = "In Transit")]
+    #[sqlx(rename = "In Transit")]
+    InTransit,
+    Received,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Transfer {
+    pub id:             i32,
+    pub source_tool_id: i32,
+    pub dest_tool_id:   Option<i32>,
+    pub tool_name:      String,
+    pub from_lab_id:    i32,
+    pub from_lab_name:  String,
+    pub to_lab_id:      i32,
+    pub to_lab_name:    String,
+    pub quantity:       i32,
+    pub status:         TransferStatus,
+    pub notes:          Option<String>,
+    pub dispatched_by:  String,
+    pub dispatched_at:  DateTime<Utc>,
+    pub received_by:    Option<String>,
+    pub received_at:    Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTransferRequest {
+    pub tool_id:   i32,
+    pub to_lab_id: i32,
+    pub quantity:  i32,
+    pub notes:     Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct TransferFilters {
+    pub lab_id: Option<i32>,
+    pub status: Option<String>,
+}
@@ -0,0 +1,165 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde_json::{json, Value};
+use sqlx::PgConnection;
+
+use crate::{
+    attachments::{handlers::delete_owned, models::AttachmentOwner},
+    auth::middleware::AuthUser,
+    errors::{AppError, Result},
+    state::AppState,
+    trash::models::{TrashFilters, TrashItem, TrashKind},
+};
+
+/// Where one kind of record lives, and the history that keeps a trashed row
+/// from being purged (`x` is the row).
+struct Bin {
+    kind:     TrashKind,
+    name:     &'static str,
+    label:    &'static str,
+    table:    &'static str,
+    key:      &'static str,
+    key_type: &'static str,
+    kept:     &'static str,
+}
+
+// Tools come before labs so a lab emptied by a purge goes in the same run.
+const BINS: [Bin; 4] = [
+    Bin {
+        kind: TrashKind::Tool, name: "tool", label: "Tool", table: "tools", key: "id", key_type: "INT",
+        kept: r#"EXISTS (SELECT 1 FROM delegations WHERE tool_id=x.id)
+              OR EXISTS (SELECT 1 FROM stock_movements WHERE tool_id=x.id)
+              OR EXISTS (SELECT 1 FROM stocktake_lines WHERE tool_id=x.id)
+              OR EXISTS (SELECT 1 FROM maintenance_records WHERE tool_id=x.id)
+              OR EXISTS (SELECT 1 FROM stock_transfers WHERE x.id IN (source_tool_id,dest_tool_id))
+              OR EXISTS (SELECT 1 FROM purchase_order_lines WHERE tool_id=x.id)
+              OR EXISTS (SELECT 1 FROM damage_reports WHERE tool_id=x.id)"#,
+    },
+    Bin {
+        kind: TrashKind::Lecturer, name: "lecturer", label: "Lecturer", table: "lecturers", key: "id", key_type: "INT",
+        kept: r#"EXISTS (SELECT 1 FROM delegations WHERE lecturer_id=x.id)
+              OR EXISTS (SELECT 1 FROM cohort_issues WHERE lecturer_id=x.id)"#,
+    },
+    Bin {
+        kind: TrashKind::Student, name: "student", label: "Student", table: "students", key: "student_id", key_type: "TEXT",
+        kept: r#"EXISTS (SELECT 1 FROM delegations WHERE student_id=x.student_id)
+              OR EXISTS (SELECT 1 FROM student_charges WHERE student_id=x.student_id)
+              OR EXISTS (SELECT 1 FROM student_ban_events WHERE student_id=x.student_id)
+              OR EXISTS (SELECT 1 FROM ban_appeals WHERE student_id=x.student_id)
+              OR EXISTS (SELECT 1 FROM student_clearances WHERE student_id=x.student_id)
+              OR EXISTS (SELECT 1 FROM unit_enrolments WHERE student_id=x.student_id)"#,
+    },
+    Bin {
+        kind: TrashKind::Lab, name: "lab", label: "Lab", table: "labs", key: "id", key_type: "INT",
+        kept: r#"EXISTS (SELECT 1 FROM tools WHERE lab_id=x.id)
+              OR EXISTS (SELECT 1 FROM stocktakes WHERE lab_id=x.id)
+              OR EXISTS (SELECT 1 FROM stock_transfers WHERE x.id IN (from_lab_id,to_lab_id))"#,
+    },
+];
+
+fn bin(kind: TrashKind) -> &'static Bin {
+    BINS.iter().find(|b| b.kind == kind).expect("every kind has a bin")
+}
+
+/// Moves a record to the trash, unless something still depends on it.
+pub async fn move_to_trash(
+    conn: &mut PgConnection, kind: TrashKind, id: &str, blockers: Vec<String>, actor: &str,
+) -> Result<()> {
+    let bin = bin(kind);
+    if !blockers.is_empty() {
+        return Err(AppError::Blocked(format!("{} cannot be deleted", bin.label), blockers));
+    }
+    let r = sqlx::query(&format!(
+        "UPDATE {} SET deleted_at=NOW(), deleted_by=$2 WHERE {}=$1::{} AND deleted_at IS NULL",
+        bin.table, bin.key, bin.key_type,
+    ))
+    .bind(id).bind(actor).execute(&mut *conn).await?;
+    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+    Ok(())
+}
+
+// GET /trash?kind=
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<TrashFilters>,
+) -> Result<Json<Value>> {
+    let sql = BINS.iter().map(|b| format!(
+        r#"SELECT '{name}' AS kind, x.{key}::text AS id, x.name, x.deleted_at, x.deleted_by,
+                  CASE WHEN {kept} THEN NULL ELSE x.deleted_at + $1 * INTERVAL '1 day' END AS purge_after
+           FROM {table} x
+           WHERE x.deleted_at IS NOT NULL AND ($2::TEXT IS NULL OR LOWER($2)='{name}')"#,
+        name = b.name, key = b.key, kept = b.kept, table = b.table,
+    )).collect::<Vec<_>>().join(" UNION ALL ");
+    let items = sqlx::query_as::<_, TrashItem>(&format!("{} ORDER BY deleted_at DESC", sql))
+        .bind(state.config.trash_retention_days).bind(&filters.kind)
+        .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": items })))
+}
+
+// POST /trash/:kind/:id/restore
+pub async fn restore(
+    _auth: AuthUser, State(state): State<AppState>, Path((kind, id)): Path<(TrashKind, String)>,
+) -> Result<StatusCode> {
+    let bin = bin(kind);
+    if bin.key_type == "INT" && id.parse::<i32>().is_err() { return Err(AppError::NotFound); }
+    let mut tx = state.db.begin().await?;
+    if kind == TrashKind::Tool {
+        let lab: Option<String> = sqlx::query_scalar(
+            "SELECT l.name FROM tools t JOIN labs l ON l.id=t.lab_id WHERE t.id=$1::INT AND l.deleted_at IS NOT NULL",
+        )
+        .bind(&id).fetch_optional(&mut *tx).await?;
+        if let Some(lab) = lab {
+            return Err(AppError::Conflict(format!("Restore lab '{}' first", lab)));
+        }
+    }
+    let r = sqlx::query(&format!(
+        "UPDATE {} SET deleted_at=NULL, deleted_by=NULL WHERE {}=$1::{} AND deleted_at IS NOT NULL",
+        bin.table, bin.key, bin.key_type,
+    ))
+    .bind(&id).execute(&mut *tx).await?;
+    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Permanently removes records trashed longer than the retention period.
+/// Records that history still refers to stay in the trash; a record that
+/// fails to delete is logged and left for the next run.
+pub async fn purge(state: &AppState) -> Result<u64> {
+    let mut purged = 0;
+    for b in &BINS {
+        let ids: Vec<String> = sqlx::query_scalar(&format!(
+            "SELECT x.{key}::text FROM {table} x
+             WHERE x.deleted_at < NOW() - $1 * INTERVAL '1 day' AND NOT ({kept})",
+            table = b.table, kept = b.kept, key = b.key,
+        ))
+        .bind(state.config.trash_retention_days).fetch_all(&state.db).await?;
+        for id in ids {
+            match purge_one(state, b, &id).await {
+                Ok(true)  => purged += 1,
+                Ok(false) => {}
+                Err(e)    => tracing::error!("Trash purge: {} {} not removed: {}", b.name, id, e),
+            }
+        }
+    }
+    Ok(purged)
+}
+
+/// Deletes one trashed record, re-checking `kept` in case history was
+/// written since the candidates were listed.
+async fn purge_one(state: &AppState, b: &Bin, id: &str) -> Result<bool> {
+    let r = sqlx::query(&format!(
+        "DELETE FROM {table} x WHERE x.{key}=$1::{key_type} AND x.deleted_at IS NOT NULL AND NOT ({kept})",
+        table = b.table, key = b.key, key_type = b.key_type, kept = b.kept,
+    ))
+    .bind(id).execute(&state.db).await?;
+    if r.rows_affected() == 0 { return Ok(false); }
+    if b.kind == TrashKind::Tool {
+        if let Ok(id) = id.parse() {
+            delete_owned(state, AttachmentOwner::Tool, id).await?;
+        }
+    }
+    Ok(true)
+}
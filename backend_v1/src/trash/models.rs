@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TrashKind {
+    Lab,
+    Tool,
+    Lecturer,
+    Student,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    pub kind:        String,
+    /// Numeric id, or the student number for students
+    pub id:          String,
+    pub name:        String,
+    pub deleted_at:  DateTime<Utc>,
+    pub deleted_by:  Option<String>,
+    /// When the purge job removes it; `None` while history refers to it
+    pub purge_after: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct TrashFilters {
+    /// lab | tool | lecturer | student
+    pub kind: Option<String>,
+}
@@ -0,0 +1,311 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool, Row};
+
+use crate::{
+    auth::middleware::AuthUser,
+    codes,
+    errors::{AppError, Result},
+    lecturers::handlers::check_lecturer,
+    state::AppState,
+    students::{handlers::STUDENT_COLUMNS, models::Student},
+    units::models::{
+        CourseUnit, CreateUnitRequest, EnrolRequest, KitRequest, LoanUnit, UnitDetail, UnitFilters,
+        UnitKitItem, UpdateUnitRequest,
+    },
+};
+
+const UNIT_SELECT: &str = r#"
+    SELECT u.id,u.code,u.name,u.department,u.lecturer_id,l.name AS lecturer_name,
+           (SELECT COUNT(*) FROM unit_enrolments e JOIN students s ON s.student_id=e.student_id
+            WHERE e.unit_id=u.id AND s.deleted_at IS NULL) AS enrolled_count,
+           (SELECT COUNT(*) FROM unit_kit_items k WHERE k.unit_id=u.id) AS kit_item_count,
+           u.created_at
+    FROM course_units u
+    LEFT JOIN lecturers l ON l.id=u.lecturer_id"#;
+
+/// The codes in `codes` that are not in the units catalogue, as given.
+pub async fn unknown_units(conn: &mut PgConnection, codes: &[String]) -> Result<Vec<String>> {
+    let normalised: Vec<String> = codes.iter().map(|c| codes::normalise(c)).collect();
+    let known: HashSet<String> = sqlx::query_scalar("SELECT code FROM course_units WHERE code=ANY($1)")
+        .bind(&normalised).fetch_all(&mut *conn).await?.into_iter().collect();
+    Ok(codes.iter().zip(&normalised)
+        .filter(|(_, n)| !known.contains(*n))
+        .map(|(c, _)| c.clone())
+        .collect())
+}
+
+/// Rejects a `units` list naming a unit the catalogue does not have.
+pub async fn check_units(conn: &mut PgConnection, codes: Option<&[String]>) -> Result<()> {
+    let Some(codes) = codes else { return Ok(()) };
+    let unknown = unknown_units(conn, codes).await?;
+    if !unknown.is_empty() {
+        return Err(AppError::Validation(format!("Unknown units: {}", unknown.join(", "))));
+    }
+    Ok(())
+}
+
+/// Works out which unit a loan is for. A given unit must list the tool in
+/// its kit and have the student enrolled. Otherwise the loan is for the one
+/// unit the student takes whose kit lists the tool, if there is exactly one.
+/// Only kit lines marked `enrolled_only` restrict who may borrow: such a tool
+/// needs the student to take one of those units; guests borrowing across
+/// departments are exempt.
+pub async fn unit_for_loan(
+    conn: &mut PgConnection, tool_id: i32, student_id: &str, unit_id: Option<i32>, guest: bool,
+) -> Result<Option<LoanUnit>> {
+    let units = sqlx::query(
+        r#"SELECT u.id,u.code,u.lecturer_id,k.enrolled_only,
+                  EXISTS (SELECT 1 FROM unit_enrolments e
+                          WHERE e.unit_id=u.id AND e.student_id=$2) AS enrolled
+           FROM course_units u
+           JOIN unit_kit_items k ON k.unit_id=u.id
+           JOIN tools t ON t.catalogue_item_id=k.catalogue_item_id
+           WHERE t.id=$1
+           ORDER BY u.code"#,
+    )
+    .bind(tool_id).bind(student_id).fetch_all(&mut *conn).await?;
+
+    if let Some(unit_id) = unit_id {
+        let code: String = sqlx::query_scalar("SELECT code FROM course_units WHERE id=$1")
+            .bind(unit_id).fetch_optional(&mut *conn).await?
+            .ok_or_else(|| AppError::Validation("Unit not found".into()))?;
+        let Some(unit) = units.iter().find(|u| u.get::<i32,_>("id") == unit_id) else {
+            return Err(AppError::Validation(format!("{}'s kit does not include this tool", code)));
+        };
+        if !unit.try_get::<bool,_>("enrolled")? {
+            return Err(AppError::Validation(format!("{} is not enrolled in {}", student_id, code)));
+        }
+        return Ok(Some(LoanUnit { id: unit_id, lecturer_id: unit.try_get("lecturer_id")? }));
+    }
+    if guest { return Ok(None); }
+
+    let codes = |rows: &[&sqlx::postgres::PgRow]| rows.iter()
+        .map(|u| u.get::<String,_>("code")).collect::<Vec<_>>().join(", ");
+    let restricting: Vec<_> = units.iter().filter(|u| u.get::<bool,_>("enrolled_only")).collect();
+    let enrolled: Vec<_> = units.iter().filter(|u| u.get::<bool,_>("enrolled")).collect();
+    if !restricting.is_empty() && !restricting.iter().any(|u| u.get::<bool,_>("enrolled")) {
+        return Err(AppError::Validation(format!(
+            "{} is not enrolled in a unit that reserves this tool ({})", student_id, codes(&restricting),
+        )));
+    }
+    match enrolled.as_slice() {
+        [unit] => Ok(Some(LoanUnit { id: unit.try_get("id")?, lecturer_id: unit.try_get("lecturer_id")? })),
+        [] => Ok(None),
+        _ if restricting.is_empty() => Ok(None),
+        several => Err(AppError::Validation(format!(
+            "unit_id required: {} takes several units that use this tool ({})", student_id, codes(several),
+        ))),
+    }
+}
+
+async fn fetch_unit(db: &PgPool, id: i32) -> Result<UnitDetail> {
+    let unit = sqlx::query_as::<_, CourseUnit>(&format!("{} WHERE u.id=$1", UNIT_SELECT))
+        .bind(id).fetch_optional(db).await?.ok_or(AppError::NotFound)?;
+    let kit = sqlx::query_as::<_, UnitKitItem>(
+        r#"SELECT k.catalogue_item_id,c.name,k.quantity,k.enrolled_only,
+                  COALESCE(SUM(t.quantity-t.issued_qty-t.held_qty),0)::BIGINT AS available
+           FROM unit_kit_items k
+           JOIN catalogue_items c ON c.id=k.catalogue_item_id
+           LEFT JOIN tools t ON t.catalogue_item_id=c.id AND t.deleted_at IS NULL
+           WHERE k.unit_id=$1
+           GROUP BY k.catalogue_item_id,c.name,k.quantity,k.enrolled_only
+           ORDER BY c.name"#,
+    )
+    .bind(id).fetch_all(db).await?;
+    let students = sqlx::query_as::<_, Student>(&format!(
+        r#"SELECT {} FROM students
+           WHERE deleted_at IS NULL
+             AND student_id IN (SELECT student_id FROM unit_enrolments WHERE unit_id=$1)
+           ORDER BY name"#, STUDENT_COLUMNS,
+    ))
+    .bind(id).fetch_all(db).await?;
+    Ok(UnitDetail { unit, kit, students })
+}
+
+/// A unit code must be new (after normalising) and fit the column.
+async fn check_code(conn: &mut PgConnection, code: &str, except: Option<i32>) -> Result<String> {
+    let code = codes::normalise(code);
+    if code.is_empty() { return Err(AppError::Validation("Unit code needs letters or digits".into())); }
+    if code.len() > 20 { return Err(AppError::Validation("Unit code is longer than 20 characters".into())); }
+    if sqlx::query("SELECT id FROM course_units WHERE code=$1 AND ($2::INT IS NULL OR id<>$2)")
+        .bind(&code).bind(except).fetch_optional(&mut *conn).await?.is_some() {
+        return Err(AppError::Conflict(format!("Unit {} already exists", code)));
+    }
+    Ok(code)
+}
+
+// GET /units?lecturer_id=&search=
+pub async fn list(
+    _auth: AuthUser, State(state): State<AppState>, Query(filters): Query<UnitFilters>,
+) -> Result<Json<Value>> {
+    let units = sqlx::query_as::<_, CourseUnit>(&format!(
+        "{} WHERE ($1::INT IS NULL OR u.lecturer_id=$1)
+              AND ($2::TEXT IS NULL OR STRPOS(LOWER(u.name), LOWER($2)) > 0
+                   OR STRPOS(u.code, UPPER($2)) > 0)
+            ORDER BY u.code", UNIT_SELECT,
+    ))
+    .bind(filters.lecturer_id).bind(&filters.search)
+    .fetch_all(&state.db).await?;
+    Ok(Json(json!({ "data": units })))
+}
+
+// GET /units/:id — with its kit and enrolled students
+pub async fn get_one(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<UnitDetail>> {
+    fetch_unit(&state.db, id).await.map(Json)
+}
+
+// POST /units
+pub async fn create(
+    _auth: AuthUser, State(state): State<AppState>, Json(body): Json<CreateUnitRequest>,
+) -> Result<(StatusCode, Json<UnitDetail>)> {
+    if body.name.trim().is_empty() { return Err(AppError::Validation("Unit name is required".into())); }
+    let mut tx = state.db.begin().await?;
+    let code = check_code(&mut tx, &body.code, None).await?;
+    if let Some(lecturer_id) = body.lecturer_id { check_lecturer(&mut tx, lecturer_id).await?; }
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO course_units (code,name,department,lecturer_id) VALUES ($1,$2,$3,$4) RETURNING id",
+    )
+    .bind(&code).bind(body.name.trim()).bind(&body.department).bind(body.lecturer_id)
+    .fetch_one(&mut *tx).await?;
+    tx.commit().await?;
+    Ok((StatusCode::CREATED, Json(fetch_unit(&state.db, id).await?)))
+}
+
+// PUT /units/:id
+pub async fn update(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<UpdateUnitRequest>,
+) -> Result<Json<UnitDetail>> {
+    let mut tx = state.db.begin().await?;
+    let old_code: String = sqlx::query_scalar("SELECT code FROM course_units WHERE id=$1 FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let code = match &body.code {
+        Some(code) => Some(check_code(&mut tx, code, Some(id)).await?),
+        None => None,
+    };
+    if let Some(lecturer_id) = body.lecturer_id { check_lecturer(&mut tx, lecturer_id).await?; }
+
+    sqlx::query(
+        r#"UPDATE course_units SET
+               code=COALESCE($1,code), name=COALESCE($2,name),
+               department=COALESCE($3,department), lecturer_id=COALESCE($4,lecturer_id)
+           WHERE id=$5"#,
+    )
+    .bind(&code).bind(body.name.as_deref().map(str::trim)).bind(&body.department)
+    .bind(body.lecturer_id).bind(id)
+    .execute(&mut *tx).await?;
+    if let Some(code) = code.filter(|c| *c != old_code) {
+        sqlx::query("UPDATE students SET units=ARRAY_REPLACE(units,$1,$2) WHERE $1=ANY(units)")
+            .bind(&old_code).bind(&code).execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    Ok(Json(fetch_unit(&state.db, id).await?))
+}
+
+// DELETE /units/:id — only once nobody is enrolled and no loan names it
+pub async fn delete(
+    _auth: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SELECT id FROM course_units WHERE id=$1 FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let uses = sqlx::query(
+        r#"SELECT (SELECT COUNT(*) FROM unit_enrolments WHERE unit_id=$1) AS enrolled,
+                  (SELECT COUNT(*) FROM delegations WHERE unit_id=$1) AS loans"#,
+    )
+    .bind(id).fetch_one(&mut *tx).await?;
+    let mut blockers = Vec::new();
+    let enrolled: i64 = uses.try_get("enrolled")?;
+    if enrolled > 0 { blockers.push(format!("{} student(s) enrolled", enrolled)); }
+    let loans: i64 = uses.try_get("loans")?;
+    if loans > 0 { blockers.push(format!("{} loan(s) issued for the unit", loans)); }
+    if !blockers.is_empty() {
+        return Err(AppError::Blocked("Unit cannot be deleted".into(), blockers));
+    }
+    sqlx::query("DELETE FROM course_units WHERE id=$1").bind(id).execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// PUT /units/:id/kit — replaces the kit
+pub async fn set_kit(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<KitRequest>,
+) -> Result<Json<UnitDetail>> {
+    if body.items.iter().any(|i| i.quantity <= 0) {
+        return Err(AppError::Validation("Quantity must be >= 1".into()));
+    }
+    let mut seen = HashSet::new();
+    if !body.items.iter().all(|i| seen.insert(i.catalogue_item_id)) {
+        return Err(AppError::Validation("Each catalogue item may appear once in a kit".into()));
+    }
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SELECT id FROM course_units WHERE id=$1 FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let item_ids: Vec<i32> = body.items.iter().map(|i| i.catalogue_item_id).collect();
+    let found: HashSet<i32> = sqlx::query_scalar("SELECT id FROM catalogue_items WHERE id=ANY($1)")
+        .bind(&item_ids).fetch_all(&mut *tx).await?.into_iter().collect();
+    if let Some(missing) = item_ids.iter().find(|i| !found.contains(i)) {
+        return Err(AppError::Validation(format!("Catalogue item {} does not exist", missing)));
+    }
+
+    sqlx::query("DELETE FROM unit_kit_items WHERE unit_id=$1").bind(id).execute(&mut *tx).await?;
+    for item in &body.items {
+        sqlx::query(
+            "INSERT INTO unit_kit_items (unit_id,catalogue_item_id,quantity,enrolled_only) VALUES ($1,$2,$3,$4)",
+        )
+        .bind(id).bind(item.catalogue_item_id).bind(item.quantity).bind(item.enrolled_only)
+        .execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    Ok(Json(fetch_unit(&state.db, id).await?))
+}
+
+// POST /units/:id/enrolments — adds the unit to each student's `units`
+pub async fn enrol(
+    _auth: AuthUser, State(state): State<AppState>,
+    Path(id): Path<i32>, Json(body): Json<EnrolRequest>,
+) -> Result<Json<UnitDetail>> {
+    if body.student_ids.is_empty() { return Err(AppError::Validation("student_ids is empty".into())); }
+    let mut tx = state.db.begin().await?;
+    let code: String = sqlx::query_scalar("SELECT code FROM course_units WHERE id=$1 FOR UPDATE")
+        .bind(id).fetch_optional(&mut *tx).await?.ok_or(AppError::NotFound)?;
+    let found: HashSet<String> = sqlx::query_scalar(
+        "SELECT student_id FROM students WHERE student_id=ANY($1) AND deleted_at IS NULL",
+    )
+    .bind(&body.student_ids).fetch_all(&mut *tx).await?.into_iter().collect();
+    let unknown: Vec<&str> = body.student_ids.iter().filter(|s| !found.contains(*s)).map(String::as_str).collect();
+    if !unknown.is_empty() {
+        return Err(AppError::Validation(format!("Unknown students: {}", unknown.join(", "))));
+    }
+    sqlx::query(
+        r#"UPDATE students SET units=ARRAY_APPEND(COALESCE(units,'{}'),$1)
+           WHERE student_id=ANY($2) AND NOT ($1=ANY(COALESCE(units,'{}')))"#,
+    )
+    .bind(&code).bind(&body.student_ids).execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(Json(fetch_unit(&state.db, id).await?))
+}
+
+// DELETE /units/:id/enrolments/:student_id
+pub async fn unenrol(
+    _auth: AuthUser, State(state): State<AppState>, Path((id, student_id)): Path<(i32, String)>,
+) -> Result<StatusCode> {
+    let r = sqlx::query(
+        r#"UPDATE students s SET units=ARRAY_REMOVE(s.units,u.code)
+           FROM course_units u
+           WHERE u.id=$1 AND s.student_id=$2 AND u.code=ANY(s.units)"#,
+    )
+    .bind(id).bind(&student_id).execute(&state.db).await?;
+    if r.rows_affected() == 0 { return Err(AppError::NotFound); }
+    Ok(StatusCode::NO_CONTENT)
+}
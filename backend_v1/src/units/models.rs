@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::students::models::Student;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CourseUnit {
+    pub id:             i32,
+    /// Upper-cased letters and digits, e.g. "EMT2104"
+    pub code:           String,
+    pub name:           String,
+    pub department:     Option<String>,
+    pub lecturer_id:    Option<i32>,
+    pub lecturer_name:  Option<String>,
+    pub enrolled_count: i64,
+    pub kit_item_count: i64,
+    pub created_at:     DateTime<Utc>,
+}
+
+/// A catalogue item the unit's kit needs, per student
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UnitKitItem {
+    pub catalogue_item_id: i32,
+    pub name:              String,
+    pub quantity:          i32,
+    /// Only students enrolled in a unit listing the item this way may borrow it
+    pub enrolled_only:     bool,
+    /// Units of the item available across labs
+    pub available:         i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnitDetail {
+    #[serde(flatten)]
+    pub unit:     CourseUnit,
+    pub kit:      Vec<UnitKitItem>,
+    pub students: Vec<Student>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUnitRequest {
+    pub code:        String,
+    pub name:        String,
+    pub department:  Option<String>,
+    pub lecturer_id: Option<i32>,
+}
+
+/// A new code is carried over to every enrolled student's `units`.
+#[derive(Debug, Deserialize)]
+pub struct UpdateUnitRequest {
+    pub code:        Option<String>,
+    pub name:        Option<String>,
+    pub department:  Option<String>,
+    pub lecturer_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct UnitFilters {
+    pub lecturer_id: Option<i32>,
+    pub search:      Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KitLine {
+    pub catalogue_item_id: i32,
+    pub quantity:          i32,
+    #[serde(default)]
+    pub enrolled_only:     bool,
+}
+
+/// Replaces the unit's whole kit
+#[derive(Debug, Deserialize)]
+pub struct KitRequest {
+    pub items: Vec<KitLine>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnrolRequest {
+    pub student_ids: Vec<String>,
+}
+
+/// The unit a loan is issued for
+pub struct LoanUnit {
+    pub id:          i32,
+    pub lecturer_id: Option<i32>,
+}